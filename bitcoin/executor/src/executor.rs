@@ -1,23 +1,154 @@
-use crate::{orders::{Orderbook}, wallet::HTLCWallet};
+use crate::{
+    orders::{Orderbook, SwapAction},
+    persist::{PendingPersist, PendingPersistQueue},
+    skip::SkipList,
+    wallet::{ChangePolicy, HTLCWallet},
+};
 use anyhow::Result;
 use bitcoin::Network;
-use primitives::{htlc::BitcoinHTLC, types::{MatchedOrder}};
-use std::{time::Duration, str::FromStr};
+use primitives::{address::parse_bitcoin_address, htlc::BitcoinHTLC, types::{MatchedOrder}};
+use serde::Deserialize;
+use std::{collections::HashMap, time::Duration};
 use tokio::time;
 use moka::future::Cache;
+use tracing::Instrument;
+
+/// Which settlement wins while an HTLC is within the race grace of its expiry,
+/// when a redeem and a refund could both still confirm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RacePolicy {
+    /// Give an in-flight redeem until `grace` blocks past expiry before refunding
+    #[default]
+    PreferRedeem,
+    /// Stop redeeming `grace` blocks before expiry and refund as soon as it expires
+    PreferRefund,
+}
 
 pub struct OrderToActionMapper {
     wallet: HTLCWallet,
     network: Network,
+    redeem_confirmations: HashMap<String, u64>,
+    change_policy: ChangePolicy,
+    race_policy: RacePolicy,
+    race_grace_blocks: u64,
 }
 
 impl OrderToActionMapper {
     pub fn new(wallet: HTLCWallet, network: Network) -> Self {
-        Self { wallet, network }
+        Self {
+            wallet,
+            network,
+            redeem_confirmations: HashMap::new(),
+            change_policy: ChangePolicy::default(),
+            race_policy: RacePolicy::default(),
+            race_grace_blocks: 0,
+        }
+    }
+
+    /// Within `grace_blocks` either side of an HTLC's expiry, only the action
+    /// `policy` prefers is taken. 0 disables the check.
+    pub fn with_race_policy(mut self, policy: RacePolicy, grace_blocks: u64) -> Self {
+        self.race_policy = policy;
+        self.race_grace_blocks = grace_blocks;
+        self
+    }
+
+    /// What funding transactions do with change too small to be worth keeping
+    pub fn with_change_policy(mut self, change_policy: ChangePolicy) -> Self {
+        self.change_policy = change_policy;
+        self
+    }
+
+    /// Confirmations the HTLC being claimed must have before a redeem reveals
+    /// the secret, keyed by the chain that HTLC is on. Chains not listed
+    /// are redeemed as soon as the secret is known.
+    pub fn with_redeem_confirmations(mut self, confirmations: HashMap<String, u64>) -> Self {
+        self.redeem_confirmations = confirmations;
+        self
+    }
+
+    /// Whether the funding of `bitcoin_htlc` is deep enough to reveal the secret by
+    /// redeeming it. Lookup failures count as not deep enough.
+    async fn redeem_allowed(&self, order: &MatchedOrder, bitcoin_htlc: &BitcoinHTLC) -> bool {
+        let chain = order.destination_swap.chain.to_string();
+        let required = self.redeem_confirmations.get(&chain).copied().unwrap_or(0);
+        if required == 0 {
+            return true;
+        }
+
+        match self.wallet.htlc_funding_confirmations(bitcoin_htlc).await {
+            Ok(Some(confirmations)) if confirmations >= required => true,
+            Ok(Some(confirmations)) => {
+                tracing::info!(
+                    "Withholding redeem for order {:?}: funding has {} of {} confirmations on {}",
+                    order.create_order.create_id, confirmations, required, chain
+                );
+                false
+            }
+            Ok(None) => {
+                tracing::info!("Withholding redeem for order {:?}: HTLC is not funded", order.create_order.create_id);
+                false
+            }
+            Err(e) => {
+                tracing::info!("Withholding redeem for order {:?}: failed to check confirmations: {}", order.create_order.create_id, e);
+                false
+            }
+        }
+    }
+
+    /// Settles the redeem/refund race near expiry. Outside the grace window, or
+    /// while the funding is unconfirmed, `action` is kept. Inside it, a redeem
+    /// under `PreferRefund` waits for expiry and becomes a refund, and a refund
+    /// under `PreferRedeem` waits out the grace in case a redeem lands first.
+    async fn resolve_race(&self, order: &MatchedOrder, action: ActionType) -> Result<ActionType> {
+        if self.race_grace_blocks == 0 || !matches!(action, ActionType::Redeem | ActionType::Refund) {
+            return Ok(action);
+        }
+
+        let bitcoin_htlc = self.destination_htlc(order)?;
+        let blocks_to_expiry = match self.wallet.htlc_blocks_to_expiry(&bitcoin_htlc).await {
+            Ok(Some(blocks)) => blocks,
+            Ok(None) => return Ok(action),
+            Err(e) => return Err(anyhow::anyhow!("Failed to check HTLC expiry: {}", e)),
+        };
+
+        let grace = self.race_grace_blocks as i64;
+        if blocks_to_expiry > grace || blocks_to_expiry < -grace {
+            return Ok(action);
+        }
+
+        let resolved = match (self.race_policy, action) {
+            (RacePolicy::PreferRefund, ActionType::Redeem) if blocks_to_expiry <= 0 => ActionType::Refund,
+            (RacePolicy::PreferRefund, ActionType::Redeem) => ActionType::NoOp,
+            (RacePolicy::PreferRedeem, ActionType::Refund) if blocks_to_expiry > -grace => ActionType::NoOp,
+            (_, action) => action,
+        };
+        if resolved != action {
+            tracing::info!(
+                "{:?} for order {:?} resolved to {:?}: HTLC is {} blocks from expiry, within the {} block race grace ({:?})",
+                action, order.create_order.create_id, resolved, blocks_to_expiry, grace, self.race_policy
+            );
+        }
+        Ok(resolved)
+    }
+
+    /// The Bitcoin HTLC of the order's destination swap, as the executor funds it
+    fn destination_htlc(&self, order: &MatchedOrder) -> Result<BitcoinHTLC> {
+        let swap = &order.destination_swap;
+        Ok(BitcoinHTLC::new(
+            swap.secret_hash.clone(),
+            swap.initiator.clone(),
+            swap.redeemer.clone(),
+            swap.timelock as i64,
+            self.network,
+        )?
+        .with_hash_function(swap.hash_function))
     }
 
     pub async fn map(&self, order: &MatchedOrder) -> Result<HTLCAction> {
-        match self.determine_action(order) {
+        let action = self.resolve_race(order, self.determine_action(order)).await?;
+        match action {
             ActionType::Init => self.handle_init(order).await,
             ActionType::Redeem => self.handle_redeem(order).await,
             ActionType::Refund => self.handle_refund(order).await,
@@ -26,35 +157,44 @@ impl OrderToActionMapper {
     }
 
     fn determine_action(&self, order: &MatchedOrder) -> ActionType {
+        // Only orders settling on Bitcoin have a destination HTLC for this executor
+        if !order.direction().to_bitcoin() {
+            return ActionType::NoOp;
+        }
+
         if order.destination_swap.initiate_tx_hash.is_none() || order.destination_swap.initiate_tx_hash.as_ref().unwrap().is_empty() {
             ActionType::Init
-        } else if (order.source_swap.redeem_tx_hash.is_none() || order.source_swap.redeem_tx_hash.as_ref().unwrap().is_empty()) && !order.destination_swap.secret.as_ref().unwrap_or(&"".to_string()).is_empty() {
+        } else if is_unset(&order.source_swap.redeem_tx_hash) && is_unset(&order.destination_swap.refund_tx_hash) && Self::revealed_secret(order).is_some() {
             ActionType::Redeem
-        } else if (order.destination_swap.refund_tx_hash.is_none() || order.destination_swap.refund_tx_hash.as_ref().unwrap().is_empty()) && order.destination_swap.initiate_tx_hash.is_some() && order.destination_swap.initiate_block_number.as_ref().unwrap_or(&"".to_string()).is_empty() {
+        } else if is_unset(&order.destination_swap.refund_tx_hash) && is_unset(&order.source_swap.redeem_tx_hash) && order.destination_swap.initiate_tx_hash.is_some() && order.destination_swap.initiate_block_number.as_ref().unwrap_or(&"".to_string()).is_empty() {
             ActionType::Refund
         } else {
             ActionType::NoOp
         }
     }
 
+    /// The order's secret, from whichever leg it was revealed on. The watcher
+    /// records it on the swap whose redeem exposed it, which is the source or
+    /// the destination depending on who claimed first.
+    fn revealed_secret(order: &MatchedOrder) -> Option<&str> {
+        [&order.destination_swap.secret, &order.source_swap.secret]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .find(|secret| !secret.is_empty())
+    }
+
     async fn handle_init(&self, order: &MatchedOrder) -> Result<HTLCAction> {
-        println!("Handling INIT action for order: {:?}", order.create_order.create_id);
+        tracing::info!("Handling INIT action for order: {:?}", order.create_order.create_id);
         
-        // Create BitcoinHTLC from the order data
-        let bitcoin_htlc = BitcoinHTLC::new(
-            order.destination_swap.secret_hash.clone(),
-            order.destination_swap.initiator.clone(),
-            order.destination_swap.redeemer.clone(),
-            order.destination_swap.timelock as i64, // Default timelock - you might want to get this from order data
-            self.network,
-        )?;
+        let bitcoin_htlc = self.destination_htlc(order)?;
 
         // Get amount from create_order or use a default
         let amount = self.extract_amount_from_order(order).unwrap_or(50000);
 
-        match self.wallet.initiate_htlc(&bitcoin_htlc, amount).await {
+        match self.wallet.initiate_htlc(&bitcoin_htlc, amount, self.change_policy).await {
             Ok(tx) => {
-                println!("✅ Init transaction created: {}", tx.compute_txid());
+                tracing::info!("✅ Init transaction created: {}", tx.compute_txid());
                 Ok(HTLCAction::Init { 
                     order_id: order.create_order.create_id.clone().unwrap(),
                     transaction: tx,
@@ -62,54 +202,46 @@ impl OrderToActionMapper {
                 })
             }
             Err(e) => {
-                println!("❌ Failed to create init transaction: {}", e);
+                tracing::error!("❌ Failed to create init transaction: {}", e);
                 Ok(HTLCAction::NoOp)
             }
         }
     }
 
     async fn handle_redeem(&self, order: &MatchedOrder) -> Result<HTLCAction> {
-        println!("Handling REDEEM action for order: {:?}", order.create_order.create_id);
+        tracing::info!("Handling REDEEM action for order: {:?}", order.create_order.create_id);
         
-        // Create BitcoinHTLC from the order data
-        let bitcoin_htlc = BitcoinHTLC::new(
-            order.destination_swap.secret_hash.clone(),
-            order.destination_swap.initiator.clone(),
-            order.destination_swap.redeemer.clone(),
-            12, // Default timelock
-            self.network,
-        )?;
+        let bitcoin_htlc = self.destination_htlc(order)?;
+
+        // Redeeming publishes the secret, so wait until the funds it claims are secure
+        if !self.redeem_allowed(order, &bitcoin_htlc).await {
+            return Ok(HTLCAction::NoOp);
+        }
 
-        let secret = &order.destination_swap.secret;
+        let Some(secret) = Self::revealed_secret(order) else {
+            return Ok(HTLCAction::NoOp);
+        };
         let recipient_address = self.wallet.get_address();
 
-        match self.wallet.redeem_htlc(&bitcoin_htlc, &secret.clone().unwrap(), &recipient_address).await {
+        match self.wallet.redeem_htlc(&bitcoin_htlc, secret, &recipient_address).await {
             Ok(tx) => {
-                println!("✅ Redeem transaction created: {}", tx.compute_txid());
+                tracing::info!("✅ Redeem transaction created: {}", tx.compute_txid());
                 Ok(HTLCAction::Redeem { 
                     order_id: order.create_order.create_id.clone().unwrap(),
                     transaction: tx,
-                    secret: secret.clone().unwrap(),
                 })
             }
             Err(e) => {
-                println!("❌ Failed to create redeem transaction: {}", e);
+                tracing::error!("❌ Failed to create redeem transaction: {}", e);
                 Ok(HTLCAction::NoOp)
             }
         }
     }
 
     async fn handle_refund(&self, order: &MatchedOrder) -> Result<HTLCAction> {
-        println!("Handling REFUND action for order: {:?}", order.create_order.create_id);
+        tracing::info!("Handling REFUND action for order: {:?}", order.create_order.create_id);
         
-        // Create BitcoinHTLC from the order data
-        let bitcoin_htlc = BitcoinHTLC::new(
-            order.destination_swap.secret_hash.clone(),
-            order.destination_swap.initiator.clone(),
-            order.destination_swap.redeemer.clone(),
-            12, // Default timelock
-            self.network,
-        )?;
+        let bitcoin_htlc = self.destination_htlc(order)?;
 
         // Use bitcoin_optional_recipient if available, otherwise use wallet address
         let refund_address_str = if let Some(recipient) = &order.create_order.bitcoin_optional_recipient {
@@ -119,36 +251,37 @@ impl OrderToActionMapper {
         };
 
         // Parse the address string to bitcoin::Address with network checking
-        let refund_address = bitcoin::Address::from_str(&refund_address_str)
-            .map_err(|e| anyhow::anyhow!("Invalid refund address: {}", e))?
-            .require_network(self.network)
-            .map_err(|e| anyhow::anyhow!("Address network mismatch: {}", e))?;
+        let refund_address = parse_bitcoin_address(self.network, &refund_address_str)
+            .map_err(|e| anyhow::anyhow!("Invalid refund address: {}", e))?;
 
         match self.wallet.refund_htlc(&bitcoin_htlc, &refund_address).await {
             Ok(tx) => {
-                println!("✅ Refund transaction created: {}", tx.compute_txid());
+                tracing::info!("✅ Refund transaction created: {}", tx.compute_txid());
                 Ok(HTLCAction::Refund { 
                     order_id: order.create_order.create_id.clone().unwrap(),
                     transaction: tx,
                 })
             }
             Err(e) => {
-                println!("❌ Failed to create refund transaction: {}", e);
+                tracing::error!("❌ Failed to create refund transaction: {}", e);
                 Ok(HTLCAction::NoOp)
             }
         }
     }
 
+    /// What the destination HTLC locks: the swap's amount, which is already net
+    /// of the relayer spread, not the quoted `create_order.destination_amount`
     fn extract_amount_from_order(&self, order: &MatchedOrder) -> Option<u64> {
-        // Try to extract amount from destination_amount in create_order
-        if let Ok(amount) = order.create_order.destination_amount.parse::<u64>() {
-            return Some(amount);
-        }
-        None
+        order.destination_swap.amount.parse::<u64>().ok()
     }
 }
 
-#[derive(Debug)]
+/// Whether an optional tx hash is unset
+fn is_unset(tx_hash: &Option<String>) -> bool {
+    tx_hash.as_deref().is_none_or(str::is_empty)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ActionType {
     Init,
     Redeem,
@@ -165,7 +298,6 @@ pub enum HTLCAction {
     Redeem {
         order_id: String,
         transaction: bitcoin::Transaction,
-        secret: String,
     },
     Refund {
         order_id: String,
@@ -179,9 +311,16 @@ pub struct Executor {
     mapper: OrderToActionMapper,
     user_addresses: Vec<String>,
     executed_actions: Cache<String, bool>,
+    pending_persists: PendingPersistQueue,
+    skip_list: SkipList,
+    executor_id: String,
+    lease: Duration,
 }
 
 impl Executor {
+    pub const DEFAULT_ID: &'static str = "executor";
+    pub const DEFAULT_LEASE: Duration = Duration::from_secs(60);
+
     pub fn new(
         orderbook: Box<dyn Orderbook + Send + Sync>,
         mapper: OrderToActionMapper,
@@ -192,11 +331,39 @@ impl Executor {
             mapper,
             user_addresses,
             executed_actions: Cache::new(1000), // Cache up to 1000 executed actions
+            pending_persists: PendingPersistQueue::in_memory(),
+            skip_list: SkipList::in_memory(),
+            executor_id: Self::DEFAULT_ID.to_string(),
+            lease: Self::DEFAULT_LEASE,
         }
     }
 
+    /// Identifies this instance in logs and order leases when several executors run side by side
+    pub fn with_executor_id(mut self, executor_id: impl Into<String>) -> Self {
+        self.executor_id = executor_id.into();
+        self
+    }
+
+    /// How long an order stays leased to this executor after it last acted on it
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Queue for txids whose DB write failed after broadcast
+    pub fn with_pending_persist_queue(mut self, queue: PendingPersistQueue) -> Self {
+        self.pending_persists = queue;
+        self
+    }
+
+    /// Orders the executor must not act on
+    pub fn with_skip_list(mut self, skip_list: SkipList) -> Self {
+        self.skip_list = skip_list;
+        self
+    }
+
     pub async fn start_polling(&self) -> Result<()> {
-        println!("Starting executor polling every 5 seconds...");
+        tracing::info!(executor_id = %self.executor_id, "Starting executor polling every 5 seconds...");
         
         let mut interval = time::interval(Duration::from_secs(5));
         
@@ -204,69 +371,79 @@ impl Executor {
             interval.tick().await;
             
             if let Err(e) = self.process_pending_orders().await {
-                println!("Error processing pending orders: {}", e);
+                tracing::error!(executor_id = %self.executor_id, "Error processing pending orders: {}", e);
             }
         }
     }
 
+    /// Runs one polling cycle, with every log line in it tagged with the executor id
     async fn process_pending_orders(&self) -> Result<()> {
-        println!("Polling for pending orders...");
+        let span = tracing::info_span!("executor", executor_id = %self.executor_id);
+        self.poll_pending_orders().instrument(span).await
+    }
+
+    async fn poll_pending_orders(&self) -> Result<()> {
+        tracing::info!("Polling for pending orders...");
+
+        self.pending_persists.retry(self.orderbook.as_ref()).await;
         
         let orders = self.orderbook.get_pending_orders(self.user_addresses.clone()).await?;
         
         if orders.is_empty() {
-            println!("No pending orders found");
+            tracing::info!("No pending orders found");
             return Ok(());
         }
 
-        println!("Found {} pending orders", orders.len());
+        tracing::info!("Found {} pending orders", orders.len());
+
+        if let Err(e) = self.skip_list.reload() {
+            tracing::warn!("Failed to reload skip list, keeping the previous one: {}", e);
+        }
 
         for order in &orders {
             let order_id = order.create_order.create_id.clone().unwrap_or_default();
+            if self.skip_list.contains(&order_id) {
+                tracing::info!("Order {} is on the skip list, no action taken", order_id);
+                continue;
+            }
+            match self.orderbook.claim_order(&order_id, &self.executor_id, self.lease).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::info!("Order {} is leased to another executor, no action taken", order_id);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to lease order {}, no action taken: {}", order_id, e);
+                    continue;
+                }
+            }
             
             match self.mapper.map(order).await {
                 Ok(action) => {
-                    match action {
+                    let (action, order_id, transaction) = match action {
                         HTLCAction::Init { order_id, transaction, htlc } => {
-                            let action_key = format!("init_{}", order_id);
-                            if !self.is_action_executed(&action_key).await {
-                                println!("Processing INIT for order: {}", order_id);
-                                if let Ok(()) = self.broadcast_transaction(&transaction).await {
-                                    self.mark_action_executed(&action_key).await;
-                                }
-                            } else {
-                                println!("INIT action already executed for order: {}", order_id);
-                            }
-                        }
-                        HTLCAction::Redeem { order_id, transaction, secret } => {
-                            let action_key = format!("redeem_{}", order_id);
-                            if !self.is_action_executed(&action_key).await {
-                                println!("Processing REDEEM for order: {}", order_id);
-                                if let Ok(()) = self.broadcast_transaction(&transaction).await {
-                                    self.mark_action_executed(&action_key).await;
-                                }
-                            } else {
-                                println!("REDEEM action already executed for order: {}", order_id);
-                            }
-                        }
-                        HTLCAction::Refund { order_id, transaction } => {
-                            let action_key = format!("refund_{}", order_id);
-                            if !self.is_action_executed(&action_key).await {
-                                println!("Processing REFUND for order: {}", order_id);
-                                if let Ok(()) = self.broadcast_transaction(&transaction).await {
-                                    self.mark_action_executed(&action_key).await;
-                                }
-                            } else {
-                                println!("REFUND action already executed for order: {}", order_id);
+                            match htlc.address() {
+                                Ok(address) => tracing::info!("Funding HTLC {} for order: {}", address, order_id),
+                                Err(e) => tracing::warn!("Failed to derive HTLC address for order {}: {}", order_id, e),
                             }
+                            (SwapAction::Init, order_id, transaction)
                         }
+                        HTLCAction::Redeem { order_id, transaction } => (SwapAction::Redeem, order_id, transaction),
+                        HTLCAction::Refund { order_id, transaction } => (SwapAction::Refund, order_id, transaction),
                         HTLCAction::NoOp => {
-                            println!("No action needed for order: {:?}", order_id);
+                            tracing::info!("No action needed for order: {:?}", order_id);
+                            continue;
                         }
-                    }
+                    };
+                    // Redeems claim the source swap, inits and refunds act on the destination
+                    let swap_id = match action {
+                        SwapAction::Redeem => &order.source_swap.swap_id,
+                        SwapAction::Init | SwapAction::Refund => &order.destination_swap.swap_id,
+                    };
+                    self.execute(&order_id, swap_id, action, &transaction).await;
                 }
                 Err(e) => {
-                    println!("Error mapping order {:?}: {}", order_id, e);
+                    tracing::error!("Error mapping order {:?}: {}", order_id, e);
                 }
             }
         }
@@ -274,15 +451,57 @@ impl Executor {
         Ok(())
     }
 
-    async fn broadcast_transaction(&self, transaction: &bitcoin::Transaction) -> Result<()> {
+    /// Broadcasts `transaction` once per order and action, then stores its txid.
+    /// A failed write is queued for retry instead of letting the next cycle
+    /// rebroadcast.
+    async fn execute(&self, order_id: &str, swap_id: &str, action: SwapAction, transaction: &bitcoin::Transaction) {
+        let action_key = format!("{}_{}", action, order_id);
+        if self.is_action_executed(&action_key).await || self.pending_persists.contains(order_id, action) {
+            tracing::info!("{} action already executed for order: {}", action, order_id);
+            return;
+        }
+
+        // A redeem and a refund spend the same HTLC output; never broadcast the second
+        let competing = match action {
+            SwapAction::Redeem => Some(SwapAction::Refund),
+            SwapAction::Refund => Some(SwapAction::Redeem),
+            SwapAction::Init => None,
+        };
+        if let Some(competing) = competing {
+            let competing_key = format!("{}_{}", competing, order_id);
+            if self.is_action_executed(&competing_key).await || self.pending_persists.contains(order_id, competing) {
+                tracing::warn!("Not broadcasting {} for order {}: a {} was already broadcast", action, order_id, competing);
+                return;
+            }
+        }
+
+        tracing::info!("Processing {} for order: {}", action, order_id);
+        let Ok(txid) = self.broadcast_transaction(transaction).await else {
+            return;
+        };
+        self.mark_action_executed(&action_key).await;
+
+        if let Err(e) = self.orderbook.record_tx_hash(swap_id, action, &txid).await {
+            tracing::error!("❌ Failed to persist {} tx {} for swap {}, queued for retry: {}", action, txid, swap_id, e);
+            self.pending_persists.push(PendingPersist {
+                order_id: order_id.to_string(),
+                swap_id: swap_id.to_string(),
+                action,
+                txid,
+                attempts: 1,
+            });
+        }
+    }
+
+    async fn broadcast_transaction(&self, transaction: &bitcoin::Transaction) -> Result<String> {
         // Use the wallet's broadcast method
         match self.mapper.wallet.broadcast_transaction(transaction).await {
             Ok(tx_id) => {
-                println!("✅ Transaction broadcasted successfully: {}", tx_id);
-                Ok(())
+                tracing::info!("✅ Transaction broadcasted successfully: {}", tx_id);
+                Ok(tx_id)
             }
             Err(e) => {
-                println!("❌ Failed to broadcast transaction: {}", e);
+                tracing::error!("❌ Failed to broadcast transaction: {}", e);
                 Err(anyhow::anyhow!("Failed to broadcast transaction: {}", e))
             }
         }
@@ -296,3 +515,411 @@ impl Executor {
         self.executed_actions.insert(action_key.to_string(), true).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{test_order, InMemoryOrderbook};
+    use crate::skip::SkipList;
+    use primitives::types::Chain;
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+    use primitives::mock_server::{MockRequest, MockServer};
+
+    const PRIVATE_KEY: &str = "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1";
+
+    /// Orderbook whose txid writes fail while `fail_writes` is set
+    struct FlakyOrderbook {
+        fail_writes: Arc<AtomicBool>,
+        writes: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Orderbook for FlakyOrderbook {
+        async fn get_pending_orders(&self, _user_addresses: Vec<String>) -> Result<Vec<MatchedOrder>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_matched_order(&self, _create_id: &str) -> Result<MatchedOrder> {
+            Err(anyhow::anyhow!("Matched order not found"))
+        }
+
+        async fn record_tx_hash(&self, _swap_id: &str, _action: SwapAction, _txid: &str) -> Result<()> {
+            if self.fail_writes.load(Ordering::SeqCst) {
+                return Err(anyhow::anyhow!("connection reset"));
+            }
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn claim_order(&self, _create_id: &str, _executor_id: &str, _lease: Duration) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    /// Indexer that accepts every broadcast and counts them
+    async fn counting_indexer(broadcasts: Arc<AtomicUsize>) -> String {
+        let server = MockServer::start(move |_: &MockRequest| {
+            broadcasts.fetch_add(1, Ordering::SeqCst);
+            "txid"
+        })
+        .await;
+        server.url().to_string()
+    }
+
+    fn empty_tx() -> bitcoin::Transaction {
+        bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_after_broadcast_does_not_rebroadcast() {
+        let broadcasts = Arc::new(AtomicUsize::new(0));
+        let fail_writes = Arc::new(AtomicBool::new(true));
+        let writes = Arc::new(AtomicUsize::new(0));
+
+        let indexer_url = counting_indexer(broadcasts.clone()).await;
+        let wallet = HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url);
+        let orderbook = FlakyOrderbook {
+            fail_writes: fail_writes.clone(),
+            writes: writes.clone(),
+        };
+        let executor = Executor::new(Box::new(orderbook), OrderToActionMapper::new(wallet, Network::Regtest), vec![]);
+
+        let tx = empty_tx();
+        executor.execute("order-1", "swap-1", SwapAction::Init, &tx).await;
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 1);
+        assert!(executor.pending_persists.contains("order-1", SwapAction::Init));
+
+        // Still pending even if the idempotency cache has forgotten the action
+        executor.executed_actions.invalidate_all();
+        executor.pending_persists.retry(executor.orderbook.as_ref()).await;
+        executor.execute("order-1", "swap-1", SwapAction::Init, &tx).await;
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 1);
+
+        // Once the DB recovers the queued write lands without another broadcast
+        fail_writes.store(false, Ordering::SeqCst);
+        executor.pending_persists.retry(executor.orderbook.as_ref()).await;
+        assert!(!executor.pending_persists.contains("order-1", SwapAction::Init));
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_executor_records_txid_in_memory_backend() {
+        let broadcasts = Arc::new(AtomicUsize::new(0));
+        let indexer_url = counting_indexer(broadcasts.clone()).await;
+        let wallet = HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url);
+
+        let mut order = test_order("order-1", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        order.source_swap.initiate_tx_hash = Some("source-init".to_string());
+        let orderbook = InMemoryOrderbook::new(vec![order]);
+        let executor = Executor::new(
+            Box::new(orderbook.clone()),
+            OrderToActionMapper::new(wallet, Network::Regtest),
+            vec!["executor".to_string()],
+        );
+
+        executor.execute("order-1", "order-1-destination", SwapAction::Init, &empty_tx()).await;
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 1);
+        assert!(!executor.pending_persists.contains("order-1", SwapAction::Init));
+
+        let stored = orderbook.get_matched_order("order-1").await.unwrap();
+        assert_eq!(stored.destination_swap.initiate_tx_hash.as_deref(), Some("txid"));
+        assert_eq!(stored.source_swap.initiate_tx_hash.as_deref(), Some("source-init"));
+    }
+
+    #[tokio::test]
+    async fn test_executor_skips_orders_not_settling_on_bitcoin() {
+        let broadcasts = Arc::new(AtomicUsize::new(0));
+        let indexer_url = counting_indexer(broadcasts.clone()).await;
+        let wallet = HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url);
+
+        let mut order = test_order("order-1", Chain::ArbitrumSepolia, Chain::AvalancheTestnet);
+        order.source_swap.initiate_tx_hash = Some("source-init".to_string());
+        let orderbook = InMemoryOrderbook::new(vec![order]);
+        let executor = Executor::new(
+            Box::new(orderbook.clone()),
+            OrderToActionMapper::new(wallet, Network::Regtest),
+            vec!["executor".to_string()],
+        );
+
+        executor.process_pending_orders().await.unwrap();
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 0);
+        let stored = orderbook.get_matched_order("order-1").await.unwrap();
+        assert!(stored.destination_swap.initiate_tx_hash.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_skipped_order_is_not_acted_upon() {
+        // Counts every indexer request, so any attempt to build or send a
+        // transaction for an order shows up
+        let requests = Arc::new(AtomicUsize::new(0));
+        let indexer_url = counting_indexer(requests.clone()).await;
+        let executor_for = |orderbook: &InMemoryOrderbook, skip_list: SkipList| {
+            Executor::new(
+                Box::new(orderbook.clone()),
+                OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest),
+                vec!["executor".to_string()],
+            )
+            .with_skip_list(skip_list)
+        };
+
+        // Real keys, so the HTLC address derives and funding reaches the indexer
+        let due_init = |create_id: &str| {
+            let mut order = test_order(create_id, Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+            order.source_swap.initiate_tx_hash = Some("source-init".to_string());
+            order.destination_swap.initiator = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".to_string();
+            order.destination_swap.redeemer = "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5".to_string();
+            order
+        };
+        let skip_list = || {
+            let skip_list = SkipList::in_memory();
+            skip_list.insert("skipped");
+            skip_list
+        };
+
+        let orderbook = InMemoryOrderbook::new(vec![due_init("skipped")]);
+        executor_for(&orderbook, skip_list()).process_pending_orders().await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // Another order due the same action is still worked on
+        orderbook.insert(due_init("other")).await;
+        executor_for(&orderbook, skip_list()).process_pending_orders().await.unwrap();
+        assert!(requests.load(Ordering::SeqCst) > 0);
+    }
+
+    /// Log output captured from a test subscriber
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_action_logs_carry_executor_id() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let indexer_url = counting_indexer(Arc::new(AtomicUsize::new(0))).await;
+        let mut order = test_order("order-1", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        order.source_swap.initiate_tx_hash = Some("source-init".to_string());
+        order.destination_swap.initiator = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".to_string();
+        order.destination_swap.redeemer = "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5".to_string();
+        let executor = Executor::new(
+            Box::new(InMemoryOrderbook::new(vec![order])),
+            OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest),
+            vec!["executor".to_string()],
+        )
+        .with_executor_id("executor-eu-1");
+
+        executor.process_pending_orders().await.unwrap();
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let action_log = logs
+            .lines()
+            .find(|line| line.contains("Handling INIT action"))
+            .unwrap_or_else(|| panic!("no INIT action logged:\n{}", logs));
+        assert!(action_log.contains("executor_id=executor-eu-1"), "{}", action_log);
+    }
+
+    #[tokio::test]
+    async fn test_order_leased_to_another_executor_is_not_acted_upon() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let indexer_url = counting_indexer(requests.clone()).await;
+        let mut order = test_order("order-1", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        order.source_swap.initiate_tx_hash = Some("source-init".to_string());
+        order.destination_swap.initiator = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".to_string();
+        order.destination_swap.redeemer = "c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5".to_string();
+        let orderbook = InMemoryOrderbook::new(vec![order]);
+        let executor = |executor_id: &str| {
+            Executor::new(
+                Box::new(orderbook.clone()),
+                OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest),
+                vec!["executor".to_string()],
+            )
+            .with_executor_id(executor_id)
+        };
+
+        assert!(orderbook.claim_order("order-1", "executor-eu-1", Executor::DEFAULT_LEASE).await.unwrap());
+        executor("executor-us-1").process_pending_orders().await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+        assert_eq!(orderbook.claimed_by("order-1").await.as_deref(), Some("executor-eu-1"));
+
+        // The lease holder keeps working the order
+        executor("executor-eu-1").process_pending_orders().await.unwrap();
+        assert!(requests.load(Ordering::SeqCst) > 0);
+        assert_eq!(orderbook.claimed_by("order-1").await.as_deref(), Some("executor-eu-1"));
+    }
+
+    /// Indexer serving a tip height that can be moved and one HTLC UTXO mined at `funded_at`
+    async fn confirming_indexer(tip: Arc<AtomicUsize>, funded_at: u64) -> String {
+        let server = MockServer::start(move |request: &MockRequest| {
+            if request.path == "/blocks/tip/height" {
+                tip.load(Ordering::SeqCst).to_string()
+            } else {
+                format!(
+                    r#"[{{"txid":"{}","vout":0,"value":50000,"status":{{"confirmed":true,"block_height":{}}}}}]"#,
+                    "ab".repeat(32),
+                    funded_at
+                )
+            }
+        })
+        .await;
+        server.url().to_string()
+    }
+
+    const REDEEM_SECRET: &str = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
+
+    /// EVM to Bitcoin order whose destination HTLC is initiated, waiting for the secret
+    fn redeemable_order() -> MatchedOrder {
+        let mut order = test_order("order-1", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        order.destination_swap.initiate_tx_hash = Some("destination-init".to_string());
+        order.destination_swap.initiator = "460f2e8ff81fc4e0a8e6ce7796704e3829e3e3eedb8db9390bdc51f4f04cf0a6".to_string();
+        order.destination_swap.redeemer = "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce".to_string();
+        order.destination_swap.secret_hash = "731170d859f81a395a79e02cf3812e413b21793900e70ff77e48dfcf7ef6a4e6".to_string();
+        order
+    }
+
+    #[tokio::test]
+    async fn test_init_locks_amount_net_of_spread() {
+        let indexer_url = confirming_indexer(Arc::new(AtomicUsize::new(105)), 100).await;
+        let mapper = OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest);
+
+        let mut order = redeemable_order();
+        order.destination_swap.initiate_tx_hash = None;
+        order.create_order.destination_amount = "50000".to_string();
+        order.destination_swap.amount = "40000".to_string();
+
+        let HTLCAction::Init { transaction, .. } = mapper.map(&order).await.unwrap() else {
+            panic!("expected an init");
+        };
+        assert_eq!(transaction.output[0].value.to_sat(), 40_000);
+    }
+
+    #[tokio::test]
+    async fn test_redeem_uses_secret_from_either_leg() {
+        let indexer_url = confirming_indexer(Arc::new(AtomicUsize::new(105)), 100).await;
+        let mapper = OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest);
+
+        let waiting = redeemable_order();
+        assert!(!matches!(mapper.determine_action(&waiting), ActionType::Redeem));
+
+        let mut on_destination = redeemable_order();
+        on_destination.destination_swap.secret = Some(REDEEM_SECRET.to_string());
+        on_destination.source_swap.secret = Some(String::new());
+
+        let mut on_source = redeemable_order();
+        on_source.source_swap.secret = Some(REDEEM_SECRET.to_string());
+
+        for order in [on_destination, on_source] {
+            assert!(matches!(mapper.determine_action(&order), ActionType::Redeem));
+            match mapper.map(&order).await.unwrap() {
+                HTLCAction::Redeem { transaction, .. } => {
+                    assert_eq!(transaction.input[0].witness[1], hex::decode(REDEEM_SECRET).unwrap())
+                }
+                _ => panic!("expected a redeem"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_one_settlement_at_expiry_boundary() {
+        // Funded at 100 with a 12 block timelock, so the HTLC expires at 112
+        let tip = Arc::new(AtomicUsize::new(112));
+        let indexer_url = confirming_indexer(tip.clone(), 100).await;
+        let mapper = |policy| {
+            OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest)
+                .with_race_policy(policy, 2)
+        };
+        let mut revealed = redeemable_order();
+        revealed.destination_swap.secret = Some(REDEEM_SECRET.to_string());
+        let unrevealed = redeemable_order();
+
+        // Preferring the redeem: it goes ahead at expiry while the refund waits out the grace
+        let prefer_redeem = mapper(RacePolicy::PreferRedeem);
+        assert_eq!(prefer_redeem.resolve_race(&revealed, ActionType::Redeem).await.unwrap(), ActionType::Redeem);
+        assert_eq!(prefer_redeem.resolve_race(&unrevealed, ActionType::Refund).await.unwrap(), ActionType::NoOp);
+        tip.store(114, Ordering::SeqCst);
+        assert_eq!(prefer_redeem.resolve_race(&unrevealed, ActionType::Refund).await.unwrap(), ActionType::Refund);
+
+        // Preferring the refund: the redeem stands down before expiry and turns into the refund at it
+        let prefer_refund = mapper(RacePolicy::PreferRefund);
+        tip.store(111, Ordering::SeqCst);
+        assert_eq!(prefer_refund.resolve_race(&revealed, ActionType::Redeem).await.unwrap(), ActionType::NoOp);
+        tip.store(112, Ordering::SeqCst);
+        assert_eq!(prefer_refund.resolve_race(&revealed, ActionType::Redeem).await.unwrap(), ActionType::Refund);
+
+        // Far from expiry the policy doesn't interfere
+        tip.store(105, Ordering::SeqCst);
+        assert_eq!(prefer_refund.resolve_race(&revealed, ActionType::Redeem).await.unwrap(), ActionType::Redeem);
+
+        // Whatever was decided, a second spend of the HTLC is never broadcast
+        let broadcasts = Arc::new(AtomicUsize::new(0));
+        let indexer_url = counting_indexer(broadcasts.clone()).await;
+        let executor = Executor::new(
+            Box::new(InMemoryOrderbook::new(vec![revealed])),
+            OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest),
+            vec!["executor".to_string()],
+        );
+        executor.execute("order-1", "order-1-source", SwapAction::Redeem, &empty_tx()).await;
+        executor.execute("order-1", "order-1-destination", SwapAction::Refund, &empty_tx()).await;
+        assert_eq!(broadcasts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_refund_uses_the_swap_timelock() {
+        let indexer_url = confirming_indexer(Arc::new(AtomicUsize::new(105)), 100).await;
+        let mapper = OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest);
+
+        let mut order = redeemable_order();
+        order.destination_swap.timelock = 2;
+
+        let HTLCAction::Refund { transaction, .. } = mapper.map(&order).await.unwrap() else {
+            panic!("expected a refund");
+        };
+        assert_eq!(transaction.input[0].sequence, bitcoin::Sequence::from_height(2));
+    }
+
+    #[tokio::test]
+    async fn test_redeem_withheld_until_funding_confirms() {
+        let tip = Arc::new(AtomicUsize::new(101));
+        let indexer_url = confirming_indexer(tip.clone(), 100).await;
+        let mapper = OrderToActionMapper::new(HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url), Network::Regtest)
+            .with_redeem_confirmations(HashMap::from([("bitcoin_testnet".to_string(), 3)]));
+
+        let mut order = redeemable_order();
+        order.destination_swap.secret = Some(REDEEM_SECRET.to_string());
+
+        // Funded at 100 with the tip at 101: two confirmations
+        assert!(matches!(mapper.map(&order).await.unwrap(), HTLCAction::NoOp));
+
+        tip.store(102, Ordering::SeqCst);
+        assert!(matches!(mapper.map(&order).await.unwrap(), HTLCAction::Redeem { .. }));
+
+        // Chains without a requirement redeem immediately
+        tip.store(100, Ordering::SeqCst);
+        let mut other_chain = order.clone();
+        other_chain.destination_swap.chain = Chain::BitcoinSignet;
+        assert!(matches!(mapper.map(&other_chain).await.unwrap(), HTLCAction::Redeem { .. }));
+    }
+}
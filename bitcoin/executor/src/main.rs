@@ -2,15 +2,23 @@ mod wallet;
 mod orders;
 mod executor;
 mod settings;
+mod persist;
+mod skip;
+#[cfg(test)]
+mod memory;
+#[cfg(all(test, feature = "regtest-it"))]
+mod test_support;
 
 use crate::{
     executor::{Executor, OrderToActionMapper},
     orders::OrderbookProvider,
+    persist::PendingPersistQueue,
+    skip::SkipList,
     wallet::HTLCWallet,
     settings::Settings,
 };
 use bitcoin::{key::Secp256k1, secp256k1::{PublicKey, SecretKey}};
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -19,7 +27,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let network = settings.get_network()?;
     tracing_subscriber::fmt::init();
     
-    tracing::info!("Starting Bitcoin HTLC Executor...");
+    tracing::info!("Starting Bitcoin HTLC Executor {}...", settings.executor_id);
     tracing::info!("Indexer: {}", settings.bitcoin.indexer_url);
     tracing::info!("Network: {:?}", network);
 
@@ -38,13 +46,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let orderbook_box = Box::new(orderbook);
 
     // Initialize wallet
-    let wallet = HTLCWallet::new(&settings.wallet.private_key, network, &settings.bitcoin.indexer_url);
+    let mut wallet = HTLCWallet::new(&settings.wallet.private_key, network, settings.bitcoin.indexer_url.as_str())
+        .with_refund_grace_blocks(settings.wallet.refund_grace_blocks)
+        .with_refund_min_confirmations(settings.wallet.refund_min_confirmations)
+        .with_broadcast_indexers(settings.bitcoin.broadcast_indexer_urls.iter().map(ToString::to_string).collect())
+        .with_txid_precheck(settings.bitcoin.txid_precheck);
+    if let Some(max_inputs) = settings.wallet.max_inputs {
+        wallet = wallet.with_max_inputs(max_inputs);
+    }
+    if settings.wallet.op_return_tag {
+        wallet = wallet.with_tx_tag(&settings.executor_id);
+    }
     
     // Initialize mapper
-    let mapper = OrderToActionMapper::new(wallet, network);
+    let mapper = OrderToActionMapper::new(wallet, network)
+        .with_redeem_confirmations(settings.redeem_confirmations)
+        .with_change_policy(settings.wallet.change_policy)
+        .with_race_policy(settings.race.policy, settings.race.grace_blocks);
+
+    let pending_persists = match &settings.persist.pending_file {
+        Some(path) => PendingPersistQueue::open(path)?,
+        None => PendingPersistQueue::in_memory(),
+    }
+    .with_alert_after(settings.persist.alert_after_attempts);
+
+    let skip_list = match &settings.control.skip_file {
+        Some(path) => SkipList::open(path)?,
+        None => SkipList::in_memory(),
+    };
 
     // Initialize executor
-    let executor = Executor::new(orderbook_box, mapper, user_addresses);
+    let executor = Executor::new(orderbook_box, mapper, user_addresses)
+        .with_pending_persist_queue(pending_persists)
+        .with_skip_list(skip_list)
+        .with_executor_id(settings.executor_id)
+        .with_lease(Duration::from_secs(settings.lease_secs));
 
     // Start polling
     executor.start_polling().await?;
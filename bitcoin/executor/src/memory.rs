@@ -0,0 +1,296 @@
+use crate::orders::{Orderbook, SwapAction};
+use anyhow::{anyhow, Result};
+use mongodb::bson::DateTime;
+use primitives::types::{normalize_txid, Chain, CreateOrder, HashFunction, MatchedOrder, Swap};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+/// Pending orders returned per query, matching `OrderbookProvider`
+const PENDING_ORDERS_LIMIT: usize = 1000;
+
+/// `Orderbook` kept in memory for tests. Clones share the same orders, so a test
+/// can hand one to the executor and inspect the writes through another.
+/// Pending-order selection mirrors the MongoDB pipeline in `OrderbookProvider`.
+#[derive(Clone, Default)]
+pub struct InMemoryOrderbook {
+    orders: Arc<RwLock<Vec<MatchedOrder>>>,
+    leases: Arc<RwLock<HashMap<String, Lease>>>,
+}
+
+/// Executor holding an order and when its lease runs out
+struct Lease {
+    claimed_by: String,
+    claimed_until: Instant,
+}
+
+impl InMemoryOrderbook {
+    pub fn new(orders: Vec<MatchedOrder>) -> Self {
+        Self {
+            orders: Arc::new(RwLock::new(orders)),
+            leases: Arc::default(),
+        }
+    }
+
+    pub async fn insert(&self, order: MatchedOrder) {
+        self.orders.write().await.push(order);
+    }
+
+    /// Executor that last leased the order with `create_id`
+    pub async fn claimed_by(&self, create_id: &str) -> Option<String> {
+        self.leases.read().await.get(create_id).map(|lease| lease.claimed_by.clone())
+    }
+}
+
+fn is_empty(value: &Option<String>) -> bool {
+    value.as_deref().is_none_or(str::is_empty)
+}
+
+/// Whether the executor has an action to take on `order` for one of `user_addresses`
+fn is_pending(order: &MatchedOrder, user_addresses: &[String]) -> bool {
+    let source = &order.source_swap;
+    let destination = &order.destination_swap;
+
+    let is_party = [&source.initiator, &source.redeemer, &destination.initiator, &destination.redeemer]
+        .iter()
+        .any(|party| user_addresses.contains(party));
+    if !is_party {
+        return false;
+    }
+
+    // Source swap initiated but destination not initiated
+    let needs_init = !is_empty(&source.initiate_tx_hash)
+        && is_empty(&source.refund_tx_hash)
+        && is_empty(&destination.initiate_tx_hash);
+    // Destination has secret but source not redeemed
+    let needs_redeem = !is_empty(&destination.secret)
+        && is_empty(&source.redeem_tx_hash)
+        && is_empty(&source.refund_tx_hash);
+    // Destination initiated but not redeemed/refunded
+    let destination_open = !is_empty(&destination.initiate_tx_hash)
+        && is_empty(&destination.refund_tx_hash)
+        && is_empty(&destination.redeem_tx_hash);
+    // Source can be refunded
+    let source_refundable = is_empty(&source.refund_tx_hash)
+        && is_empty(&source.redeem_tx_hash)
+        && destination
+            .refund_block_number
+            .as_deref()
+            .and_then(|height| height.parse::<u64>().ok())
+            .is_some_and(|height| height > 0);
+
+    needs_init || needs_redeem || destination_open || source_refundable
+}
+
+fn tx_hash_mut(swap: &mut Swap, action: SwapAction) -> &mut Option<String> {
+    match action {
+        SwapAction::Init => &mut swap.initiate_tx_hash,
+        SwapAction::Redeem => &mut swap.redeem_tx_hash,
+        SwapAction::Refund => &mut swap.refund_tx_hash,
+    }
+}
+
+#[async_trait::async_trait]
+impl Orderbook for InMemoryOrderbook {
+    async fn get_pending_orders(&self, user_addresses: Vec<String>) -> Result<Vec<MatchedOrder>> {
+        let lowercase_addresses: Vec<String> = user_addresses.iter().map(|addr| addr.to_lowercase()).collect();
+
+        let mut pending: Vec<MatchedOrder> = self
+            .orders
+            .read()
+            .await
+            .iter()
+            .filter(|order| is_pending(order, &lowercase_addresses))
+            .cloned()
+            .collect();
+        pending.truncate(PENDING_ORDERS_LIMIT);
+        Ok(pending)
+    }
+
+    async fn get_matched_order(&self, create_id: &str) -> Result<MatchedOrder> {
+        self.orders
+            .read()
+            .await
+            .iter()
+            .find(|order| order.create_order.create_id.as_deref() == Some(create_id))
+            .cloned()
+            .ok_or_else(|| anyhow!("Matched order not found"))
+    }
+
+    async fn record_tx_hash(&self, swap_id: &str, action: SwapAction, txid: &str) -> Result<()> {
+        let mut orders = self.orders.write().await;
+        for order in orders.iter_mut() {
+            for swap in [&mut order.source_swap, &mut order.destination_swap] {
+                if swap.swap_id == swap_id {
+                    *tx_hash_mut(swap, action) = Some(normalize_txid(txid));
+                    return Ok(());
+                }
+            }
+        }
+        Err(anyhow!("No matched order found for swap {}", swap_id))
+    }
+
+    async fn claim_order(&self, create_id: &str, executor_id: &str, lease: Duration) -> Result<bool> {
+        let exists = self
+            .orders
+            .read()
+            .await
+            .iter()
+            .any(|order| order.create_order.create_id.as_deref() == Some(create_id));
+        if !exists {
+            return Ok(false);
+        }
+
+        let now = Instant::now();
+        let mut leases = self.leases.write().await;
+        if let Some(held) = leases.get(create_id)
+            && held.claimed_by != executor_id
+            && held.claimed_until > now
+        {
+            return Ok(false);
+        }
+        leases.insert(
+            create_id.to_string(),
+            Lease {
+                claimed_by: executor_id.to_string(),
+                claimed_until: now + lease,
+            },
+        );
+        Ok(true)
+    }
+}
+
+/// Builds a matched order between a user and the executor
+pub fn test_order(create_id: &str, source_chain: Chain, destination_chain: Chain) -> MatchedOrder {
+
+    let swap = |swap_id: &str, chain, initiator: &str, redeemer: &str| Swap {
+        _id: None,
+        created_at: DateTime::now(),
+        swap_id: swap_id.to_string(),
+        chain,
+        asset: "btc".to_string(),
+        htlc_address: String::new(),
+        token_address: String::new(),
+        initiator: initiator.to_string(),
+        redeemer: redeemer.to_string(),
+        filled_amount: "0".to_string(),
+        amount: "50000".to_string(),
+        timelock: 12,
+        secret_hash: "a201be6510790b5b1ebab36fc5e0ee5db382f1afb7850d1444e80952c58edcd8".to_string(),
+        hash_function: HashFunction::Sha256,
+        secret: None,
+        initiate_tx_hash: None,
+        redeem_tx_hash: None,
+        refund_tx_hash: None,
+        initiate_block_number: None,
+        redeem_block_number: None,
+        refund_block_number: None,
+        deposit_address: None,
+        has_deposit: false,
+        expired: false,
+    };
+
+    MatchedOrder {
+        _id: None,
+        created_at: DateTime::now(),
+        source_swap: swap(&format!("{}-source", create_id), source_chain, "user", "executor"),
+        destination_swap: swap(&format!("{}-destination", create_id), destination_chain, "executor", "user"),
+        create_order: CreateOrder {
+            _id: None,
+            from: String::new(),
+            to: String::new(),
+            source_amount: "50000".to_string(),
+            destination_amount: "50000".to_string(),
+            initiator_source_address: "user".to_string(),
+            initiator_destination_address: "user".to_string(),
+            secret_hash: "a201be6510790b5b1ebab36fc5e0ee5db382f1afb7850d1444e80952c58edcd8".to_string(),
+            nonce: "1".to_string(),
+            bitcoin_optional_recipient: None,
+            hash_function: HashFunction::Sha256,
+            create_id: Some(create_id.to_string()),
+        },
+        direction: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn executor() -> Vec<String> {
+        vec!["executor".to_string()]
+    }
+
+    #[tokio::test]
+    async fn test_only_actionable_orders_are_pending() {
+        let mut funded = test_order("funded", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        funded.source_swap.initiate_tx_hash = Some("init".to_string());
+        let unfunded = test_order("unfunded", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        let orderbook = InMemoryOrderbook::new(vec![funded]);
+        orderbook.insert(unfunded).await;
+
+        let pending = orderbook.get_pending_orders(executor()).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].create_order.create_id.as_deref(), Some("funded"));
+
+        assert!(orderbook.get_pending_orders(vec!["someone-else".to_string()]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_checksummed_address_matches_lowercase_party() {
+        // The orderbook stores parties lowercased, like the Mongo `$in` expects
+        let mut order = test_order("order", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        order.source_swap.initiate_tx_hash = Some("init".to_string());
+        order.source_swap.redeemer = "0xe62a2b235f7bb86c1122313153824d54e6137e77".to_string();
+        let orderbook = InMemoryOrderbook::new(vec![order]);
+
+        let pending = orderbook
+            .get_pending_orders(vec!["0xE62a2b235f7bB86C1122313153824D54E6137e77".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_init_clears_pending_init() {
+        let mut order = test_order("order", Chain::AvalancheTestnet, Chain::BitcoinTestnet);
+        order.source_swap.initiate_tx_hash = Some("init".to_string());
+        let orderbook = InMemoryOrderbook::new(vec![order]);
+
+        orderbook.record_tx_hash("order-destination", SwapAction::Init, "0xAB12").await.unwrap();
+        let stored = orderbook.get_matched_order("order").await.unwrap();
+        assert_eq!(stored.destination_swap.initiate_tx_hash.as_deref(), Some("ab12"));
+
+        // Still pending: the destination HTLC is now open awaiting redeem or refund
+        assert_eq!(orderbook.get_pending_orders(executor()).await.unwrap().len(), 1);
+        orderbook.record_tx_hash("order-destination", SwapAction::Refund, "refund").await.unwrap();
+        assert!(orderbook.get_pending_orders(executor()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_excludes_other_executors_until_it_expires() {
+        let orderbook = InMemoryOrderbook::new(vec![test_order("order", Chain::AvalancheTestnet, Chain::BitcoinTestnet)]);
+        let lease = Duration::from_secs(60);
+
+        assert!(orderbook.claim_order("order", "executor-a", lease).await.unwrap());
+        assert!(!orderbook.claim_order("order", "executor-b", lease).await.unwrap());
+        // The holder renews its own lease
+        assert!(orderbook.claim_order("order", "executor-a", lease).await.unwrap());
+        assert_eq!(orderbook.claimed_by("order").await.as_deref(), Some("executor-a"));
+
+        tokio::time::advance(lease).await;
+        assert!(orderbook.claim_order("order", "executor-b", lease).await.unwrap());
+        assert_eq!(orderbook.claimed_by("order").await.as_deref(), Some("executor-b"));
+
+        assert!(!orderbook.claim_order("missing", "executor-a", lease).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_swap_is_an_error() {
+        let orderbook = InMemoryOrderbook::default();
+        assert!(orderbook.record_tx_hash("missing", SwapAction::Init, "txid").await.is_err());
+        assert!(orderbook.get_matched_order("missing").await.is_err());
+    }
+}
@@ -1,13 +1,79 @@
 use mongodb::{
-    bson::{doc, Document},
+    bson::{doc, DateTime, Document},
     options::ClientOptions,
     Client, Collection, Database,
 };
-use primitives::types::MatchedOrder;
+use primitives::types::{normalize_txid, MatchedOrder};
 use anyhow::{anyhow, Result};
 use futures::stream::TryStreamExt;
+use std::{fmt, str::FromStr, time::Duration};
 
+/// Transaction the executor broadcasts for a swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAction {
+    Init,
+    Redeem,
+    Refund,
+}
+
+impl SwapAction {
+    /// Swap field holding the txid of this action
+    pub fn tx_hash_field(&self) -> &'static str {
+        match self {
+            SwapAction::Init => "initiate_tx_hash",
+            SwapAction::Redeem => "redeem_tx_hash",
+            SwapAction::Refund => "refund_tx_hash",
+        }
+    }
+}
+
+impl fmt::Display for SwapAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwapAction::Init => write!(f, "init"),
+            SwapAction::Redeem => write!(f, "redeem"),
+            SwapAction::Refund => write!(f, "refund"),
+        }
+    }
+}
+
+impl FromStr for SwapAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "init" => Ok(SwapAction::Init),
+            "redeem" => Ok(SwapAction::Redeem),
+            "refund" => Ok(SwapAction::Refund),
+            _ => Err(anyhow!("Unknown swap action: {}", s)),
+        }
+    }
+}
 
+/// Storage the executor reads orders from and writes txids back to.
+///
+/// `OrderbookProvider` backs it with MongoDB and `memory::InMemoryOrderbook` is
+/// used in tests. A SQL backend would keep one row per swap:
+///
+/// ```sql
+/// CREATE TABLE swaps (
+///     swap_id          TEXT PRIMARY KEY,
+///     create_id        TEXT NOT NULL,   -- order the swap belongs to
+///     side             TEXT NOT NULL,   -- 'source' | 'destination'
+///     initiator        TEXT NOT NULL,
+///     redeemer         TEXT NOT NULL,
+///     secret           TEXT,
+///     initiate_tx_hash TEXT,
+///     redeem_tx_hash   TEXT,
+///     refund_tx_hash   TEXT,
+///     ...
+/// );
+/// ```
+///
+/// with `get_pending_orders` joining both sides of an order on `create_id` and
+/// applying the same conditions as the MongoDB pipeline, and `record_tx_hash`
+/// being `UPDATE swaps SET <action>_tx_hash = $1 WHERE swap_id = $2`, failing
+/// when no row is updated.
 #[async_trait::async_trait]
 pub trait Orderbook {
     /// Get all pending orders on which COBI can perform some action on.
@@ -16,6 +82,14 @@ pub trait Orderbook {
     
     /// Get a specific matched order by create ID
     async fn get_matched_order(&self, create_id: &str) -> Result<MatchedOrder>;
+
+    /// Store the txid of a broadcast `action` on the swap with `swap_id`
+    async fn record_tx_hash(&self, swap_id: &str, action: SwapAction, txid: &str) -> Result<()>;
+
+    /// Lease the order with `create_id` to `executor_id` for `lease`, recorded as
+    /// `claimed_by`/`claimed_until` on the order. Returns false while another
+    /// executor holds an unexpired lease; the holder renews its own.
+    async fn claim_order(&self, create_id: &str, executor_id: &str, lease: Duration) -> Result<bool>;
 }
 
 pub struct OrderbookProvider {
@@ -44,6 +118,37 @@ impl OrderbookProvider {
 
 #[async_trait::async_trait]
 impl Orderbook for OrderbookProvider {
+    async fn record_tx_hash(&self, swap_id: &str, action: SwapAction, txid: &str) -> Result<()> {
+        // The swap is embedded as either side of the matched order
+        for side in ["source_swap", "destination_swap"] {
+            let mut filter = Document::new();
+            filter.insert(format!("{}.swap_id", side), swap_id);
+            let mut fields = Document::new();
+            fields.insert(format!("{}.{}", side, action.tx_hash_field()), normalize_txid(txid));
+            let result = self.matched_orders.update_one(filter, doc! { "$set": fields }).await?;
+            if result.matched_count > 0 {
+                return Ok(());
+            }
+        }
+        Err(anyhow!("No matched order found for swap {}", swap_id))
+    }
+
+    async fn claim_order(&self, create_id: &str, executor_id: &str, lease: Duration) -> Result<bool> {
+        let now = DateTime::now();
+        let claimed_until = DateTime::from_millis(now.timestamp_millis() + lease.as_millis() as i64);
+        let filter = doc! {
+            "create_order.create_id": create_id,
+            "$or": [
+                { "claimed_by": executor_id },
+                { "claimed_until": { "$lte": now } },
+                { "claimed_until": { "$exists": false } }
+            ]
+        };
+        let update = doc! { "$set": { "claimed_by": executor_id, "claimed_until": claimed_until } };
+        let result = self.matched_orders.update_one(filter, update).await?;
+        Ok(result.matched_count > 0)
+    }
+
     async fn get_matched_order(&self, create_id: &str) -> Result<MatchedOrder> {
         let pipeline = vec![
             doc! {
@@ -0,0 +1,176 @@
+use crate::orders::{Orderbook, SwapAction};
+use anyhow::{anyhow, Result};
+use std::{fs, path::PathBuf, str::FromStr, sync::Mutex};
+
+/// A broadcast whose txid has not been written to the orderbook yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingPersist {
+    pub order_id: String,
+    pub swap_id: String,
+    pub action: SwapAction,
+    pub txid: String,
+    pub attempts: u32,
+}
+
+impl PendingPersist {
+    fn to_line(&self) -> String {
+        format!("{} {} {} {} {}", self.action, self.order_id, self.swap_id, self.txid, self.attempts)
+    }
+
+    fn from_line(line: &str) -> Result<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 5 {
+            return Err(anyhow!("Malformed pending persist entry: {}", line));
+        }
+        Ok(Self {
+            action: SwapAction::from_str(parts[0])?,
+            order_id: parts[1].to_string(),
+            swap_id: parts[2].to_string(),
+            txid: parts[3].to_string(),
+            attempts: parts[4].parse()?,
+        })
+    }
+}
+
+/// Broadcast transactions whose DB write failed, retried every cycle until the
+/// txid is stored. While an entry is queued its action counts as executed, so
+/// the transaction is never rebroadcast. With a file the queue survives restarts.
+pub struct PendingPersistQueue {
+    entries: Mutex<Vec<PendingPersist>>,
+    path: Option<PathBuf>,
+    alert_after: u32,
+}
+
+impl PendingPersistQueue {
+    pub const DEFAULT_ALERT_AFTER: u32 = 5;
+
+    pub fn in_memory() -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+            path: None,
+            alert_after: Self::DEFAULT_ALERT_AFTER,
+        }
+    }
+
+    /// Opens a queue backed by `path`, loading any entries left by a previous run
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PendingPersist::from_line)
+                .collect::<Result<Vec<_>>>()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            entries: Mutex::new(entries),
+            path: Some(path),
+            alert_after: Self::DEFAULT_ALERT_AFTER,
+        })
+    }
+
+    /// Alert once an entry has failed this many write attempts
+    pub fn with_alert_after(mut self, attempts: u32) -> Self {
+        self.alert_after = attempts;
+        self
+    }
+
+    pub fn push(&self, entry: PendingPersist) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|e| !(e.order_id == entry.order_id && e.action == entry.action));
+        entries.push(entry);
+        self.save(&entries);
+    }
+
+    /// Whether `action` for `order_id` was broadcast and is awaiting its DB write
+    pub fn contains(&self, order_id: &str, action: SwapAction) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.order_id == order_id && e.action == action)
+    }
+
+    /// Retries every queued write, dropping entries that succeed
+    pub async fn retry(&self, orderbook: &(dyn Orderbook + Send + Sync)) {
+        let pending = self.entries.lock().unwrap().clone();
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut remaining = Vec::new();
+        for mut entry in pending.clone() {
+            match orderbook.record_tx_hash(&entry.swap_id, entry.action, &entry.txid).await {
+                Ok(()) => {
+                    tracing::info!("Persisted {} tx {} for swap {} after retry", entry.action, entry.txid, entry.swap_id);
+                }
+                Err(e) => {
+                    entry.attempts += 1;
+                    if entry.attempts >= self.alert_after {
+                        tracing::error!(
+                            "ALERT: {} tx {} for swap {} was broadcast but still not persisted after {} attempts: {}",
+                            entry.action, entry.txid, entry.swap_id, entry.attempts, e
+                        );
+                    } else {
+                        tracing::warn!("Failed to persist {} tx {} for swap {}: {}", entry.action, entry.txid, entry.swap_id, e);
+                    }
+                    remaining.push(entry);
+                }
+            }
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        // Keep anything pushed while the retries were running
+        entries.retain(|e| !pending.iter().any(|p| p.txid == e.txid && p.action == e.action));
+        entries.extend(remaining);
+        self.save(&entries);
+    }
+
+    fn save(&self, entries: &[PendingPersist]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let contents: String = entries.iter().map(|e| e.to_line() + "\n").collect();
+        if let Err(e) = fs::write(path, contents) {
+            tracing::error!("Failed to save pending persist queue to {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> PendingPersist {
+        PendingPersist {
+            order_id: "order-1".to_string(),
+            swap_id: "swap-1".to_string(),
+            action: SwapAction::Redeem,
+            txid: "ab".repeat(32),
+            attempts: 2,
+        }
+    }
+
+    #[test]
+    fn test_queue_file_survives_restart() {
+        let path = std::env::temp_dir().join(format!("pending-persist-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        PendingPersistQueue::open(&path).unwrap().push(entry());
+        let reopened = PendingPersistQueue::open(&path).unwrap();
+        assert!(reopened.contains("order-1", SwapAction::Redeem));
+        assert!(!reopened.contains("order-1", SwapAction::Refund));
+        assert_eq!(reopened.entries.lock().unwrap()[0], entry());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_malformed_entry_is_rejected() {
+        assert!(PendingPersist::from_line("redeem order-1 swap-1").is_err());
+        assert!(PendingPersist::from_line("claim order-1 swap-1 ab 0").is_err());
+    }
+}
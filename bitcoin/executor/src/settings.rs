@@ -1,4 +1,9 @@
+use crate::executor::{Executor, RacePolicy};
+use crate::persist::PendingPersistQueue;
+use crate::wallet::ChangePolicy;
+use primitives::urls::IndexerUrl;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
@@ -7,6 +12,24 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub bitcoin: BitcoinSettings,
     pub wallet: WalletSettings,
+    #[serde(default)]
+    pub persist: PersistSettings,
+    #[serde(default)]
+    pub control: ControlSettings,
+    #[serde(default)]
+    pub race: RaceSettings,
+    /// Confirmations the HTLC being claimed needs before a redeem reveals the
+    /// secret, keyed by the chain of the claimed swap (e.g. `bitcoin_testnet`)
+    #[serde(default)]
+    pub redeem_confirmations: HashMap<String, u64>,
+    /// Name of this instance, attached to its logs and, with
+    /// `wallet.op_return_tag`, to its funding transactions
+    #[serde(default = "default_executor_id")]
+    pub executor_id: String,
+    /// Seconds an order stays leased to this executor after it last acted on
+    /// it; other executors leave it alone meanwhile
+    #[serde(default = "default_lease_secs")]
+    pub lease_secs: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -18,13 +41,90 @@ pub struct DatabaseSettings {
 #[derive(Debug, Deserialize)]
 pub struct BitcoinSettings {
     pub network: String,
-    pub indexer_url: String,
+    pub indexer_url: IndexerUrl,
+    /// Additional indexers transactions are broadcast to alongside `indexer_url`
+    #[serde(default)]
+    pub broadcast_indexer_urls: Vec<IndexerUrl>,
+    /// Check whether a transaction is already known before broadcasting it
+    #[serde(default)]
+    pub txid_precheck: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct WalletSettings {
     pub private_key: String,
     pub user_addresses: Vec<String>,
+    /// Extra blocks to wait past HTLC expiry before refunding
+    #[serde(default)]
+    pub refund_grace_blocks: u64,
+    /// Confirmations the HTLC funding needs before it is refunded
+    #[serde(default = "default_refund_min_confirmations")]
+    pub refund_min_confirmations: u64,
+    /// Maximum UTXOs a funding transaction may spend
+    pub max_inputs: Option<usize>,
+    /// Change handling for funding transactions: "always", "donate_all" or
+    /// `{ never_below = <sats> }`
+    #[serde(default)]
+    pub change_policy: ChangePolicy,
+    /// Tag funding transactions with `executor_id` in an OP_RETURN output
+    #[serde(default)]
+    pub op_return_tag: bool,
+}
+
+/// Retry queue for txids that were broadcast but could not be written to the DB
+#[derive(Debug, Deserialize)]
+pub struct PersistSettings {
+    /// File the queue is kept in so pending writes survive a restart; in memory when unset
+    pub pending_file: Option<String>,
+    /// Failed write attempts before an entry is reported as an alert
+    #[serde(default = "default_alert_after_attempts")]
+    pub alert_after_attempts: u32,
+}
+
+fn default_executor_id() -> String {
+    Executor::DEFAULT_ID.to_string()
+}
+
+fn default_lease_secs() -> u64 {
+    Executor::DEFAULT_LEASE.as_secs()
+}
+
+fn default_refund_min_confirmations() -> u64 {
+    1
+}
+
+fn default_alert_after_attempts() -> u32 {
+    PendingPersistQueue::DEFAULT_ALERT_AFTER
+}
+
+impl Default for PersistSettings {
+    fn default() -> Self {
+        Self {
+            pending_file: None,
+            alert_after_attempts: default_alert_after_attempts(),
+        }
+    }
+}
+
+/// How the executor settles an HTLC that could be redeemed or refunded around
+/// its expiry. Within `grace_blocks` either side of the expiry height only the
+/// action `policy` prefers is taken, and a redeem and a refund are never both
+/// broadcast for one order.
+#[derive(Debug, Default, Deserialize)]
+pub struct RaceSettings {
+    /// Blocks either side of expiry the policy applies to; 0 disables it
+    #[serde(default)]
+    pub grace_blocks: u64,
+    /// "prefer_redeem" (default) or "prefer_refund"
+    #[serde(default)]
+    pub policy: RacePolicy,
+}
+
+/// Operator controls over which orders the executor acts on
+#[derive(Debug, Default, Deserialize)]
+pub struct ControlSettings {
+    /// File listing create ids the executor must not act on, re-read every cycle
+    pub skip_file: Option<String>,
 }
 
 impl Settings {
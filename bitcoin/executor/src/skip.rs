@@ -0,0 +1,86 @@
+use anyhow::Result;
+use std::{collections::HashSet, fs, path::PathBuf, sync::Mutex};
+
+/// Orders the executor must leave alone, e.g. while an incident on them is
+/// investigated. With a file, one create id per line (`#` starts a comment);
+/// the file is re-read every cycle so operators can edit it while running.
+pub struct SkipList {
+    order_ids: Mutex<HashSet<String>>,
+    path: Option<PathBuf>,
+}
+
+impl SkipList {
+    pub fn in_memory() -> Self {
+        Self {
+            order_ids: Mutex::new(HashSet::new()),
+            path: None,
+        }
+    }
+
+    /// Opens a skip list backed by `path`; a missing file is an empty list
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let list = Self {
+            order_ids: Mutex::new(HashSet::new()),
+            path: Some(path.into()),
+        };
+        list.reload()?;
+        Ok(list)
+    }
+
+    /// Re-reads the file, if any. On error the previous list is kept.
+    pub fn reload(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let order_ids = match fs::read_to_string(path) {
+            Ok(contents) => parse(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+        *self.order_ids.lock().unwrap() = order_ids;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn insert(&self, order_id: &str) {
+        self.order_ids.lock().unwrap().insert(order_id.to_string());
+    }
+
+    pub fn contains(&self, order_id: &str) -> bool {
+        self.order_ids.lock().unwrap().contains(order_id)
+    }
+}
+
+fn parse(contents: &str) -> HashSet<String> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_is_reloaded() {
+        let path = std::env::temp_dir().join(format!("skip-orders-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let list = SkipList::open(&path).unwrap();
+        assert!(!list.contains("order-1"));
+
+        fs::write(&path, "# stuck refund\norder-1  # INC-42\n\n").unwrap();
+        list.reload().unwrap();
+        assert!(list.contains("order-1"));
+        assert!(!list.contains("# stuck refund"));
+
+        fs::write(&path, "").unwrap();
+        list.reload().unwrap();
+        assert!(!list.contains("order-1"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+//! Regtest fixtures for transaction tests: a funded wallet, HTLCs with fresh
+//! secrets, broadcasting and mining. Needs a regtest bitcoind reachable through
+//! `bitcoin-cli -regtest` and an Esplora indexer following it, at
+//! `REGTEST_INDEXER_URL` (default `http://localhost:3000`). Built only with the
+//! `regtest-it` feature: `cargo test --features regtest-it`.
+
+use crate::wallet::{ChangePolicy, HTLCWallet};
+use bitcoin::{
+    hashes::{sha256, Hash},
+    key::Secp256k1,
+    secp256k1::{PublicKey, SecretKey},
+    Address, Network, Transaction, XOnlyPublicKey,
+};
+use primitives::{htlc::BitcoinHTLC, htlc_handler::UTXO, indexer::SimpleIndexer};
+use rand::RngCore;
+use std::{process::Command, str::FromStr, time::Duration};
+
+pub const PRIVATE_KEY: &str = "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1";
+
+/// Key that is not the wallet's, for the side of an HTLC the test doesn't sign for
+pub const OTHER_PUBKEY: &str = "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce";
+
+// Coinbase outputs mature after 100 blocks
+const COINBASE_MATURITY: u64 = 100;
+
+/// An HTLC built for a test together with the preimage that opens it
+pub struct TestHtlc {
+    pub htlc: BitcoinHTLC,
+    /// Hex preimage, as `redeem_htlc` takes it
+    pub secret: String,
+    pub address: Address,
+}
+
+pub struct RegtestContext {
+    pub wallet: HTLCWallet,
+    pub indexer: SimpleIndexer,
+    pub pubkey: XOnlyPublicKey,
+}
+
+impl RegtestContext {
+    /// Connects to the regtest node and indexer and makes sure the wallet has
+    /// mature coins to fund HTLCs with
+    pub async fn new() -> Self {
+        let indexer_url = std::env::var("REGTEST_INDEXER_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let secp = Secp256k1::new();
+        let private_key = SecretKey::from_str(PRIVATE_KEY).unwrap();
+        let context = Self {
+            wallet: HTLCWallet::new(PRIVATE_KEY, Network::Regtest, &indexer_url),
+            indexer: SimpleIndexer::new(&indexer_url).unwrap(),
+            pubkey: PublicKey::from_secret_key(&secp, &private_key).x_only_public_key().0,
+        };
+
+        let balance = context.indexer.get_address_balance(&context.wallet.get_address().to_string()).await.unwrap_or(0);
+        if balance == 0 {
+            context.mine(COINBASE_MATURITY + 1).await;
+        }
+        context
+    }
+
+    /// An HTLC with a fresh random secret between `initiator` and `redeemer` (x-only pubkeys)
+    pub fn htlc(&self, initiator: &str, redeemer: &str, timelock: u64) -> TestHtlc {
+        let mut secret = [0u8; 32];
+        rand::rng().fill_bytes(&mut secret);
+        let htlc = BitcoinHTLC::new(
+            hex::encode(sha256::Hash::hash(&secret).to_byte_array()),
+            initiator.to_string(),
+            redeemer.to_string(),
+            timelock as i64,
+            Network::Regtest,
+        )
+        .expect("Failed to create BitcoinHTLC");
+        let address = htlc.address().expect("Failed to derive HTLC address");
+        TestHtlc { htlc, secret: hex::encode(secret), address }
+    }
+
+    /// Funds `htlc` with `amount` sats from the wallet and broadcasts it, unconfirmed
+    pub async fn fund_htlc(&self, htlc: &TestHtlc, amount: u64) -> Transaction {
+        let tx = self
+            .wallet
+            .initiate_htlc(&htlc.htlc, amount, ChangePolicy::Always)
+            .await
+            .expect("Failed to build funding tx");
+        self.broadcast(&tx).await;
+        tx
+    }
+
+    /// Sends `sats` to `address` from the node's own wallet and returns the txid
+    #[allow(dead_code)]
+    pub fn fund_address(&self, address: &Address, sats: u64) -> String {
+        let btc = format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000);
+        bitcoin_cli(&["sendtoaddress", &address.to_string(), &btc])
+    }
+
+    /// Broadcasts `tx`, asserting the indexer accepted it under its own txid
+    pub async fn broadcast(&self, tx: &Transaction) -> String {
+        let txid = self.indexer.submit_tx(tx).await.expect("Node rejected transaction");
+        assert_eq!(txid, tx.compute_txid().to_string());
+        txid
+    }
+
+    /// Mines `blocks` blocks to the wallet and waits for the indexer, returning the new tip
+    pub async fn mine(&self, blocks: u64) -> u64 {
+        let tip = self.tip().await;
+        bitcoin_cli(&["generatetoaddress", &blocks.to_string(), &self.wallet.get_address().to_string()]);
+        self.wait_for_height(tip + blocks).await
+    }
+
+    pub async fn tip(&self) -> u64 {
+        self.indexer.get_current_block_height().await.expect("Indexer unavailable")
+    }
+
+    /// Waits for the indexer to reach `height` and returns the indexed tip
+    pub async fn wait_for_height(&self, height: u64) -> u64 {
+        for _ in 0..30 {
+            let tip = self.indexer.get_current_block_height().await.unwrap_or(0);
+            if tip >= height {
+                return tip;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        panic!("Indexer did not catch up with the regtest node");
+    }
+
+    /// Unspent outputs at `address`
+    pub async fn utxos(&self, address: &Address) -> Vec<UTXO> {
+        self.indexer.get_utxos(&address.to_string()).await.expect("Indexer unavailable")
+    }
+
+    /// The confirmed output `txid` created at `address`
+    pub async fn confirmed_output(&self, address: &Address, txid: &str) -> UTXO {
+        let output = self
+            .utxos(address)
+            .await
+            .into_iter()
+            .find(|utxo| utxo.txid == txid)
+            .unwrap_or_else(|| panic!("No output of {} at {}", txid, address));
+        assert!(output.status.confirmed, "Output of {} is not confirmed", txid);
+        output
+    }
+}
+
+/// Runs `bitcoin-cli -regtest` with `args` and returns its trimmed output
+fn bitcoin_cli(args: &[&str]) -> String {
+    let output = Command::new("bitcoin-cli")
+        .arg("-regtest")
+        .args(args)
+        .output()
+        .expect("Failed to run bitcoin-cli");
+    assert!(
+        output.status.success(),
+        "bitcoin-cli {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
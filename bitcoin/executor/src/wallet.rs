@@ -9,10 +9,36 @@ use bitcoin::{
     sighash::{EcdsaSighashType, SighashCache}, 
     taproot::LeafVersion, 
     transaction::Version, 
+    script::PushBytesBuf,
     Address, Amount, CompressedPublicKey, OutPoint, PrivateKey, Script, ScriptBuf, Sequence, TapLeafHash, TapSighashType, Txid, Witness
 };
 use std::{collections::HashMap, str::FromStr};
-use primitives::{htlc::BitcoinHTLC, indexer::SimpleIndexer};
+use primitives::{htlc::{BitcoinHTLC, Leaf}, htlc_handler::UTXO, indexer::SimpleIndexer};
+use serde::Deserialize;
+
+/// What a funding transaction does with the value left over after the HTLC
+/// output and the fee
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangePolicy {
+    /// Return change to the wallet unless it is dust
+    #[default]
+    Always,
+    /// Return change only when it is at least this many sats, otherwise add it to the fee
+    NeverBelow(u64),
+    /// Never create a change output; everything above the HTLC amount goes to the fee
+    DonateAll,
+}
+
+impl ChangePolicy {
+    fn keeps_change(&self, change: u64, dust_threshold: u64) -> bool {
+        match self {
+            ChangePolicy::Always => change >= dust_threshold,
+            ChangePolicy::NeverBelow(min) => change >= dust_threshold.max(*min),
+            ChangePolicy::DonateAll => false,
+        }
+    }
+}
 
 pub struct HTLCWallet {
     secp: Secp256k1<secp256k1::All>,
@@ -22,6 +48,10 @@ pub struct HTLCWallet {
     address: Address,
     utxos: HashMap<OutPoint, TxOut>,
     indexer: SimpleIndexer,
+    refund_grace_blocks: u64,
+    refund_min_confirmations: u64,
+    max_inputs: usize,
+    tx_tag: Option<Vec<u8>>,
 }
 
 impl HTLCWallet {
@@ -30,6 +60,15 @@ impl HTLCWallet {
     const P2TR_DUST_THRESHOLD: u64 = 330;   // P2TR dust threshold
     const DEFAULT_DUST_THRESHOLD: u64 = 546; // Default dust threshold
 
+    // Refunding within this many blocks past expiry races a late redeem
+    const REFUND_RACE_WARNING_BLOCKS: u64 = 2;
+
+    // Keeps funding transactions well within standardness size limits
+    const DEFAULT_MAX_INPUTS: usize = 50;
+
+    // Longest OP_RETURN tag written into funding transactions
+    pub const MAX_TX_TAG_LEN: usize = 16;
+
     pub fn new(private_key_str: &str, network: Network, indexer_url: &str) -> Self {
         let secp = Secp256k1::new();
         let sec_key = SecretKey::from_str(private_key_str).unwrap();
@@ -38,8 +77,8 @@ impl HTLCWallet {
         let priv_key = PrivateKey::from_slice(&priv_key_bytes, network).unwrap();
         let compressed = CompressedPublicKey::from_private_key(&secp, &priv_key);
         let address = Address::p2wpkh(&compressed.unwrap(), network);
-        println!("address: {:?}", address);
-        
+        tracing::info!("Wallet address: {}", address);
+
         Self {
             secp,
             network,
@@ -48,9 +87,54 @@ impl HTLCWallet {
             address,
             utxos: HashMap::new(),
             indexer: SimpleIndexer::new(indexer_url).unwrap(),
+            refund_grace_blocks: 0,
+            refund_min_confirmations: 1,
+            max_inputs: Self::DEFAULT_MAX_INPUTS,
+            tx_tag: None,
         }
     }
 
+    /// Add an OP_RETURN output carrying `tag` to every funding transaction so
+    /// the executor that broadcast it can be told from the chain. Tags longer
+    /// than `MAX_TX_TAG_LEN` bytes are truncated.
+    pub fn with_tx_tag(mut self, tag: &str) -> Self {
+        let mut tag = tag.as_bytes().to_vec();
+        tag.truncate(Self::MAX_TX_TAG_LEN);
+        self.tx_tag = Some(tag);
+        self
+    }
+
+    /// Cap the number of UTXOs a funding transaction may spend
+    pub fn with_max_inputs(mut self, max_inputs: usize) -> Self {
+        self.max_inputs = max_inputs;
+        self
+    }
+
+    /// Broadcast transactions to these indexers as well as the primary one
+    pub fn with_broadcast_indexers(mut self, urls: Vec<String>) -> Self {
+        self.indexer = self.indexer.with_broadcast_urls(urls);
+        self
+    }
+
+    /// Look each transaction up before broadcasting and skip the broadcast when
+    /// the indexer already has it
+    pub fn with_txid_precheck(mut self, enabled: bool) -> Self {
+        self.indexer = self.indexer.with_txid_precheck(enabled);
+        self
+    }
+
+    /// Require `blocks` extra blocks past the HTLC expiry before refunding
+    pub fn with_refund_grace_blocks(mut self, blocks: u64) -> Self {
+        self.refund_grace_blocks = blocks;
+        self
+    }
+
+    /// Confirmations the HTLC funding needs before a refund is built (at least 1)
+    pub fn with_refund_min_confirmations(mut self, confirmations: u64) -> Self {
+        self.refund_min_confirmations = confirmations.max(1);
+        self
+    }
+
     pub fn get_address(&self) -> Address {
         self.address.clone()
     }
@@ -83,6 +167,72 @@ impl HTLCWallet {
         value < Self::get_dust_threshold(script_pubkey)
     }
 
+    /// Check whether an HTLC expiring at `htlc_expiry_height` may be refunded at `current_height`
+    fn check_refund_height(current_height: u64, htlc_expiry_height: u64, grace_blocks: u64) -> Result<(), String> {
+        let refundable_height = htlc_expiry_height + grace_blocks;
+        if current_height < refundable_height {
+            return Err(format!(
+                "HTLC timelock not expired yet. Current height: {}, expires at: {}, grace: {} blocks, need to wait {} blocks",
+                current_height, htlc_expiry_height, grace_blocks, refundable_height - current_height
+            ));
+        }
+
+        if current_height < htlc_expiry_height + Self::REFUND_RACE_WARNING_BLOCKS {
+            tracing::warn!(
+                "Refunding {} blocks after expiry at height {}, a competing redeem may still confirm",
+                current_height - htlc_expiry_height, htlc_expiry_height
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The HTLC funding output a refund spends. It must have `min_confirmations`
+    /// so the expiry is counted from the height the funding actually confirmed at.
+    fn refund_funding_utxo(utxos: &[UTXO], current_height: u64, min_confirmations: u64) -> Result<&UTXO, String> {
+        let funding = utxos
+            .iter()
+            .max_by_key(|utxo| utxo.confirmations(current_height))
+            .ok_or("HTLC address is not funded")?;
+        let confirmations = funding.confirmations(current_height);
+        if confirmations < min_confirmations.max(1) {
+            return Err(format!(
+                "HTLC funding {} has {} of {} confirmations required to refund",
+                funding.txid, confirmations, min_confirmations
+            ));
+        }
+        Ok(funding)
+    }
+
+    /// BIP68 sequence satisfying the refund leaf's `OP_CSV` for a block-based relative timelock
+    fn refund_sequence(timelock: u64) -> Result<Sequence, String> {
+        let blocks = u16::try_from(timelock).map_err(|_| {
+            format!("Timelock {} exceeds the maximum relative lock of {} blocks", timelock, u16::MAX)
+        })?;
+        Ok(Sequence::from_height(blocks))
+    }
+
+    /// Restrict selected UTXOs to at most `max_inputs`, falling back to the largest
+    /// ones when the indexer's selection is too wide
+    fn limit_inputs(mut utxos: Vec<UTXO>, amount: u64, max_inputs: usize) -> Result<Vec<UTXO>, String> {
+        if utxos.len() <= max_inputs {
+            return Ok(utxos);
+        }
+
+        utxos.sort_by_key(|u| std::cmp::Reverse(u.value));
+        utxos.truncate(max_inputs);
+
+        let total: u64 = utxos.iter().map(|utxo| utxo.value).sum();
+        if total < amount {
+            return Err(format!(
+                "Funding {} sats needs more than {} inputs (largest {} UTXOs hold {} sats). Consolidate the wallet's UTXOs first",
+                amount, max_inputs, max_inputs, total
+            ));
+        }
+
+        Ok(utxos)
+    }
+
     /// Calculate a more accurate fee based on transaction size
     fn calculate_fee(inputs: usize, outputs: usize, fee_rate: u64) -> u64 {
         // Approximate transaction size in vbytes
@@ -97,15 +247,65 @@ impl HTLCWallet {
         fee_rate * total_vbytes as u64
     }
 
+    /// Outputs of a funding transaction spending `total_input` sats from `inputs`
+    /// inputs: the HTLC output, the OP_RETURN tag if configured, then a change
+    /// output if `change_policy` keeps it. The fee is estimated for the outputs
+    /// actually created.
+    fn funding_outputs(
+        &self,
+        htlc_output: TxOut,
+        total_input: u64,
+        inputs: usize,
+        fee_rate: u64,
+        change_policy: ChangePolicy,
+    ) -> Result<Vec<TxOut>, String> {
+        let amount = htlc_output.value.to_sat();
+        let mut outputs = vec![htlc_output];
+        if let Some(tag) = &self.tx_tag {
+            let tag = PushBytesBuf::try_from(tag.clone()).map_err(|e| format!("Invalid transaction tag: {}", e))?;
+            outputs.push(TxOut {
+                value: Amount::ZERO,
+                script_pubkey: ScriptBuf::new_op_return(tag),
+            });
+        }
+
+        let fee_without_change = Self::calculate_fee(inputs, outputs.len(), fee_rate);
+        if total_input < amount + fee_without_change {
+            return Err(format!(
+                "Insufficient funds: need {} sats, have {} sats",
+                amount + fee_without_change,
+                total_input
+            ));
+        }
+
+        let change_script = self.address.script_pubkey();
+        let change_amount = total_input.saturating_sub(amount + Self::calculate_fee(inputs, outputs.len() + 1, fee_rate));
+        if change_policy.keeps_change(change_amount, Self::get_dust_threshold(&change_script)) {
+            outputs.push(TxOut {
+                value: Amount::from_sat(change_amount),
+                script_pubkey: change_script,
+            });
+        } else if total_input > amount + fee_without_change {
+            tracing::info!(
+                "Adding {} sats of change to the fee ({:?})",
+                total_input - amount - fee_without_change,
+                change_policy
+            );
+        }
+        Ok(outputs)
+    }
+
     pub async fn initiate_htlc(
         &self,
         bitcoin_htlc: &BitcoinHTLC,
         amount: u64,
+        change_policy: ChangePolicy,
     ) -> Result<Transaction, Box<dyn std::error::Error>> {
         let htlc_address = bitcoin_htlc.address()?;
-        println!("address: {:?}", htlc_address);
+        tracing::info!("Funding HTLC address: {}", htlc_address);
         // Get UTXOs for funding from sender's address
         let utxos = self.indexer.get_utxos_for_amount(&self.address.to_string(), amount as i64).await?;
+        let utxos = Self::limit_inputs(utxos, amount, self.max_inputs)?;
         
         // Create inputs and track values
         let mut inputs: Vec<TxIn> = Vec::new();
@@ -124,42 +324,16 @@ impl HTLCWallet {
             input_values.push(utxo.value);
         }
 
-        // Calculate fee with better estimation
         let fee_rate = 10; // sat/vbyte - reduced for regtest
-        let estimated_fee = Self::calculate_fee(inputs.len(), 2, fee_rate);
         let total_input: u64 = input_values.iter().sum();
 
-        // Validate we have enough funds
-        if total_input < amount + estimated_fee {
-            return Err(format!(
-                "Insufficient funds: need {} sats, have {} sats", 
-                amount + estimated_fee, 
-                total_input
-            ).into());
-        }
-
         // Create HTLC output
         let htlc_output = TxOut {
             value: Amount::from_sat(amount),
             script_pubkey: htlc_address.script_pubkey(),
         };
 
-        let mut outputs = vec![htlc_output];
-
-        // Add change output if needed and above dust threshold
-        let change_amount = total_input - amount - estimated_fee;
-        if change_amount > 0 {
-            let change_script = self.address.script_pubkey();
-            if !Self::is_dust(change_amount, &change_script) {
-                outputs.push(TxOut {
-                    value: Amount::from_sat(change_amount),
-                    script_pubkey: change_script,
-                });
-            } else {
-                // Add dust to fee instead of creating dust output
-                println!("Warning: Change amount {} sats is dust, adding to fee", change_amount);
-            }
-        }
+        let outputs = self.funding_outputs(htlc_output, total_input, inputs.len(), fee_rate, change_policy)?;
 
         // Create unsigned transaction
         let mut unsigned_tx = Transaction {
@@ -205,6 +379,37 @@ impl HTLCWallet {
         Ok(sighash_cache.transaction().clone())
     }
 
+    /// Confirmations of the HTLC output `redeem_htlc` would spend, `None` while unfunded
+    pub async fn htlc_funding_confirmations(
+        &self,
+        bitcoin_htlc: &BitcoinHTLC,
+    ) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let htlc_address = bitcoin_htlc.address()?;
+        let utxos = self.indexer.get_utxos(&htlc_address.to_string()).await?;
+        let Some(utxo) = utxos.first() else {
+            return Ok(None);
+        };
+        let current_height = self.indexer.get_current_block_height().await?;
+        Ok(Some(utxo.confirmations(current_height)))
+    }
+
+    /// Blocks left until the HTLC's relative timelock expires, counted from its
+    /// confirmed funding; zero or negative once it has expired. `None` while
+    /// the HTLC has no confirmed funding.
+    pub async fn htlc_blocks_to_expiry(
+        &self,
+        bitcoin_htlc: &BitcoinHTLC,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+        let htlc_address = bitcoin_htlc.address()?;
+        let utxos = self.indexer.get_utxos(&htlc_address.to_string()).await?;
+        let Some(funding) = utxos.iter().filter(|utxo| utxo.status.confirmed).min_by_key(|utxo| utxo.status.block_height) else {
+            return Ok(None);
+        };
+        let current_height = self.indexer.get_current_block_height().await?;
+        let expiry_height = funding.status.block_height + bitcoin_htlc.timelock();
+        Ok(Some(expiry_height as i64 - current_height as i64))
+    }
+
     pub async fn redeem_htlc(
         &self,
         bitcoin_htlc: &BitcoinHTLC,
@@ -267,10 +472,7 @@ impl HTLCWallet {
         // [1] - secret/preimage
         // [2] - redeem script 
         // [3] - control block
-        
-        if witness_data.len() < 4 {
-            return Err("Invalid witness data from BitcoinHTLC::redeem".into());
-        }
+        Leaf::Redeem.validate_witness(&witness_data)?;
         
         // Create the correct prevouts for sighash calculation
         let prevouts = vec![TxOut {
@@ -282,7 +484,7 @@ impl HTLCWallet {
         let mut sighash_cache = SighashCache::new(&tx);
         
         // Create the leaf hash from the redeem script
-        let redeem_script = Script::from_bytes(&witness_data[2]);
+        let redeem_script = Script::from_bytes(&witness_data[Leaf::Redeem.script_index()]);
         let leaf_hash = TapLeafHash::from_script(redeem_script, LeafVersion::TapScript);
     
         // Generate the sighash message to sign using taproot script spend path
@@ -311,8 +513,8 @@ impl HTLCWallet {
         let mut witness = Witness::new();
         witness.push(&sig_serialized);        // Our signature
         witness.push(&witness_data[1]);       // Secret/preimage  
-        witness.push(&witness_data[2]);       // Redeem script
-        witness.push(&witness_data[3]);       // Control block
+        witness.push(&witness_data[Leaf::Redeem.script_index()]);        // Redeem script
+        witness.push(&witness_data[Leaf::Redeem.control_block_index()]); // Control block
     
         // Set the witness on the transaction
         tx.input[0].witness = witness;
@@ -328,27 +530,14 @@ impl HTLCWallet {
         
         // Get UTXOs for the HTLC address
         let utxos = self.indexer.get_utxos(&htlc_address.to_string()).await?;
-        if utxos.is_empty() {
-            return Err("HTLC address is not funded".into());
-        }
-        let utxo = &utxos[0];
-
-        if utxo.status.block_height == 0 {
-            return Err("UTXO is not confirmed".into());
-        }
 
         // Get current block height for timelock validation
         let current_height = self.indexer.get_current_block_height().await?;
+        let utxo = Self::refund_funding_utxo(&utxos, current_height, self.refund_min_confirmations)?;
         let utxo_block_height = utxo.status.block_height;
         let htlc_expiry_height = utxo_block_height + bitcoin_htlc.timelock();
         
-        if current_height < htlc_expiry_height {
-            let need_to_wait = htlc_expiry_height - current_height;
-            return Err(format!(
-                "HTLC timelock not expired yet. Current height: {}, expires at: {}, need to wait {} blocks", 
-                current_height, htlc_expiry_height, need_to_wait
-            ).into());
-        }
+        Self::check_refund_height(current_height, htlc_expiry_height, self.refund_grace_blocks)?;
         
         // Parse the UTXO transaction ID
         let txid = Txid::from_str(&utxo.txid)?;
@@ -370,33 +559,31 @@ impl HTLCWallet {
             ).into());
         }
         
-                 // Create the transaction structure 
-         // The timelock is enforced by the script itself (OP_CHECKLOCKTIMEVERIFY in refund_leaf)
-         // So we need to set the transaction locktime to satisfy the script's requirements
-         let mut tx = Transaction {
-             version: Version::TWO,
-             lock_time: LockTime::ZERO,
-             input: vec![TxIn {
-                 previous_output: OutPoint {
-                     txid,
-                     vout: utxo.vout,
-                 },
-                 script_sig: ScriptBuf::new(),
-                 sequence: Sequence(bitcoin_htlc.timelock() as u32), 
-                 witness: Witness::new(),
-             }],
-             output: vec![TxOut {
-                 value: Amount::from_sat(output_value),
-                 script_pubkey: refund_script,
-             }],
-         };
-    
+        // The refund leaf enforces a relative timelock with OP_CHECKSEQUENCEVERIFY, so the
+        // lock is encoded in the input's BIP68 sequence. BIP68 only applies to version 2
+        // transactions, and the absolute lock_time stays at zero.
+        let sequence = Self::refund_sequence(bitcoin_htlc.timelock())?;
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid,
+                    vout: utxo.vout,
+                },
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(output_value),
+                script_pubkey: refund_script,
+            }],
+        };
+
         // Get witness data from BitcoinHTLC
         let witness_data = bitcoin_htlc.refund()?;
-        
-        if witness_data.len() < 3 {
-            return Err("Invalid witness data from BitcoinHTLC::refund".into());
-        }
+        Leaf::Refund.validate_witness(&witness_data)?;
         
         // Create the correct prevouts for sighash calculation
         let prevouts = vec![TxOut {
@@ -408,7 +595,7 @@ impl HTLCWallet {
         let mut sighash_cache = SighashCache::new(&tx);
         
         // Create the leaf hash from the refund script
-        let refund_script_bytes = Script::from_bytes(&witness_data[1]);
+        let refund_script_bytes = Script::from_bytes(&witness_data[Leaf::Refund.script_index()]);
         let leaf_hash = TapLeafHash::from_script(refund_script_bytes, LeafVersion::TapScript);
     
         // Generate the sighash message to sign using taproot script spend path
@@ -436,8 +623,8 @@ impl HTLCWallet {
         // For refund: [signature, refund_script, control_block]
         let mut witness = Witness::new();
         witness.push(&sig_serialized);        // Our signature
-        witness.push(&witness_data[1]);       // refund script
-        witness.push(&witness_data[2]);       // Control block
+        witness.push(&witness_data[Leaf::Refund.script_index()]);        // refund script
+        witness.push(&witness_data[Leaf::Refund.control_block_index()]); // Control block
     
         // Set the witness on the transaction
         tx.input[0].witness = witness;
@@ -464,233 +651,267 @@ impl HTLCWallet {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bitcoin::{hex, secp256k1::SecretKey};
-    use ::hex::{decode, encode};
-    use rand::RngCore;
-    use std::str::FromStr;
+    #[cfg(feature = "regtest-it")]
+    use crate::test_support::{RegtestContext, OTHER_PUBKEY};
 
+    #[cfg(feature = "regtest-it")]
     #[tokio::test]
     async fn test_htlc_init_and_redeem() {
-        // Test configuration
-        let indexer_url = "http://localhost:3000";
-        let network = Network::Regtest;
-        let secp = Secp256k1::new();
-        
-        let private_key_hex = "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1";
-        let private_key_bytes = decode(private_key_hex).expect("Invalid private key");
-        let private_key = SecretKey::from_str(private_key_hex).expect("Invalid private key");
-        let priv_key = PrivateKey::from_slice(&private_key_bytes, network).unwrap();
-        let x_only_key = PublicKey::from_secret_key(&secp, &private_key).x_only_public_key().0;
-        let compressed = CompressedPublicKey::from_private_key(&secp, &priv_key).unwrap();
-        let address = Address::p2wpkh(&compressed, network);
-        println!("Address: {}", address);
-
-        let wallet = HTLCWallet::new(&private_key_hex, network, indexer_url);
-        
-        // Create a BitcoinHTLC instance
-        let secret_hash = "731170d859f81a395a79e02cf3812e413b21793900e70ff77e48dfcf7ef6a4e6";
-        let initiator_pubkey = x_only_key.to_string();
-        let redeemer_pubkey = x_only_key.to_string();
-        let timelock = 12;
-        
-        let bitcoin_htlc = BitcoinHTLC::new(
-            secret_hash.to_string(),
-            initiator_pubkey.to_string(),
-            redeemer_pubkey.to_string(),
-            timelock,
-            network,
-        ).expect("Failed to create BitcoinHTLC");
-        
-        // Test 1: Initiate HTLC with a higher amount to avoid dust issues
-        println!("Testing HTLC initiation...");
-        let amount = 50000; // Increased to 50k sats to avoid dust issues
-        
-        match wallet.initiate_htlc(&bitcoin_htlc, amount).await {
-            Ok(tx) => {
-                println!("✅ HTLC initiation transaction created successfully");
-                println!("Transaction ID: {}", tx.txid());
-                println!("Inputs: {}", tx.input.len());
-                println!("Outputs: {}", tx.output.len());
-                
-                // Display transaction details
-                for (i, output) in tx.output.iter().enumerate() {
-                    println!("Output {}: {} sats", i, output.value.to_sat());
-                }
-                
-                match wallet.indexer.submit_tx(&tx).await {
-                    Ok(tx_id) => {
-                        println!("✅ Transaction broadcasted successfully");
-                        println!("Broadcasted TX ID: {}", tx_id);
-                        
-                        // Test 2: Redeem HTLC
-                        println!("\nTesting HTLC redemption...");
-                        let secret = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
-                        let recipient_address = wallet.get_address();
-                        
-                        // Wait for confirmation
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                        
-                        match wallet.redeem_htlc(&bitcoin_htlc, secret, &recipient_address).await {
-                            Ok(redeem_tx) => {
-                                println!("✅ HTLC redemption transaction created successfully");
-                                println!("Redeem Transaction ID: {}", redeem_tx.txid());
-                                
-                                match wallet.indexer.submit_tx(&redeem_tx).await {
-                                    Ok(redeem_tx_id) => {
-                                        println!("✅ Redemption transaction broadcasted successfully");
-                                        println!("Redeem Broadcasted TX ID: {}", redeem_tx_id);
-                                    }
-                                    Err(e) => {
-                                        println!("❌ Failed to broadcast redemption transaction: {}", e);
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                println!("❌ Failed to create redemption transaction: {}", e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        println!("❌ Failed to broadcast initiation transaction: {}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                println!("❌ Failed to create initiation transaction: {}", e);
-            }
-        }
-        
-        // Test dust threshold calculations
-        println!("\nTesting dust threshold calculations...");
+        let ctx = RegtestContext::new().await;
+        let pubkey = ctx.pubkey.to_string();
+        let htlc = ctx.htlc(&pubkey, &pubkey, 12);
+
+        let init_tx = ctx.fund_htlc(&htlc, 50_000).await;
+        assert_eq!(init_tx.output[0].value.to_sat(), 50_000);
+        assert_eq!(init_tx.output[0].script_pubkey, htlc.address.script_pubkey());
+        ctx.mine(1).await;
+
+        let recipient = ctx.wallet.get_address();
+        let redeem_tx = ctx.wallet.redeem_htlc(&htlc.htlc, &htlc.secret, &recipient).await.expect("Failed to build redeem tx");
+        // The redeem publishes the preimage in its witness
+        let secret = hex::decode(&htlc.secret).unwrap();
+        assert!(redeem_tx.input[0].witness.iter().any(|item| item == secret.as_slice()));
+
+        let redeem_txid = ctx.broadcast(&redeem_tx).await;
+        ctx.mine(1).await;
+        assert!(ctx.utxos(&htlc.address).await.is_empty());
+        let redeemed = ctx.confirmed_output(&recipient, &redeem_txid).await;
+        assert_eq!(redeemed.value, redeem_tx.output[0].value.to_sat());
+    }
+
+    #[test]
+    fn test_dust_thresholds() {
+        let wallet = HTLCWallet::new(
+            "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1",
+            Network::Regtest,
+            "http://localhost:3000",
+        );
         let p2wpkh_script = wallet.address.script_pubkey();
-        let htlc_script = bitcoin_htlc.address().unwrap().script_pubkey();
-        
-        println!("P2WPKH dust threshold: {} sats", HTLCWallet::get_dust_threshold(&p2wpkh_script));
-        println!("HTLC script dust threshold: {} sats", HTLCWallet::get_dust_threshold(&htlc_script));
-        
-                 println!("Is 200 sats dust for P2WPKH? {}", HTLCWallet::is_dust(200, &p2wpkh_script));
-         println!("Is 1000 sats dust for P2WPKH? {}", HTLCWallet::is_dust(1000, &p2wpkh_script));
-     }
+        assert_eq!(HTLCWallet::get_dust_threshold(&p2wpkh_script), 294);
+        assert!(HTLCWallet::is_dust(200, &p2wpkh_script));
+        assert!(!HTLCWallet::is_dust(1000, &p2wpkh_script));
+    }
 
+     #[cfg(feature = "regtest-it")]
      #[tokio::test]
      async fn test_htlc_init_and_refund() {
-         // Test configuration
-         let indexer_url = "http://localhost:3000";
-         let network = Network::Regtest;
-         let secp = Secp256k1::new();
-         
-         let private_key_hex = "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1";
-         let private_key_bytes = decode(private_key_hex).expect("Invalid private key");
-         let private_key = SecretKey::from_str(private_key_hex).expect("Invalid private key");
-         let priv_key = PrivateKey::from_slice(&private_key_bytes, network).unwrap();
-         let x_only_key = PublicKey::from_secret_key(&secp, &private_key).x_only_public_key().0;
-         let compressed = CompressedPublicKey::from_private_key(&secp, &priv_key).unwrap();
-         let address = Address::p2wpkh(&compressed, network);
-         println!("Address: {}", address);
-
-         let wallet = HTLCWallet::new(&private_key_hex, network, indexer_url);
-         
-         let mut random_bytes = [0u8; 32];
-         rand::rng().fill_bytes(&mut random_bytes);
-         let secret = encode(random_bytes);
-         println!("Secret: {}", secret);
-         let secret_hash = sha256::Hash::hash(secret.as_bytes()).to_byte_array();
-         println!("Secret hash: {}", encode(secret_hash));
-
-         // Create a BitcoinHTLC instance with timelock = 2
-         let secret_hash = encode(secret_hash);
-         let initiator_pubkey = x_only_key.to_string();
-         let redeemer_pubkey = "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce".to_string();
-         let timelock = 2; // Short timelock for testing
-         
-         let bitcoin_htlc = BitcoinHTLC::new(
-             secret_hash.to_string(),
-             initiator_pubkey.to_string(),
-             redeemer_pubkey.to_string(),
-             timelock,
-             network,
-         ).expect("Failed to create BitcoinHTLC");
-         
-         // Test 1: Initiate HTLC
-         println!("Testing HTLC initiation for refund test...");
-         let amount = 30020; // 30k sats
-         
-         match wallet.initiate_htlc(&bitcoin_htlc, amount).await {
-             Ok(tx) => {
-                 println!("✅ HTLC initiation transaction created successfully");
-                 println!("Transaction ID: {}", tx.compute_txid());
-                 println!("Inputs: {}", tx.input.len());
-                 println!("Outputs: {}", tx.output.len());
-                 
-                 // Display transaction details
-                 for (i, output) in tx.output.iter().enumerate() {
-                     println!("Output {}: {} sats", i, output.value.to_sat());
-                 }
-                 
-                 match wallet.indexer.submit_tx(&tx).await {
-                     Ok(tx_id) => {
-                         println!("✅ Transaction broadcasted successfully");
-                         println!("Broadcasted TX ID: {}", tx_id);
-                         
-                         // Test 2: Wait for timelock and then refund
-                         println!("\nTesting HTLC refund after timelock...");
-                         let refund_address = wallet.get_address();
-                         
-                         // Wait for timelock to expire (2 blocks + some buffer)
-                         println!("Waiting for timelock to expire ({} blocks)...", timelock);
-                         tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-                         
-                         // Get current block height
-                         match wallet.indexer.get_current_block_height().await {
-                             Ok(current_height) => {
-                                 println!("Current block height: {}", current_height);
-                                 
-                                 match wallet.refund_htlc(&bitcoin_htlc, &refund_address).await {
-                                     Ok(refund_tx) => {
-                                         println!("✅ HTLC refund transaction created successfully");
-                                         println!("Refund Transaction ID: {}", refund_tx.compute_txid());
-                                         
-                                         match wallet.indexer.submit_tx(&refund_tx).await {
-                                             Ok(refund_tx_id) => {
-                                                 println!("✅ Refund transaction broadcasted successfully");
-                                                 println!("Refund Broadcasted TX ID: {}", refund_tx_id);
-                                             }
-                                             Err(e) => {
-                                                 println!("❌ Failed to broadcast refund transaction: {}", e);
-                                                 println!("This might be expected if timelock hasn't expired yet");
-                                             }
-                                         }
-                                     }
-                                     Err(e) => {
-                                         println!("❌ Failed to create refund transaction: {}", e);
-                                         println!("This might be expected if timelock hasn't expired yet");
-                                     }
-                                 }
-                             }
-                             Err(e) => {
-                                 println!("❌ Failed to get current block height: {}", e);
-                             }
-                         }
-                     }
-                     Err(e) => {
-                         println!("❌ Failed to broadcast initiation transaction: {}", e);
-                     }
-                 }
-             }
-             Err(e) => {
-                 println!("❌ Failed to create initiation transaction: {}", e);
-             }
-         }
-         
-         // Test 3: Get HTLC address
-         println!("\nTesting HTLC address generation for refund test...");
-         match bitcoin_htlc.address() {
-             Ok(htlc_addr) => {
-                 println!("✅ HTLC address generated: {}", htlc_addr);
-             }
-             Err(e) => {
-                 println!("❌ Failed to generate HTLC address: {}", e);
-             }
+         let ctx = RegtestContext::new().await;
+         let timelock = 2;
+         let htlc = ctx.htlc(&ctx.pubkey.to_string(), OTHER_PUBKEY, timelock);
+
+         ctx.fund_htlc(&htlc, 30_020).await;
+         // Unconfirmed funding can't be refunded
+         assert!(ctx.wallet.refund_htlc(&htlc.htlc, &ctx.wallet.get_address()).await.is_err());
+
+         ctx.mine(1 + timelock).await;
+         let refund_address = ctx.wallet.get_address();
+         let refund_tx = ctx.wallet.refund_htlc(&htlc.htlc, &refund_address).await.expect("Failed to build refund tx");
+         let refund_txid = ctx.broadcast(&refund_tx).await;
+         ctx.mine(1).await;
+
+         assert!(ctx.utxos(&htlc.address).await.is_empty());
+         let refunded = ctx.confirmed_output(&refund_address, &refund_txid).await;
+         assert_eq!(refunded.value, refund_tx.output[0].value.to_sat());
+     }
+
+     fn utxo(value: u64) -> UTXO {
+         UTXO {
+             txid: "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
+             vout: 0,
+             status: primitives::htlc_handler::Status {
+                 confirmed: true,
+                 block_height: 100,
+                 block_hash: String::new(),
+                 block_time: 0,
+             },
+             value,
          }
      }
+
+     #[test]
+     fn test_funding_within_max_inputs_is_kept() {
+         let utxos = vec![utxo(1000), utxo(2000)];
+         let selected = HTLCWallet::limit_inputs(utxos, 2500, 2).unwrap();
+         assert_eq!(selected.len(), 2);
+     }
+
+     #[test]
+     fn test_funding_over_max_inputs_uses_largest_utxos() {
+         let utxos = vec![utxo(100), utxo(5000), utxo(200), utxo(4000)];
+         let selected = HTLCWallet::limit_inputs(utxos, 8000, 2).unwrap();
+         assert_eq!(selected.iter().map(|u| u.value).collect::<Vec<_>>(), vec![5000, 4000]);
+     }
+
+     #[test]
+     fn test_funding_requiring_more_than_max_inputs_is_rejected() {
+         let utxos = vec![utxo(1000); 5];
+         let err = HTLCWallet::limit_inputs(utxos, 4500, 3).unwrap_err();
+         assert!(err.contains("more than 3 inputs"));
+         assert!(err.contains("Consolidate"));
+     }
+
+     fn funding_outputs(total_input: u64, change_policy: ChangePolicy) -> Result<Vec<TxOut>, String> {
+         let wallet = HTLCWallet::new(
+             "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1",
+             Network::Regtest,
+             "http://localhost:3000",
+         );
+         let htlc_output = TxOut {
+             value: Amount::from_sat(30_000),
+             script_pubkey: wallet.address.script_pubkey(),
+         };
+         wallet.funding_outputs(htlc_output, total_input, 1, 10, change_policy)
+     }
+
+     #[test]
+     fn test_change_policy_output_counts() {
+         // 1 input and 2 outputs cost 1480 sats at 10 sat/vB, leaving 2000 sats of change
+         let total_input = 30_000 + 1_480 + 2_000;
+         let outputs = funding_outputs(total_input, ChangePolicy::Always).unwrap();
+         assert_eq!(outputs.len(), 2);
+         assert_eq!(outputs[1].value.to_sat(), 2_000);
+         assert_eq!(funding_outputs(total_input, ChangePolicy::NeverBelow(2_000)).unwrap().len(), 2);
+         assert_eq!(funding_outputs(total_input, ChangePolicy::NeverBelow(2_001)).unwrap().len(), 1);
+         assert_eq!(funding_outputs(total_input, ChangePolicy::DonateAll).unwrap().len(), 1);
+
+         // Dust change is donated even under Always
+         assert_eq!(funding_outputs(30_000 + 1_480 + 100, ChangePolicy::Always).unwrap().len(), 1);
+     }
+
+     #[test]
+     fn test_change_free_funding_only_needs_the_single_output_fee() {
+         // 1 input and 1 output cost 1130 sats at 10 sat/vB
+         assert_eq!(funding_outputs(31_130, ChangePolicy::DonateAll).unwrap().len(), 1);
+         assert_eq!(funding_outputs(31_130, ChangePolicy::Always).unwrap().len(), 1);
+         assert!(funding_outputs(31_129, ChangePolicy::DonateAll).is_err());
+     }
+
+     #[test]
+     fn test_tagged_funding_carries_op_return() {
+         let wallet = HTLCWallet::new(
+             "8459644d232bed482bccf5131c371c65f39c12efa5e7e5e7b162016378ae26d1",
+             Network::Regtest,
+             "http://localhost:3000",
+         )
+         .with_tx_tag("executor-eu-west-1-primary");
+         let htlc_output = TxOut {
+             value: Amount::from_sat(30_000),
+             script_pubkey: wallet.address.script_pubkey(),
+         };
+
+         // 1 input and 3 outputs cost 1830 sats at 10 sat/vB, leaving 2000 sats of change
+         let outputs = wallet.funding_outputs(htlc_output, 30_000 + 1_830 + 2_000, 1, 10, ChangePolicy::Always).unwrap();
+         assert_eq!(outputs.len(), 3);
+         assert!(outputs[1].script_pubkey.is_op_return());
+         assert_eq!(outputs[1].value, Amount::ZERO);
+         assert_eq!(&outputs[1].script_pubkey.as_bytes()[2..], b"executor-eu-west");
+         assert_eq!(outputs[2].value.to_sat(), 2_000);
+     }
+
+     fn funding_utxo(confirmed: bool, block_height: u64) -> UTXO {
+         let mut funding = utxo(30_000);
+         funding.status.confirmed = confirmed;
+         funding.status.block_height = block_height;
+         funding
+     }
+
+     #[test]
+     fn test_refund_refused_on_under_confirmed_funding() {
+         let unconfirmed = [funding_utxo(false, 0)];
+         assert!(HTLCWallet::refund_funding_utxo(&unconfirmed, 110, 1).is_err());
+
+         // Confirmed at 108: 3 confirmations at tip 110
+         let confirmed = [funding_utxo(true, 108)];
+         let err = HTLCWallet::refund_funding_utxo(&confirmed, 110, 6).unwrap_err();
+         assert!(err.contains("3 of 6"));
+         assert_eq!(HTLCWallet::refund_funding_utxo(&confirmed, 110, 3).unwrap().status.block_height, 108);
+
+         assert!(HTLCWallet::refund_funding_utxo(&[], 110, 1).is_err());
+     }
+
+     #[test]
+     fn test_refund_expiry_counts_from_confirmed_funding() {
+         // An unconfirmed output listed first must not stand in for the funding
+         let utxos = [funding_utxo(false, 0), funding_utxo(true, 100)];
+         let funding = HTLCWallet::refund_funding_utxo(&utxos, 105, 1).unwrap();
+         assert_eq!(funding.status.block_height, 100);
+     }
+
+     #[test]
+     fn test_refund_before_expiry_is_rejected() {
+         assert!(HTLCWallet::check_refund_height(99, 100, 0).is_err());
+         assert!(HTLCWallet::check_refund_height(99, 100, 3).is_err());
+     }
+
+     #[test]
+     fn test_refund_at_expiry_respects_grace() {
+         assert!(HTLCWallet::check_refund_height(100, 100, 0).is_ok());
+         assert!(HTLCWallet::check_refund_height(100, 100, 3).is_err());
+     }
+
+     #[test]
+     fn test_refund_at_expiry_plus_grace() {
+         assert!(HTLCWallet::check_refund_height(103, 100, 3).is_ok());
+         assert!(HTLCWallet::check_refund_height(102, 100, 3).is_err());
+     }
+
+     #[test]
+     fn test_refund_sequence_is_block_based_relative_lock() {
+         let sequence = HTLCWallet::refund_sequence(144).unwrap();
+         assert!(sequence.is_relative_lock_time());
+         assert!(sequence.is_height_locked());
+         assert!(!sequence.is_time_locked());
+         assert_eq!(sequence.0, 144);
+     }
+
+     #[test]
+     fn test_refund_sequence_rejects_oversized_timelock() {
+         assert!(HTLCWallet::refund_sequence(u16::MAX as u64).is_ok());
+         assert!(HTLCWallet::refund_sequence(u16::MAX as u64 + 1).is_err());
+     }
+
+     #[cfg(feature = "regtest-it")]
+     #[tokio::test]
+     async fn test_htlc_refund_via_csv_after_timelock() {
+         let ctx = RegtestContext::new().await;
+         let timelock: u64 = 3;
+         let htlc = ctx.htlc(&ctx.pubkey.to_string(), OTHER_PUBKEY, timelock);
+
+         // The refund witness must carry the initiator's leaf, committed to by the HTLC address
+         let info = htlc.htlc.control_block_for(Leaf::Refund).unwrap();
+         assert!(info.leaf_script.as_bytes().windows(32).any(|key| key == ctx.pubkey.serialize()));
+         assert!(primitives::htlc::verify_control_block(
+             &info.internal_key,
+             info.merkle_root,
+             &info.leaf_script,
+             &info.control_block,
+         ));
+         let witness = htlc.htlc.refund().unwrap();
+         assert_eq!(witness[Leaf::Refund.script_index()], info.leaf_script.to_bytes());
+         assert_eq!(witness[Leaf::Refund.control_block_index()], info.control_block);
+
+         ctx.fund_htlc(&htlc, 30_000).await;
+
+         // Confirm the funding transaction, then mine until one block short of the CSV lock
+         let funding_height = ctx.mine(1).await;
+         ctx.mine(timelock - 1).await;
+         assert!(ctx.wallet.refund_htlc(&htlc.htlc, &ctx.wallet.get_address()).await.is_err());
+
+         ctx.mine(1).await;
+         let refund_tx = ctx
+             .wallet
+             .refund_htlc(&htlc.htlc, &ctx.wallet.get_address())
+             .await
+             .expect("Failed to build refund tx");
+         assert!(ctx.tip().await >= funding_height + timelock);
+         assert_eq!(refund_tx.version, Version::TWO);
+         assert_eq!(refund_tx.lock_time, LockTime::ZERO);
+         assert_eq!(refund_tx.input[0].sequence, Sequence::from_height(timelock as u16));
+         let refund_txid = ctx.broadcast(&refund_tx).await;
+         ctx.mine(1).await;
+
+         assert!(ctx.utxos(&htlc.address).await.is_empty());
+         let refund_output = ctx.confirmed_output(&ctx.wallet.get_address(), &refund_txid).await;
+         assert_eq!(refund_output.value, refund_tx.output[0].value.to_sat());
+     }
  }
\ No newline at end of file
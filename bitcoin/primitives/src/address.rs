@@ -0,0 +1,29 @@
+use anyhow::{anyhow, Result};
+use bitcoin::{Address, Network};
+use std::str::FromStr;
+
+/// Parses a Bitcoin address and checks it belongs to `network`
+pub fn parse_bitcoin_address(network: Network, value: &str) -> Result<Address> {
+    Address::from_str(value.trim())
+        .map_err(|e| anyhow!("Invalid Bitcoin address '{}': {}", value, e))?
+        .require_network(network)
+        .map_err(|_| anyhow!("Bitcoin address '{}' is not valid on {}", value, network))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TESTNET_ADDRESS: &str = "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy";
+    const MAINNET_ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    const REGTEST_ADDRESS: &str = "bcrt1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjlfdsnd";
+
+    #[test]
+    fn test_bitcoin_network_mismatch() {
+        assert!(parse_bitcoin_address(Network::Regtest, REGTEST_ADDRESS).is_ok());
+        assert!(parse_bitcoin_address(Network::Regtest, TESTNET_ADDRESS).is_err());
+        assert!(parse_bitcoin_address(Network::Bitcoin, MAINNET_ADDRESS).is_ok());
+        let err = parse_bitcoin_address(Network::Bitcoin, TESTNET_ADDRESS).unwrap_err();
+        assert!(err.to_string().contains("not valid on"));
+    }
+}
@@ -1,41 +1,48 @@
 use anyhow::{anyhow, Context, Result};
 use bitcoin::{
-    key::Secp256k1, secp256k1::{self, PublicKey, XOnlyPublicKey}, taproot::{LeafVersion, TaprootBuilder}, Address, KnownHrp, Network, ScriptBuf
+    key::{Secp256k1, TapTweak}, secp256k1::{self, schnorr, Message, PublicKey, XOnlyPublicKey}, taproot::{ControlBlock, LeafVersion, TapNodeHash, TaprootBuilder}, Address, KnownHrp, Network, Script, ScriptBuf
 };
 
+use once_cell::sync::Lazy;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
+use std::str::FromStr;
 
 use super::scripts::{redeem_leaf, refund_leaf, instant_refund_leaf};
+use super::types::HashFunction;
 
 
 
-pub fn garden_nums() -> Result<XOnlyPublicKey, Box<dyn std::error::Error>> {
-    let mut hasher = Sha256::new();
-    hasher.update(b"GardenHTLC");
-    let r = hasher.finalize();
+/// Garden internal key for Taproot HTLC addresses: the BIP-341 H point plus
+/// r*G with r = SHA256("GardenHTLC"), so no one knows its discrete log and the
+/// key path can't be spent. Derived from constants, so a failure here is a
+/// programming error caught by the pinned-value test rather than a runtime condition.
+pub static GARDEN_NUMS: Lazy<XOnlyPublicKey> = Lazy::new(|| {
+    let r = Sha256::digest(b"GardenHTLC");
 
     // Parse the BIP-341 H point
-    let h_hex = "0250929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
-    let h = PublicKey::from_slice(&hex::decode(h_hex)?)?;
+    const H_HEX: &str = "0250929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+    let h_bytes = hex::decode(H_HEX).expect("Invalid hex in GARDEN_NUMS");
+    let h = PublicKey::from_slice(&h_bytes).expect("Invalid H point in GARDEN_NUMS");
 
     // Create the r*G point
     let secp = Secp256k1::new();
-    let r_scalar = secp256k1::SecretKey::from_slice(&r)?;
+    let r_scalar = secp256k1::SecretKey::from_slice(&r).expect("Invalid scalar in GARDEN_NUMS");
     let r_g = PublicKey::from_secret_key(&secp, &r_scalar);
 
     // Add H + r*G
-    let nums = h.combine(&r_g)?;
+    let nums = h.combine(&r_g).expect("Point addition failed in GARDEN_NUMS");
 
     let (xonly, _) = nums.x_only_public_key();
-
-    Ok(xonly)
-}
+    xonly
+});
 
 pub struct BitcoinHTLC {
     initiator_pubkey: String,
     redeemer_pubkey: String,
     secret_hash: Vec<u8>,
+    hash_function: HashFunction,
     timelock: i64,
     network: Network,
 }
@@ -53,21 +60,50 @@ impl BitcoinHTLC {
             initiator_pubkey,
             redeemer_pubkey,
             secret_hash,
+            hash_function: HashFunction::default(),
             timelock,
             network
         })
     }
 
-    fn construct_taproot(&self) -> Result<TaprootBuilder> {
-        let redeem_leaf = redeem_leaf(&self.secret_hash, &self.redeemer_pubkey).context("error building redeem leaf")?;
-        let refund_leaf = refund_leaf(self.timelock, &self.initiator_pubkey).context("error building refund leaf")?;
+    /// Sets the algorithm used to check the secret against `secret_hash` (SHA256 by default)
+    pub fn with_hash_function(mut self, hash_function: HashFunction) -> Self {
+        self.hash_function = hash_function;
+        self
+    }
+
+    /// Returns whether the hex-encoded `secret` hashes to this HTLC's secret hash
+    pub fn verify_secret(&self, secret: &str) -> Result<bool> {
+        let secret_bytes = hex::decode(secret)?;
+        Ok(self.hash_function.hash(&secret_bytes) == self.secret_hash)
+    }
+
+    /// Script of every spend path, in `Leaf::ALL` order
+    pub fn leaves(&self) -> Result<Vec<(Leaf, ScriptBuf)>> {
+        Leaf::ALL
+            .into_iter()
+            .map(|leaf| Ok((leaf, self.leaf_script(leaf)?)))
+            .collect()
+    }
 
-        let instant_refund = instant_refund_leaf(&self.initiator_pubkey, &self.redeemer_pubkey).context("error building instand refund leaf")?;
+    fn leaf_script(&self, leaf: Leaf) -> Result<ScriptBuf> {
+        match leaf {
+            Leaf::Redeem => redeem_leaf(&self.secret_hash, &self.redeemer_pubkey, self.hash_function).context("error building redeem leaf"),
+            Leaf::Refund => refund_leaf(self.timelock, &self.initiator_pubkey).context("error building refund leaf"),
+            Leaf::InstantRefund => instant_refund_leaf(&self.initiator_pubkey, &self.redeemer_pubkey).context("error building instand refund leaf"),
+        }
+    }
 
+    fn construct_taproot(&self) -> Result<TaprootBuilder> {
         let mut script_map = BTreeMap::new();
-        script_map.insert(10, redeem_leaf);
-        script_map.insert(5, refund_leaf);
-        script_map.insert(1, instant_refund);
+        for (leaf, script) in self.leaves()? {
+            let weight = match leaf {
+                Leaf::Redeem => 10,
+                Leaf::Refund => 5,
+                Leaf::InstantRefund => 1,
+            };
+            script_map.insert(weight, script);
+        }
 
         let taproot = TaprootBuilder::with_huffman_tree(script_map)
             .map_err(|e| anyhow!("Failed to create huffman tree: {}", e))?;
@@ -84,10 +120,11 @@ impl BitcoinHTLC {
             return Err(anyhow::anyhow!("Taproot builder is not finalizable"));
         }
 
-        let internal_key =
-            garden_nums().map_err(|e| anyhow!("error creating internal_key {}", e)).expect("error getting garden NUMS");
+        let internal_key = *GARDEN_NUMS;
         
-        let spend_info = taproot_builder.finalize(&secp, internal_key).expect("error finalizing builder");
+        let spend_info = taproot_builder
+            .finalize(&secp, internal_key)
+            .map_err(|_| anyhow!("error finalizing taproot builder"))?;
         let addr = Address::p2tr(
             &secp,
             internal_key,
@@ -98,48 +135,39 @@ impl BitcoinHTLC {
     }
     
     pub fn get_control_block(&self, leaf: Leaf) -> Result<(ScriptBuf, Vec<u8>)> {
+        let info = self.control_block_for(leaf)?;
+        Ok((info.leaf_script, info.control_block))
+    }
+
+    /// Returns the serialized control block for `leaf` together with everything
+    /// needed to check it with [`verify_control_block`]
+    pub fn control_block_for(&self, leaf: Leaf) -> Result<ControlBlockInfo> {
         let secp = Secp256k1::new();
-        let internal_key = garden_nums().unwrap();
-        let taproot_script_tree = self.construct_taproot()?.finalize(&secp, internal_key).unwrap();
-        
-        let (leaf_script, cb_bytes) = match leaf {
-            Leaf::Redeem => {
-                let redeem = redeem_leaf(&self.secret_hash, &self.redeemer_pubkey)?;
-                
-                let ctrlblck = taproot_script_tree.control_block(&(redeem.clone(), LeafVersion::TapScript)).unwrap();
-                
-                let cb_bytes = ctrlblck.serialize();
-                (redeem, cb_bytes.clone())
-            },
-            Leaf::Refund => {
-                let refund = refund_leaf(self.timelock, &self.initiator_pubkey)?;
-                
-                let ctrlblck = taproot_script_tree.control_block(&(refund.clone(), LeafVersion::TapScript)).unwrap();
-                
-                let cb_bytes = ctrlblck.serialize();
-                (refund, cb_bytes.clone())
-            },
-            Leaf::InstantRefund => {
-                let instant_refund = instant_refund_leaf(&self.initiator_pubkey, &self.redeemer_pubkey)?;
-                
-                let ctrlblck = taproot_script_tree.control_block(&(instant_refund.clone(), LeafVersion::TapScript)).unwrap();
-                
-                let cb_bytes = ctrlblck.serialize();
-                (instant_refund, cb_bytes.clone())
-            }
-        };
-        Ok((leaf_script, cb_bytes))
+        let internal_key = *GARDEN_NUMS;
+        let spend_info = self
+            .construct_taproot()?
+            .finalize(&secp, internal_key)
+            .map_err(|_| anyhow!("error finalizing taproot builder"))?;
+
+        let leaf_script = self.leaf_script(leaf)?;
+
+        let control_block = spend_info
+            .control_block(&(leaf_script.clone(), LeafVersion::TapScript))
+            .ok_or_else(|| anyhow!("leaf script not found in taproot tree"))?;
+
+        Ok(ControlBlockInfo {
+            control_block: control_block.serialize(),
+            leaf_script,
+            internal_key,
+            merkle_root: spend_info.merkle_root(),
+        })
     }
     
     pub fn redeem(&self, secret: &str) -> Result<Vec<Vec<u8>>> {
-        let redeem_secret_bytes = hex::decode(secret)?;
-        let mut hasher = Sha256::new();
-        hasher.update(redeem_secret_bytes.clone());
-        let secret_hash_bytes = hasher.finalize().to_vec();
-    
-        if !secret_hash_bytes.eq(&self.secret_hash) {
+        if !self.verify_secret(secret)? {
             return Err(anyhow!("secret mismatch")); 
         }
+        let redeem_secret_bytes = hex::decode(secret)?;
         
         
         let (redeem_script, cb_bytes) = self.get_control_block(Leaf::Redeem)?;
@@ -153,6 +181,7 @@ impl BitcoinHTLC {
             redeem_script.into_bytes(),
             cb_bytes,
         ]);
+        Leaf::Redeem.validate_witness(&witness_data)?;
         Ok(witness_data)
     }
     
@@ -168,38 +197,158 @@ impl BitcoinHTLC {
             refund_script.into_bytes(),
             cb_bytes,
         ]);
-        
+        Leaf::Refund.validate_witness(&witness_data)?;
         Ok(witness_data)
     }
     
-    #[allow(dead_code)]
-    pub fn instant_refund(&self) -> Result<Vec<Vec<u8>>> {
-        let mut witness_data: Vec<Vec<u8>> = Vec::new();
-        let sig_data = hex::decode("000000000000")?;
-        let random_sig = hex::decode("1111111111111")?;
+    /// Witness for the cooperative 2-of-2 refund leaf. Its script checks the
+    /// initiator's signature with `OP_CHECKSIG` and then adds the redeemer's with
+    /// `OP_CHECKSIGADD 2 OP_NUMEQUAL`, so the initiator's signature is on top of
+    /// the stack: `[redeemer_sig, initiator_sig, script, control_block]`.
+    /// Both are BIP-340 signatures, 64 bytes or 65 with a sighash type.
+    pub fn instant_refund(&self, initiator_sig: &[u8], redeemer_sig: &[u8]) -> Result<Vec<Vec<u8>>> {
+        for (party, sig) in [("initiator", initiator_sig), ("redeemer", redeemer_sig)] {
+            parse_schnorr_sig(sig).with_context(|| format!("invalid {} signature", party))?;
+        }
         let (instant_refund_script, cb_bytes) = self.get_control_block(Leaf::InstantRefund)?;
-        
-        witness_data.extend([
-            sig_data,
-            random_sig,
+
+        let witness_data = vec![
+            redeemer_sig.to_vec(),
+            initiator_sig.to_vec(),
             instant_refund_script.into_bytes(),
             cb_bytes,
-        ]);
-        
+        ];
+        Leaf::InstantRefund.validate_witness(&witness_data)?;
         Ok(witness_data)
     }
 
+    /// Checks an instant refund witness the way its script would: the leaf and
+    /// control block belong to this HTLC and both signatures sign `sighash` with
+    /// the keys the script expects them under
+    pub fn verify_instant_refund(&self, witness: &[Vec<u8>], sighash: &Message) -> Result<()> {
+        let leaf = Leaf::InstantRefund;
+        leaf.validate_witness(witness)?;
+        let (script, control_block) = self.get_control_block(leaf)?;
+        if witness[leaf.script_index()] != script.as_bytes() || witness[leaf.control_block_index()] != control_block {
+            return Err(anyhow!("witness is not for this HTLC's instant refund leaf"));
+        }
+
+        let secp = Secp256k1::verification_only();
+        // Stack top first, in the order the script consumes them
+        let checks = [
+            ("initiator", &witness[1], &self.initiator_pubkey),
+            ("redeemer", &witness[0], &self.redeemer_pubkey),
+        ];
+        for (party, sig, pubkey) in checks {
+            let pubkey = XOnlyPublicKey::from_str(pubkey).with_context(|| format!("invalid {} pubkey", party))?;
+            secp.verify_schnorr(&parse_schnorr_sig(sig)?, sighash, &pubkey)
+                .map_err(|_| anyhow!("{} signature does not verify", party))?;
+        }
+        Ok(())
+    }
+
     pub fn timelock(&self) -> u64 {
         self.timelock as u64
     }
 }
 
+/// Spend path of the HTLC's taproot tree
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Leaf {
     Redeem,
     Refund,
     InstantRefund
 }
 
+impl Leaf {
+    pub const ALL: [Leaf; 3] = [Leaf::Redeem, Leaf::Refund, Leaf::InstantRefund];
+
+    /// Signatures at the bottom of the witness stack, before any leaf data:
+    /// redeem `[sig, secret, script, control_block]`, refund
+    /// `[sig, script, control_block]`, instant refund
+    /// `[sig, sig, script, control_block]`
+    pub fn signature_count(&self) -> usize {
+        match self {
+            Leaf::Redeem | Leaf::Refund => 1,
+            Leaf::InstantRefund => 2,
+        }
+    }
+
+    /// Items in this leaf's witness stack
+    pub fn witness_items(&self) -> usize {
+        match self {
+            Leaf::Redeem => 4,
+            Leaf::Refund => 3,
+            Leaf::InstantRefund => 4,
+        }
+    }
+
+    /// Position of the leaf script, second to last in every stack
+    pub fn script_index(&self) -> usize {
+        self.witness_items() - 2
+    }
+
+    /// Position of the control block, last in every stack
+    pub fn control_block_index(&self) -> usize {
+        self.witness_items() - 1
+    }
+
+    /// Checks that `witness` has this leaf's layout before it is indexed into
+    pub fn validate_witness(&self, witness: &[Vec<u8>]) -> Result<()> {
+        if witness.len() != self.witness_items() {
+            return Err(anyhow!(
+                "{:?} witness has {} items, expected {}",
+                self,
+                witness.len(),
+                self.witness_items()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Parses a BIP-340 signature with an optional trailing sighash type, which
+/// BIP-342 forbids from being `SIGHASH_DEFAULT` when present
+fn parse_schnorr_sig(sig: &[u8]) -> Result<schnorr::Signature> {
+    let sig = match sig.len() {
+        64 => sig,
+        65 if sig[64] != 0x00 => &sig[..64],
+        len => return Err(anyhow!("expected a 64 or 65 byte Schnorr signature, got {} bytes", len)),
+    };
+    schnorr::Signature::from_slice(sig).map_err(|e| anyhow!("malformed Schnorr signature: {}", e))
+}
+
+/// A leaf's control block and the data needed to verify it independently
+#[derive(Debug, Clone)]
+pub struct ControlBlockInfo {
+    pub control_block: Vec<u8>,
+    pub leaf_script: ScriptBuf,
+    pub internal_key: XOnlyPublicKey,
+    pub merkle_root: Option<TapNodeHash>,
+}
+
+/// Checks that `control_block` proves `leaf_script` is committed to by the
+/// taproot output built from `internal_key` and `merkle_root`
+pub fn verify_control_block(
+    internal_key: &XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+    leaf_script: &Script,
+    control_block: &[u8],
+) -> bool {
+    let control_block = match ControlBlock::decode(control_block) {
+        Ok(control_block) => control_block,
+        Err(_) => return false,
+    };
+    if control_block.internal_key != *internal_key {
+        return false;
+    }
+
+    let secp = Secp256k1::verification_only();
+    let (output_key, _) = internal_key.tap_tweak(&secp, merkle_root);
+    control_block.verify_taproot_commitment(&secp, output_key.to_x_only_public_key(), leaf_script)
+}
+
 #[cfg(test) ]
 mod tests {
     use super::*;
@@ -221,4 +370,242 @@ mod tests {
         }
 
     }
-}
\ No newline at end of file
+
+    fn test_htlc(secret: &str, hash_function: HashFunction) -> BitcoinHTLC {
+        let secret_hash = hex::encode(hash_function.hash(&hex::decode(secret).unwrap()));
+        BitcoinHTLC::new(
+            secret_hash,
+            "460f2e8ff81fc4e0a8e6ce7796704e3829e3e3eedb8db9390bdc51f4f04cf0a6".to_string(),
+            "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce".to_string(),
+            12,
+            Network::Testnet4,
+        )
+        .unwrap()
+        .with_hash_function(hash_function)
+    }
+
+    #[test]
+    fn test_sha256_htlc_round_trip() {
+        let secret = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
+        let htlc = test_htlc(secret, HashFunction::Sha256);
+
+        assert!(htlc.address().is_ok());
+        assert!(htlc.verify_secret(secret).unwrap());
+        assert!(!htlc.verify_secret("00").unwrap());
+
+        let witness = htlc.redeem(secret).unwrap();
+        assert_eq!(witness[1], hex::decode(secret).unwrap());
+        assert_eq!(witness[2][0], bitcoin::opcodes::all::OP_SHA256.to_u8());
+    }
+
+    #[test]
+    fn test_hash160_htlc_round_trip() {
+        let secret = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
+        let htlc = test_htlc(secret, HashFunction::Hash160);
+
+        assert_ne!(
+            htlc.address().unwrap(),
+            test_htlc(secret, HashFunction::Sha256).address().unwrap()
+        );
+        assert!(htlc.verify_secret(secret).unwrap());
+
+        let witness = htlc.redeem(secret).unwrap();
+        assert_eq!(witness[1], hex::decode(secret).unwrap());
+        assert_eq!(witness[2][0], bitcoin::opcodes::all::OP_HASH160.to_u8());
+        assert!(htlc.redeem("00").is_err());
+    }
+
+    #[test]
+    fn test_garden_nums_matches_pinned_value() {
+        // Changing the tag or derivation would silently move every HTLC address
+        assert_eq!(
+            GARDEN_NUMS.to_string(),
+            "2160e11a135f94e536a5b222e5d09fd9db1be5f5f5e753920290c0410cf388f0"
+        );
+    }
+
+    #[test]
+    fn test_control_block_verifies_for_every_leaf() {
+        let htlc = test_htlc("db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180", HashFunction::Sha256);
+        for leaf in Leaf::ALL {
+            let info = htlc.control_block_for(leaf).unwrap();
+            assert!(
+                verify_control_block(&info.internal_key, info.merkle_root, &info.leaf_script, &info.control_block),
+                "control block for {:?} should verify",
+                leaf
+            );
+        }
+    }
+
+    #[test]
+    fn test_tampered_control_block_fails() {
+        let htlc = test_htlc("db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180", HashFunction::Sha256);
+        let info = htlc.control_block_for(Leaf::Redeem).unwrap();
+
+        // Flip a byte of the merkle path
+        let mut tampered = info.control_block.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        assert!(!verify_control_block(&info.internal_key, info.merkle_root, &info.leaf_script, &tampered));
+
+        // A valid control block for a different leaf doesn't prove this script
+        let refund = htlc.control_block_for(Leaf::Refund).unwrap();
+        assert!(!verify_control_block(&info.internal_key, info.merkle_root, &info.leaf_script, &refund.control_block));
+
+        assert!(!verify_control_block(&info.internal_key, info.merkle_root, &info.leaf_script, &[0u8; 3]));
+    }
+
+    #[test]
+    fn test_sha256d_htlc_round_trip() {
+        let secret = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
+        let htlc = test_htlc(secret, HashFunction::Sha256d);
+
+        assert_ne!(
+            htlc.address().unwrap(),
+            test_htlc(secret, HashFunction::Sha256).address().unwrap()
+        );
+        assert!(htlc.verify_secret(secret).unwrap());
+        // A single SHA256 of the secret is not the hashlock
+        let sha256_hash = HashFunction::Sha256.hash(&hex::decode(secret).unwrap());
+        assert!(!htlc.verify_secret(&hex::encode(sha256_hash)).unwrap());
+
+        let witness = htlc.redeem(secret).unwrap();
+        assert_eq!(witness[1], hex::decode(secret).unwrap());
+        let sha256 = bitcoin::opcodes::all::OP_SHA256.to_u8();
+        assert_eq!(&witness[2][..2], &[sha256, sha256]);
+        assert!(htlc.redeem("00").is_err());
+    }
+
+    #[test]
+    fn test_secret_hash_length_must_match_hash_function() {
+        // A 32-byte SHA256 hash cannot be used with HASH160
+        let htlc = BitcoinHTLC::new(
+            "731170d859f81a395a79e02cf3812e413b21793900e70ff77e48dfcf7ef6a4e6".to_string(),
+            "460f2e8ff81fc4e0a8e6ce7796704e3829e3e3eedb8db9390bdc51f4f04cf0a6".to_string(),
+            "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce".to_string(),
+            12,
+            Network::Testnet4,
+        )
+        .unwrap()
+        .with_hash_function(HashFunction::Hash160);
+        assert!(htlc.address().is_err());
+
+        // SHA256d hashlocks are 32 bytes like SHA256
+        let htlc = BitcoinHTLC::new(
+            "731170d859f81a395a79e02cf3812e413b21793900e70ff77e48dfcf7ef6a4e6".to_string(),
+            "460f2e8ff81fc4e0a8e6ce7796704e3829e3e3eedb8db9390bdc51f4f04cf0a6".to_string(),
+            "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce".to_string(),
+            12,
+            Network::Testnet4,
+        )
+        .unwrap()
+        .with_hash_function(HashFunction::Sha256d);
+        assert!(htlc.address().is_ok());
+    }
+
+    #[test]
+    fn test_witness_layout_per_leaf() {
+        let secret = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
+        let htlc = test_htlc(secret, HashFunction::Sha256);
+
+        let redeem = htlc.redeem(secret).unwrap();
+        assert_eq!(redeem.len(), 4);
+        assert_eq!(Leaf::Redeem.witness_items(), 4);
+        assert_eq!(redeem[1], hex::decode(secret).unwrap());
+
+        let refund = htlc.refund().unwrap();
+        assert_eq!(refund.len(), 3);
+        assert_eq!(Leaf::Refund.witness_items(), 3);
+
+        assert_eq!(Leaf::InstantRefund.witness_items(), 4);
+        assert_eq!(Leaf::InstantRefund.signature_count(), 2);
+
+        for (leaf, witness) in [(Leaf::Redeem, &redeem), (Leaf::Refund, &refund)] {
+            let info = htlc.control_block_for(leaf).unwrap();
+            assert_eq!(witness[leaf.script_index()], info.leaf_script.to_bytes());
+            assert_eq!(witness[leaf.control_block_index()], info.control_block);
+        }
+    }
+
+    #[test]
+    fn test_witness_for_another_leaf_is_rejected() {
+        let secret = "db3fafd38168bcb8ea8979e010f4a377ca426f3ce478ea6ea23769d416306180";
+        let htlc = test_htlc(secret, HashFunction::Sha256);
+
+        let refund = htlc.refund().unwrap();
+        assert!(Leaf::Refund.validate_witness(&refund).is_ok());
+        assert!(Leaf::Redeem.validate_witness(&refund).is_err());
+
+        let redeem = htlc.redeem(secret).unwrap();
+        assert!(Leaf::Refund.validate_witness(&redeem).is_err());
+    }
+
+    #[test]
+    fn test_instant_refund_two_of_two_witness_validates() {
+        use bitcoin::{
+            hashes::Hash,
+            secp256k1::{Keypair, SecretKey},
+            sighash::{Prevouts, SighashCache, TapSighashType},
+            taproot::TapLeafHash,
+            absolute::LockTime, transaction::Version, Amount, OutPoint, Sequence, Transaction, TxIn, TxOut, Witness,
+        };
+
+        let secp = Secp256k1::new();
+        let initiator = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1u8; 32]).unwrap());
+        let redeemer = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2u8; 32]).unwrap());
+        let htlc = BitcoinHTLC::new(
+            "731170d859f81a395a79e02cf3812e413b21793900e70ff77e48dfcf7ef6a4e6".to_string(),
+            initiator.x_only_public_key().0.to_string(),
+            redeemer.x_only_public_key().0.to_string(),
+            12,
+            Network::Regtest,
+        )
+        .unwrap();
+
+        let prevout = TxOut { value: Amount::from_sat(50_000), script_pubkey: htlc.address().unwrap().script_pubkey() };
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut { value: Amount::from_sat(49_000), script_pubkey: prevout.script_pubkey.clone() }],
+        };
+        let (script, _) = htlc.get_control_block(Leaf::InstantRefund).unwrap();
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prevout]),
+                TapLeafHash::from_script(&script, LeafVersion::TapScript),
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let message = Message::from_digest(sighash.to_byte_array());
+        let initiator_sig = secp.sign_schnorr_no_aux_rand(&message, &initiator).as_ref().to_vec();
+        let redeemer_sig = secp.sign_schnorr_no_aux_rand(&message, &redeemer).as_ref().to_vec();
+
+        let witness = htlc.instant_refund(&initiator_sig, &redeemer_sig).unwrap();
+        assert_eq!(witness.len(), Leaf::InstantRefund.witness_items());
+        assert!(htlc.verify_instant_refund(&witness, &message).is_ok());
+
+        // Signatures in each other's slots fail the script's key order
+        let swapped = htlc.instant_refund(&redeemer_sig, &initiator_sig).unwrap();
+        assert!(htlc.verify_instant_refund(&swapped, &message).is_err());
+
+        // One party signing twice is not 2-of-2
+        let one_party = htlc.instant_refund(&initiator_sig, &initiator_sig).unwrap();
+        assert!(htlc.verify_instant_refund(&one_party, &message).is_err());
+
+        // Placeholders are rejected up front, with or without a sighash byte
+        assert!(htlc.instant_refund(&[0u8; 6], &redeemer_sig).is_err());
+        let mut with_default_type = initiator_sig.clone();
+        with_default_type.push(0x00);
+        assert!(htlc.instant_refund(&with_default_type, &redeemer_sig).is_err());
+        let mut with_all_type = initiator_sig.clone();
+        with_all_type.push(0x01);
+        assert!(htlc.instant_refund(&with_all_type, &redeemer_sig).is_ok());
+    }
+}
@@ -14,7 +14,7 @@ use bitcoin::{
 };
 use serde::Deserialize;
 
-use crate::indexer::SimpleIndexer;
+use crate::{address::parse_bitcoin_address, htlc::Leaf, indexer::SimpleIndexer};
 
 /// Constants for transaction fees and sizes
 const DEFAULT_FEE_RATE_SAT_PER_VBYTE: u64 = 250;
@@ -141,9 +141,11 @@ impl HtlcHandler {
         let fee = fee_rate * ESTIMATED_TAPROOT_TX_SIZE_VBYTES;
         let output_value = utxo.value.saturating_sub(fee);
 
+        Leaf::Redeem.validate_witness(&witness_stack)?;
+
         // Create and sign the transaction
         let mut tx = self.create_unsigned_redeem_tx(&utxo, &recipient_addr, output_value)?;
-        let leaf_hash = self.create_leaf_hash(&witness_stack[2])?;
+        let leaf_hash = self.create_leaf_hash(&witness_stack[Leaf::Redeem.script_index()])?;
         let prevouts = self.create_prevouts_for_signing(htlc_addr, utxo.value);
 
         tx = self.sign_and_set_taproot_witness(
@@ -166,6 +168,8 @@ impl HtlcHandler {
         fee_rate: u64,
         witness_stack: Vec<Vec<u8>>,
     ) -> Result<Transaction> {
+        Leaf::Refund.validate_witness(&witness_stack)?;
+
         let utxo = self.get_htlc_utxo(htlc_addr).await?;
         let sender_address = self.get_btc_address_for_priv_key(private_key)?;
         let sender_address = self.parse_and_validate_address(&sender_address)?;
@@ -180,7 +184,10 @@ impl HtlcHandler {
         tx = self.sign_and_set_taproot_witness(
             tx,
             0,
-            TapLeafHash::from_script(Script::from_bytes(&witness_stack[2]), LeafVersion::TapScript),
+            TapLeafHash::from_script(
+                Script::from_bytes(&witness_stack[Leaf::Refund.script_index()]),
+                LeafVersion::TapScript,
+            ),
             private_key,
             TapSighashType::All,
             prevouts,
@@ -310,10 +317,7 @@ impl HtlcHandler {
 
     /// Parses and validates a Bitcoin address
     fn parse_and_validate_address(&self, address: &str) -> Result<Address> {
-        Address::from_str(address)
-            .map_err(|e| anyhow!("Invalid address format: {:?}", e))?
-            .require_network(self.network)
-            .map_err(|e| anyhow!("Network mismatch: {:?}", e))
+        parse_bitcoin_address(self.network, address)
     }
 
     /// Creates an unsigned redeem transaction
@@ -390,11 +394,12 @@ impl HtlcHandler {
             sig_serialized.push(sighash_type as u8);
         }
 
+        // Replace the signature placeholder and keep the rest of the leaf's stack
         let mut witness = Witness::new();
         witness.push(sig_serialized);
-        witness.push(&witness_stack[1]);
-        witness.push(&witness_stack[2]);
-        witness.push(&witness_stack[3]);
+        for item in witness_stack.iter().skip(1) {
+            witness.push(item);
+        }
 
         tx.input[input_index].witness = witness;
 
@@ -412,6 +417,13 @@ pub struct UTXO {
     pub value: u64,
 }
 
+impl UTXO {
+    /// Number of confirmations at `tip_height`, 0 while unconfirmed
+    pub fn confirmations(&self, tip_height: u64) -> u64 {
+        self.status.confirmations(tip_height)
+    }
+}
+
 /// Represents the status of a transaction
 #[derive(Debug, Deserialize, Clone)]
 #[allow(dead_code)]
@@ -424,3 +436,52 @@ pub struct Status {
     #[serde(default)]
     pub block_time: u64,
 }
+
+impl Status {
+    /// Number of confirmations at `tip_height`, counting the including block as the first.
+    /// Returns 0 for unconfirmed transactions and for a tip behind the including block.
+    pub fn confirmations(&self, tip_height: u64) -> u64 {
+        if !self.confirmed || self.block_height == 0 || tip_height < self.block_height {
+            return 0;
+        }
+        tip_height - self.block_height + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(confirmed: bool, block_height: u64) -> UTXO {
+        UTXO {
+            txid: String::new(),
+            vout: 0,
+            status: Status {
+                confirmed,
+                block_height,
+                block_hash: String::new(),
+                block_time: 0,
+            },
+            value: 1000,
+        }
+    }
+
+    #[test]
+    fn test_confirmations_at_various_depths() {
+        assert_eq!(utxo(true, 100).confirmations(100), 1);
+        assert_eq!(utxo(true, 100).confirmations(101), 2);
+        assert_eq!(utxo(true, 100).confirmations(105), 6);
+    }
+
+    #[test]
+    fn test_unconfirmed_has_no_confirmations() {
+        assert_eq!(utxo(false, 0).confirmations(100), 0);
+        assert_eq!(utxo(true, 0).confirmations(100), 0);
+        assert_eq!(utxo(false, 100).confirmations(105), 0);
+    }
+
+    #[test]
+    fn test_tip_behind_block_has_no_confirmations() {
+        assert_eq!(utxo(true, 100).confirmations(99), 0);
+    }
+}
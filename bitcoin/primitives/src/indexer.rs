@@ -4,6 +4,9 @@ use std::time::Duration;
 
 use crate::htlc_handler::UTXO;
 
+/// UTXOs below the P2WPKH dust threshold cost more in fees to spend than they add
+pub const MIN_SPENDABLE_UTXO_VALUE: u64 = 294;
+
 /// Statistics for address transactions on the blockchain
 #[derive(Debug, Deserialize, Clone)]
 pub struct ChainStats {
@@ -32,9 +35,27 @@ pub struct AddressInfo {
     pub mempool_stats: MempoolStats,
 }
 
+/// Parses the plain-text body of `/blocks/tip/height`. Anything other than a
+/// number, such as a proxy's HTML error page served with 200, is reported with
+/// a snippet of the body so a misconfigured indexer URL is easy to spot.
+fn parse_block_height(body: &str) -> Result<u64> {
+    let trimmed = body.trim();
+    if !trimmed.is_empty()
+        && trimmed.bytes().all(|b| b.is_ascii_digit())
+        && let Ok(height) = trimmed.parse()
+    {
+        return Ok(height);
+    }
+    let snippet: String = trimmed.chars().take(80).collect();
+    let ellipsis = if trimmed.chars().count() > 80 { "..." } else { "" };
+    Err(anyhow!("Indexer returned a non-numeric block height: '{}{}'", snippet, ellipsis))
+}
+
 pub struct SimpleIndexer {
     client: reqwest::Client,
-    url: String
+    url: String,
+    broadcast_urls: Vec<String>, // extra indexers transactions are also submitted to
+    txid_precheck: bool, // look the txid up before broadcasting
 }
 
 impl SimpleIndexer {
@@ -44,17 +65,29 @@ impl SimpleIndexer {
             .build()?;
 
         Ok(
-            Self { client, url: url.to_string() }
+            Self { client, url: url.to_string(), broadcast_urls: Vec::new(), txid_precheck: false }
         )
     }
 
+    /// Also submit transactions to these indexers, for redundancy in propagation
+    pub fn with_broadcast_urls(mut self, urls: Vec<String>) -> Self {
+        self.broadcast_urls = urls;
+        self
+    }
+
+    /// Before broadcasting, look the transaction up and return its txid without
+    /// submitting when the indexer already knows it. Costs an extra GET per submit.
+    pub fn with_txid_precheck(mut self, enabled: bool) -> Self {
+        self.txid_precheck = enabled;
+        self
+    }
+
     pub async fn get_current_block_height(&self) -> Result<u64> {
         let url = format!("{}/blocks/tip/height", self.url);
         
         let response = self.client.get(&url).send().await?;
         if response.status().is_success() {
-            let height: u64 = response.text().await?.parse()?;
-            Ok(height)
+            parse_block_height(&response.text().await?)
         } else {
             Err(anyhow::anyhow!("Failed to get current block height: {}", response.status()))
         }
@@ -101,72 +134,308 @@ impl SimpleIndexer {
         Ok(funded.saturating_sub(spent))
     }
 
+    /// Fetches a transaction by txid, or `None` when the indexer doesn't know it
+    pub async fn get_transaction(&self, txid: &str) -> Result<Option<bitcoin::Transaction>> {
+        let url = format!("{}/tx/{}/hex", self.url, txid);
+
+        let response = self.client.get(&url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to fetch transaction {}: {}", txid, response.status()));
+        }
+
+        let tx_bytes = hex::decode(response.text().await?.trim())?;
+        Ok(Some(bitcoin::consensus::deserialize(&tx_bytes)?))
+    }
+
     pub async fn get_utxos(&self, address: &str) -> Result<Vec<UTXO>> {
         let url = format!("{}/address/{}/utxo", &self.url, address);
 
         let response = self.client.get(url).send().await?;
         let resp = response.json::<Vec<UTXO>>().await?;
 
-        Ok(resp)
+        // A malformed response may contain zero-value outputs, which can never be useful
+        Ok(resp.into_iter().filter(|utxo| utxo.value > 0).collect())
     }
 
     pub async fn get_utxos_for_amount(&self, address:&str, amount: i64) -> Result<Vec<UTXO>> {
         let utxos = self.get_utxos(address).await?;
-        let mut filtered_utxos: Vec<UTXO> = Vec::new();
-        let mut total = 0;
-
-        for utxo in utxos {
-            total += utxo.value as i64;
-            filtered_utxos.push(utxo);
-            if total == amount {
-                return Ok(filtered_utxos);
+        select_utxos(utxos, amount)
+    }
+
+    /// Broadcasts `tx` to the primary indexer and every broadcast URL concurrently.
+    /// Succeeds as soon as one accepts it (or reports it as already known); the
+    /// remaining submissions keep running in the background.
+    pub async fn submit_tx(&self, tx: &bitcoin::Transaction) -> Result<String> {
+        let tx_bytes = bitcoin::consensus::serialize(tx);
+        let body = hex::encode(tx_bytes).into_bytes();
+        let txid = tx.compute_txid().to_string();
+
+        // A failed lookup just means we broadcast as usual
+        if self.txid_precheck && let Ok(Some(_)) = self.get_transaction(&txid).await {
+            return Ok(txid);
+        }
+
+        let mut urls = vec![self.url.clone()];
+        for url in &self.broadcast_urls {
+            if !urls.contains(url) {
+                urls.push(url.clone());
             }
         }
 
-        if total < amount {
-            return Err(anyhow!("Not enough funds in UTXOs"));
+        let (sender, mut results) = tokio::sync::mpsc::unbounded_channel();
+        for url in &urls {
+            let client = self.client.clone();
+            let endpoint = format!("{}/tx", url);
+            let body = body.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let _ = sender.send(submit_with_retries(&client, &endpoint, body).await);
+            });
+        }
+        drop(sender);
+
+        let mut errors = Vec::new();
+        while let Some(result) = results.recv().await {
+            match result {
+                Ok(accepted_txid) => return Ok(accepted_txid),
+                Err(e) if is_already_known(&e.to_string()) => return Ok(txid),
+                Err(e) => errors.push(e.to_string()),
+            }
         }
-        Ok(filtered_utxos)
+
+        Err(anyhow!("No indexer accepted transaction {}: {}", txid, errors.join("; ")))
     }
 
-    pub async fn submit_tx(&self, tx: &bitcoin::Transaction) -> Result<String> {
-        let endpoint = format!("{}/tx", self.url);
-        let tx_bytes = bitcoin::consensus::serialize(tx);
-        let hex_tx = hex::encode(tx_bytes);
-        let str_buffer = hex_tx.as_bytes();
-
-        const MAX_RETRIES: usize = 3;
-        let mut attempts = 0;
-        let mut last_error = None;
-
-        while attempts < MAX_RETRIES {
-            match self.client
-                .post(&endpoint)
-                .header("Content-Type", "application/text")
-                .body(str_buffer.to_vec())
-                .send().await {
-                    Ok(resp) => {
-                        if resp.status().is_success() {
-                            return Ok(resp.text().await?.to_string());
-                        } else {
-                            let err_msg = resp.text().await.map_err(|e| e)?;
-                            last_error = Some(anyhow!("req failed : {:#?}", err_msg));
+}
+
+/// Whether a broadcast error just means the node already has the transaction
+fn is_already_known(error: &str) -> bool {
+    const KNOWN: [&str; 4] = [
+        "already known",
+        "txn-already-known",
+        "txn-already-in-mempool",
+        "Transaction already in block chain",
+    ];
+    KNOWN.iter().any(|known| error.contains(known))
+}
+
+async fn submit_with_retries(client: &reqwest::Client, endpoint: &str, body: Vec<u8>) -> Result<String> {
+    const MAX_RETRIES: usize = 3;
+    let mut attempts = 0;
+    let mut last_error = None;
+
+    while attempts < MAX_RETRIES {
+        match client
+            .post(endpoint)
+            .header("Content-Type", "application/text")
+            .body(body.clone())
+            .send().await {
+                Ok(resp) => {
+                    if resp.status().is_success() {
+                        return Ok(resp.text().await?.to_string());
+                    } else {
+                        let err_msg = resp.text().await.map_err(|e| e)?;
+                        last_error = Some(anyhow!("req failed : {:#?}", err_msg));
+                        // Retrying won't change the answer for a transaction the node already has
+                        if is_already_known(&err_msg) {
+                            break;
                         }
-                    },
-                    Err(e) => {
-                        last_error = Some(anyhow!("request error: {}", e));
                     }
+                },
+                Err(e) => {
+                    last_error = Some(anyhow!("request error: {}", e));
                 }
-
-            attempts += 1;
-            if attempts < MAX_RETRIES {
-                // Add a small delay before retrying
-                tokio::time::sleep(tokio::time::Duration::from_millis(500 * attempts as u64)).await;
             }
+
+        attempts += 1;
+        if attempts < MAX_RETRIES {
+            // Add a small delay before retrying
+            tokio::time::sleep(tokio::time::Duration::from_millis(500 * attempts as u64)).await;
         }
+    }
 
-        Err(last_error.unwrap_or_else(|| anyhow!("Failed to submit transaction after {} attempts", MAX_RETRIES)))
+    Err(last_error.unwrap_or_else(|| anyhow!("Failed to submit transaction after {} attempts", MAX_RETRIES)))
+}
+
+/// Accumulates UTXOs towards `amount`, skipping any too small to be worth spending
+fn select_utxos(utxos: Vec<UTXO>, amount: i64) -> Result<Vec<UTXO>> {
+    let mut filtered_utxos: Vec<UTXO> = Vec::new();
+    let mut total = 0;
+
+    for utxo in utxos {
+        if utxo.value < MIN_SPENDABLE_UTXO_VALUE {
+            continue;
+        }
+        total += utxo.value as i64;
+        filtered_utxos.push(utxo);
+        if total == amount {
+            return Ok(filtered_utxos);
+        }
     }
 
+    if total < amount {
+        return Err(anyhow!("Not enough funds in UTXOs"));
+    }
+    Ok(filtered_utxos)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::htlc_handler::Status;
+    use bitcoin::hashes::Hash;
+    use crate::mock_server::{MockRequest, MockResponse, MockServer};
+
+    /// Serves every request with the given status and body
+    async fn serve(status: u16, body: &'static str) -> String {
+        let server = MockServer::start(move |_: &MockRequest| MockResponse::with_status(status, body)).await;
+        server.url().to_string()
+    }
+
+    fn empty_tx() -> bitcoin::Transaction {
+        bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_height_is_trimmed() {
+        let url = serve(200, "  871234\n").await;
+        let indexer = SimpleIndexer::new(&url).unwrap();
+        assert_eq!(indexer.get_current_block_height().await.unwrap(), 871234);
+    }
+
+    #[tokio::test]
+    async fn test_non_numeric_block_height_is_reported() {
+        let url = serve(200, "<html><body>502 Bad Gateway</body></html>").await;
+        let indexer = SimpleIndexer::new(&url).unwrap();
+        let err = indexer.get_current_block_height().await.unwrap_err().to_string();
+        assert!(err.contains("non-numeric block height"), "{}", err);
+        assert!(err.contains("502 Bad Gateway"), "{}", err);
+
+        let long_page = "<html>".repeat(100);
+        let err = parse_block_height(&long_page).unwrap_err().to_string();
+        assert!(err.ends_with("...'"), "{}", err);
+        assert!(parse_block_height("").is_err());
+        assert!(parse_block_height("-1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_secondary_indexer_accepts_when_primary_rejects() {
+        let primary = serve(400, "sendrawtransaction RPC error: bad-txns").await;
+        let secondary = serve(200, "accepted-txid").await;
+
+        let indexer = SimpleIndexer::new(&primary).unwrap().with_broadcast_urls(vec![secondary]);
+        assert_eq!(indexer.submit_tx(&empty_tx()).await.unwrap(), "accepted-txid");
+    }
+
+    #[tokio::test]
+    async fn test_already_known_counts_as_success() {
+        let primary = serve(400, "sendrawtransaction RPC error: txn-already-in-mempool").await;
+
+        let indexer = SimpleIndexer::new(&primary).unwrap();
+        let tx = empty_tx();
+        assert_eq!(indexer.submit_tx(&tx).await.unwrap(), tx.compute_txid().to_string());
+    }
+
+    /// Indexer that knows `known` (served from `GET /tx/:txid/hex`) and accepts broadcasts
+    async fn serve_known(known: &bitcoin::Transaction) -> MockServer {
+        let known_path = format!("/tx/{}/hex", known.compute_txid());
+        let known_hex = bitcoin::consensus::encode::serialize_hex(known);
+        MockServer::start(move |request: &MockRequest| {
+            if request.method == "GET" && request.path == known_path {
+                MockResponse::ok(known_hex.clone())
+            } else if request.method == "POST" && request.path == "/tx" {
+                MockResponse::ok("broadcast-txid")
+            } else {
+                MockResponse::with_status(404, "Transaction not found")
+            }
+        })
+        .await
+    }
+
+    fn broadcasts(server: &MockServer) -> usize {
+        server.requests().iter().filter(|request| request.method == "POST").count()
+    }
+
+    fn spending_tx(vout: u32) -> bitcoin::Transaction {
+        bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint { txid: bitcoin::Txid::all_zeros(), vout },
+                ..Default::default()
+            }],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(1_000),
+                script_pubkey: bitcoin::ScriptBuf::new(),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_precheck_skips_broadcast_of_known_transaction() {
+        let known = spending_tx(0);
+        let server = serve_known(&known).await;
+
+        let indexer = SimpleIndexer::new(server.url()).unwrap().with_txid_precheck(true);
+        assert_eq!(indexer.submit_tx(&known).await.unwrap(), known.compute_txid().to_string());
+        assert_eq!(broadcasts(&server), 0);
+
+        // Unknown transactions are still broadcast
+        assert_eq!(indexer.submit_tx(&spending_tx(1)).await.unwrap(), "broadcast-txid");
+        assert_eq!(broadcasts(&server), 1);
+    }
+
+    #[tokio::test]
+    async fn test_known_transaction_is_broadcast_without_precheck() {
+        let known = spending_tx(0);
+        let server = serve_known(&known).await;
+
+        let indexer = SimpleIndexer::new(server.url()).unwrap();
+        assert_eq!(indexer.submit_tx(&known).await.unwrap(), "broadcast-txid");
+        assert_eq!(broadcasts(&server), 1);
+    }
+
+    fn utxo(txid: &str, value: u64) -> UTXO {
+        UTXO {
+            txid: txid.to_string(),
+            vout: 0,
+            status: Status {
+                confirmed: true,
+                block_height: 100,
+                block_hash: String::new(),
+                block_time: 0,
+            },
+            value,
+        }
+    }
+
+    #[test]
+    fn test_zero_value_utxo_is_ignored() {
+        let utxos = vec![utxo("zero", 0), utxo("a", 5000), utxo("b", 5000)];
+        let selected = select_utxos(utxos, 10000).unwrap();
+        let txids: Vec<&str> = selected.iter().map(|u| u.txid.as_str()).collect();
+        assert_eq!(txids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_sub_dust_utxo_is_ignored() {
+        let utxos = vec![utxo("dust", MIN_SPENDABLE_UTXO_VALUE - 1), utxo("a", 5000)];
+        let selected = select_utxos(utxos, 5000).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].txid, "a");
+    }
+
+    #[test]
+    fn test_dust_does_not_count_towards_amount() {
+        let utxos = vec![utxo("dust", 100), utxo("a", 5000)];
+        assert!(select_utxos(utxos, 5100).is_err());
+    }
+}
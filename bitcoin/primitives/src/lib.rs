@@ -3,6 +3,10 @@ pub mod types;
 pub mod scripts;
 pub mod indexer;
 pub mod htlc_handler;
+pub mod address;
+pub mod urls;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock_server;
 
 // Re-export commonly used types from indexer
 pub use indexer::{AddressInfo, ChainStats, MempoolStats};
\ No newline at end of file
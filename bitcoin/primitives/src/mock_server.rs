@@ -0,0 +1,214 @@
+//! Local HTTP server standing in for an Esplora indexer, a JSON-RPC node or a
+//! webhook receiver in tests. Each connection carries one request and is closed
+//! after the reply. Enabled for other crates with the `test-util` feature.
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A request received by a `MockServer`
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path and query, e.g. `/address/tb1q.../utxo`
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Value of the header `name`, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reply a `MockServer` sends for a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self::with_status(200, body)
+    }
+
+    pub fn with_status(status: u16, body: impl Into<String>) -> Self {
+        Self { status, body: body.into() }
+    }
+}
+
+impl From<String> for MockResponse {
+    fn from(body: String) -> Self {
+        Self::ok(body)
+    }
+}
+
+impl From<&str> for MockResponse {
+    fn from(body: &str) -> Self {
+        Self::ok(body)
+    }
+}
+
+/// HTTP server on a free local port answering every request with a handler,
+/// keeping the requests it received
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    /// Starts a server replying to each request with `respond(request)`
+    pub async fn start<F, R>(mut respond: F) -> Self
+    where
+        F: FnMut(&MockRequest) -> R + Send + 'static,
+        R: Into<MockResponse>,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind mock server");
+        let url = format!("http://{}", listener.local_addr().expect("Mock server has no local address"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let Some(request) = read_request(&mut socket).await else {
+                    continue;
+                };
+                let response = respond(&request).into();
+                received.lock().unwrap().push(request);
+                let reply = format!(
+                    "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    response.status,
+                    reason(response.status),
+                    response.body.len(),
+                    response.body
+                );
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Starts a server replying 200 with `body` to every request
+    pub async fn serving(body: impl Into<String>) -> Self {
+        let body = body.into();
+        Self::start(move |_: &MockRequest| body.clone()).await
+    }
+
+    /// Base URL of the server, without a trailing slash
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Requests answered so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// URL of a local port nothing listens on, for exercising connection failures
+pub async fn unreachable_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind probe port");
+    format!("http://{}", listener.local_addr().expect("Probe port has no local address"))
+}
+
+async fn read_request(socket: &mut TcpStream) -> Option<MockRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let body_start = header_end + 4;
+    while buf.len() < body_start + content_length {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let body = String::from_utf8_lossy(&buf[body_start..]).to_string();
+
+    Some(MockRequest { method, path, headers, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_are_answered_and_recorded() {
+        let server = MockServer::start(|request: &MockRequest| {
+            if request.path == "/missing" {
+                MockResponse::with_status(404, "not found")
+            } else {
+                MockResponse::ok(format!("{} {}", request.method, request.body))
+            }
+        })
+        .await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/tx", server.url()))
+            .header("X-Test", "1")
+            .body("raw-tx")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "POST raw-tx");
+
+        let response = client.get(format!("{}/missing", server.url())).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/tx");
+        assert_eq!(requests[0].header("x-test"), Some("1"));
+        assert_eq!(requests[1].method, "GET");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_url_refuses_connections() {
+        let url = unreachable_url().await;
+        assert!(reqwest::get(&url).await.is_err());
+    }
+}
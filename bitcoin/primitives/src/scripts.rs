@@ -1,24 +1,32 @@
 use anyhow::{anyhow, Result};
-use bitcoin::{ScriptBuf, Script, opcodes};
+use bitcoin::{ScriptBuf, Script, opcodes, script::PushBytesBuf};
 
-pub fn redeem_leaf(secret_hash_bytes: &Vec<u8>, redeemer_pubkey: &str) -> Result<ScriptBuf> {
-    if secret_hash_bytes.len() != 32 {
+use crate::types::HashFunction;
+
+pub fn redeem_leaf(secret_hash_bytes: &[u8], redeemer_pubkey: &str, hash_function: HashFunction) -> Result<ScriptBuf> {
+    if secret_hash_bytes.len() != hash_function.digest_len() {
         return Err(anyhow!(
-            "Secret hash must be 32 bytes (64 hex chars), got {} bytes",
+            "Secret hash must be {} bytes for {:?}, got {} bytes",
+            hash_function.digest_len(),
+            hash_function,
             secret_hash_bytes.len()
         ));
     }
 
-    let mut secret_hash_array = [0u8; 32];
-    secret_hash_array.copy_from_slice(&secret_hash_bytes);
-
     let bytes = hex::decode(redeemer_pubkey)?;
     let mut redeem_pub_array = [0u8; 32];
     redeem_pub_array.copy_from_slice(&bytes[0..32]);
 
-    let script = Script::builder()
-        .push_opcode(opcodes::all::OP_SHA256)
-        .push_slice(secret_hash_array)
+    let secret_hash = PushBytesBuf::try_from(secret_hash_bytes.to_vec())
+        .map_err(|e| anyhow!("Invalid secret hash push: {e}"))?;
+
+    let mut builder = Script::builder();
+    for opcode in hash_function.script_opcodes() {
+        builder = builder.push_opcode(*opcode);
+    }
+
+    let script = builder
+        .push_slice(secret_hash)
         .push_opcode(opcodes::all::OP_EQUALVERIFY)
         .push_slice(&redeem_pub_array)
         .push_opcode(opcodes::all::OP_CHECKSIG)
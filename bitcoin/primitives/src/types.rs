@@ -1,5 +1,7 @@
 use std::fmt;
 
+use bitcoin::hashes::{hash160, sha256, sha256d, Hash};
+use bitcoin::opcodes::{all::{OP_HASH160, OP_SHA256}, Opcode};
 use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -73,6 +75,8 @@ pub struct CreateOrder {
     pub secret_hash: String,
     pub nonce : String,
     pub bitcoin_optional_recipient: Option<String>,
+    #[serde(default)]
+    pub hash_function: HashFunction,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub create_id: Option<String>, // Generated automatically by the service
 }
@@ -86,6 +90,17 @@ pub struct MatchedOrder {
     pub source_swap: Swap,
     pub destination_swap: Swap,
     pub create_order: CreateOrder,
+    /// Set by the orderbook at creation; absent on orders created before it existed
+    #[serde(default)]
+    pub direction: Option<SwapDirection>,
+}
+
+impl MatchedOrder {
+    /// Direction of the order, derived from the swap chains when not stored
+    pub fn direction(&self) -> SwapDirection {
+        self.direction
+            .unwrap_or_else(|| SwapDirection::from_chains(&self.source_swap.chain, &self.destination_swap.chain))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +120,8 @@ pub struct Swap {
     pub amount: String,
     pub timelock: i32,
     pub secret_hash: String,
+    #[serde(default)]
+    pub hash_function: HashFunction,
     pub secret: Option<String>,
     pub initiate_tx_hash: Option<String>,
     pub redeem_tx_hash: Option<String>,
@@ -114,13 +131,97 @@ pub struct Swap {
     pub refund_block_number: Option<String>,
     pub deposit_address: Option<String>,
     #[serde(default = "default_has_deposit")]
-    pub has_deposit: bool
+    pub has_deposit: bool,
+    /// Set by the watcher once the order expired before this swap's HTLC was funded
+    #[serde(default)]
+    pub expired: bool,
+}
+
+/// Form txids are stored and looked up in: lowercase hex without a `0x` prefix,
+/// so the per-field txid indexes serve exact matches
+pub fn normalize_txid(txid: &str) -> String {
+    let txid = txid.trim().to_lowercase();
+    match txid.strip_prefix("0x") {
+        Some(hex) => hex.to_string(),
+        None => txid,
+    }
+}
+
+/// Hash algorithm used for an order's `secret_hash`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashFunction {
+    #[default]
+    Sha256,
+    Hash160,
+    /// Double SHA256, checked on-chain with `OP_SHA256 OP_SHA256`
+    Sha256d,
+}
+
+impl HashFunction {
+    /// Length in bytes of the digest this function produces
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashFunction::Sha256 | HashFunction::Sha256d => 32,
+            HashFunction::Hash160 => 20,
+        }
+    }
+
+    /// Opcodes a redeem script runs on the revealed secret before comparing it to the hash
+    pub fn script_opcodes(&self) -> &'static [Opcode] {
+        match self {
+            HashFunction::Sha256 => &[OP_SHA256],
+            HashFunction::Hash160 => &[OP_HASH160],
+            HashFunction::Sha256d => &[OP_SHA256, OP_SHA256],
+        }
+    }
+
+    pub fn hash(&self, preimage: &[u8]) -> Vec<u8> {
+        match self {
+            HashFunction::Sha256 => sha256::Hash::hash(preimage).to_byte_array().to_vec(),
+            HashFunction::Hash160 => hash160::Hash::hash(preimage).to_byte_array().to_vec(),
+            HashFunction::Sha256d => sha256d::Hash::hash(preimage).to_byte_array().to_vec(),
+        }
+    }
+}
+
+/// Which way value moves across chains in an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapDirection {
+    BtcToEvm,
+    EvmToBtc,
+    EvmToEvm,
+    BtcToBtc,
+}
+
+impl SwapDirection {
+    pub fn from_chains(source: &Chain, destination: &Chain) -> Self {
+        Self::from_legs(source.is_bitcoin(), destination.is_bitcoin())
+    }
+
+    /// Direction of an order given which of its legs are on Bitcoin
+    pub fn from_legs(source_is_bitcoin: bool, destination_is_bitcoin: bool) -> Self {
+        match (source_is_bitcoin, destination_is_bitcoin) {
+            (true, false) => SwapDirection::BtcToEvm,
+            (false, true) => SwapDirection::EvmToBtc,
+            (false, false) => SwapDirection::EvmToEvm,
+            (true, true) => SwapDirection::BtcToBtc,
+        }
+    }
+
+    /// Whether the destination leg is a Bitcoin HTLC, i.e. one this executor settles
+    pub fn to_bitcoin(&self) -> bool {
+        matches!(self, SwapDirection::EvmToBtc | SwapDirection::BtcToBtc)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Chain {
     #[serde(rename = "bitcoin_testnet")]
     BitcoinTestnet,
+    #[serde(rename = "bitcoin_signet")]
+    BitcoinSignet,
     #[serde(rename = "arbitrum_sepolia")]
     ArbitrumSepolia,
     #[serde(rename = "avalanche_testnet")]
@@ -131,8 +232,31 @@ impl std::fmt::Display for Chain {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Chain::BitcoinTestnet => write!(f, "bitcoin_testnet"),
+            Chain::BitcoinSignet => write!(f, "bitcoin_signet"),
             Chain::ArbitrumSepolia => write!(f, "arbitrum_sepolia"),
             Chain::AvalancheTestnet => write!(f, "avalanche_testnet"),
         }
     }
 }
+
+impl Chain {
+    pub const ALL: [Chain; 4] = [
+        Chain::BitcoinTestnet,
+        Chain::BitcoinSignet,
+        Chain::ArbitrumSepolia,
+        Chain::AvalancheTestnet,
+    ];
+
+    pub fn is_bitcoin(&self) -> bool {
+        self.bitcoin_network().is_some()
+    }
+
+    /// The Bitcoin network HTLCs on this chain are derived for, if any
+    pub fn bitcoin_network(&self) -> Option<bitcoin::Network> {
+        match self {
+            Chain::BitcoinTestnet => Some(bitcoin::Network::Testnet4),
+            Chain::BitcoinSignet => Some(bitcoin::Network::Signet),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Base URL of an Esplora-compatible indexer, validated when config is loaded.
+/// Trailing slashes are dropped so paths can be appended with `format!("{}/tx", url)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IndexerUrl(String);
+
+impl IndexerUrl {
+    pub fn parse(value: &str) -> Result<Self> {
+        parse_http_url(value).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for IndexerUrl {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::parse(value)
+    }
+}
+
+impl TryFrom<String> for IndexerUrl {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<IndexerUrl> for String {
+    fn from(url: IndexerUrl) -> Self {
+        url.0
+    }
+}
+
+impl std::fmt::Display for IndexerUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Parses an absolute http(s) URL and returns it without trailing slashes
+pub fn parse_http_url(value: &str) -> Result<String> {
+    let url = Url::parse(value.trim()).map_err(|e| anyhow!("Invalid URL '{}': {}", value, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("URL '{}' must use http or https, not {}", value, url.scheme()));
+    }
+    if url.host_str().is_none() {
+        return Err(anyhow!("URL '{}' has no host", value));
+    }
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_slashes_are_dropped() {
+        let url = IndexerUrl::parse("https://mempool.space/testnet4/api/").unwrap();
+        assert_eq!(url.as_str(), "https://mempool.space/testnet4/api");
+        assert_eq!(format!("{}/tx/abc", url), "https://mempool.space/testnet4/api/tx/abc");
+
+        let url = IndexerUrl::parse("http://localhost:3000").unwrap();
+        assert_eq!(url.to_string(), "http://localhost:3000");
+    }
+
+    #[test]
+    fn test_non_http_schemes_are_rejected() {
+        assert!(IndexerUrl::parse("ftp://mempool.space/api").is_err());
+        assert!(IndexerUrl::parse("ws://localhost:3000").is_err());
+        assert!(IndexerUrl::parse("localhost:3000").is_err());
+        assert!(IndexerUrl::parse("not a url").is_err());
+    }
+}
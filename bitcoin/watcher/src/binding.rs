@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+/// Outcome of binding a swap's secret hash to the order it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingCheck {
+    /// The secret hash is unused or already belongs to this order
+    Bound,
+    /// Another order claimed the secret hash first
+    Collision { bound_to: String },
+}
+
+/// Binds every secret hash to the earliest created order using it. A second
+/// order reusing a victim's secret hash gets an HTLC that opens with the same
+/// preimage, so once the victim's swap is redeemed the copy can be claimed or
+/// used to grief it. The HTLC scripts carry no order context, so the owner is
+/// looked up in the store (`BitcoinStore::secret_hash_owner`) and cached here.
+#[derive(Debug, Default)]
+pub struct SecretHashBindings {
+    orders: HashMap<String, String>, // normalized secret hash -> create id
+}
+
+impl SecretHashBindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the owner of `secret_hash` is already known
+    pub fn is_bound(&self, secret_hash: &str) -> bool {
+        self.orders.contains_key(&normalize(secret_hash))
+    }
+
+    /// Records `order_id` as the owner of `secret_hash`
+    pub fn bind(&mut self, secret_hash: &str, order_id: &str) {
+        self.orders.insert(normalize(secret_hash), order_id.to_string());
+    }
+
+    /// Binds `secret_hash` to `order_id` unless another order holds it already
+    pub fn check(&mut self, secret_hash: &str, order_id: &str) -> BindingCheck {
+        let bound = self.orders.entry(normalize(secret_hash)).or_insert_with(|| order_id.to_string());
+        if bound == order_id {
+            BindingCheck::Bound
+        } else {
+            BindingCheck::Collision { bound_to: bound.clone() }
+        }
+    }
+}
+
+/// Lowercase hex without a `0x` prefix
+pub fn normalize(secret_hash: &str) -> String {
+    let secret_hash = secret_hash.trim();
+    secret_hash.strip_prefix("0x").unwrap_or(secret_hash).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET_HASH: &str = "a201be6510790b5b1ebab36fc5e0ee5db382f1afb7850d1444e80952c58edcd8";
+
+    #[test]
+    fn test_secret_hash_reused_by_another_order_collides() {
+        let mut bindings = SecretHashBindings::new();
+        assert_eq!(bindings.check(SECRET_HASH, "order-a"), BindingCheck::Bound);
+        // Both legs of the same order share the hash
+        assert_eq!(bindings.check(SECRET_HASH, "order-a"), BindingCheck::Bound);
+
+        let reused = format!("0x{}", SECRET_HASH.to_uppercase());
+        assert_eq!(
+            bindings.check(&reused, "order-b"),
+            BindingCheck::Collision { bound_to: "order-a".to_string() }
+        );
+        assert_eq!(bindings.check(&"00".repeat(32), "order-b"), BindingCheck::Bound);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::binding::normalize;
+use primitives::types::{MatchedOrder, Swap};
+use serde::{Deserialize, Serialize};
+
+/// Why a preimage revealed on one leg must not be carried over to the other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LegAnomaly {
+    /// The two swaps of the order lock to different hashes, so no single
+    /// preimage can redeem both
+    HashlockMismatch {
+        source_secret_hash: String,
+        destination_secret_hash: String,
+    },
+    /// The preimage does not hash to the hashlock of the swap named
+    PreimageMismatch { swap_id: String },
+    /// The revealed preimage is not valid hex
+    InvalidPreimage,
+}
+
+impl std::fmt::Display for LegAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LegAnomaly::HashlockMismatch { source_secret_hash, destination_secret_hash } => write!(
+                f,
+                "source hashlock {} differs from destination hashlock {}",
+                source_secret_hash, destination_secret_hash
+            ),
+            LegAnomaly::PreimageMismatch { swap_id } => {
+                write!(f, "preimage does not match the hashlock of swap {}", swap_id)
+            }
+            LegAnomaly::InvalidPreimage => write!(f, "preimage is not valid hex"),
+        }
+    }
+}
+
+fn preimage_matches(swap: &Swap, preimage: &[u8]) -> bool {
+    hex::encode(swap.hash_function.hash(preimage)) == normalize(&swap.secret_hash)
+}
+
+/// Checks that `preimage`, revealed when one swap of `order` was claimed, also
+/// unlocks the other swap: both legs must share a hashlock and the preimage must
+/// hash to it under each leg's hash function
+pub fn check_revealed_preimage(order: &MatchedOrder, preimage: &str) -> Result<(), LegAnomaly> {
+    let source = &order.source_swap;
+    let destination = &order.destination_swap;
+
+    if normalize(&source.secret_hash) != normalize(&destination.secret_hash) {
+        return Err(LegAnomaly::HashlockMismatch {
+            source_secret_hash: source.secret_hash.clone(),
+            destination_secret_hash: destination.secret_hash.clone(),
+        });
+    }
+
+    let preimage = hex::decode(preimage.trim_start_matches("0x")).map_err(|_| LegAnomaly::InvalidPreimage)?;
+    for swap in [source, destination] {
+        if !preimage_matches(swap, &preimage) {
+            return Err(LegAnomaly::PreimageMismatch { swap_id: swap.swap_id.clone() });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cross_chain_order, secret_hash, SECRET};
+    use primitives::types::HashFunction;
+
+    #[test]
+    fn test_matching_hashlocks_accept_preimage() {
+        let hash = secret_hash();
+        let order = cross_chain_order(&hash, &format!("0x{}", hash.to_uppercase()));
+        assert_eq!(check_revealed_preimage(&order, SECRET), Ok(()));
+    }
+
+    #[test]
+    fn test_mismatched_hashlocks_are_an_anomaly() {
+        let order = cross_chain_order(&"11".repeat(32), &secret_hash());
+        assert!(matches!(
+            check_revealed_preimage(&order, SECRET),
+            Err(LegAnomaly::HashlockMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_preimage_must_hash_under_each_legs_function() {
+        let mut order = cross_chain_order(&secret_hash(), &secret_hash());
+        order.source_swap.hash_function = HashFunction::Sha256d;
+        assert_eq!(
+            check_revealed_preimage(&order, SECRET),
+            Err(LegAnomaly::PreimageMismatch { swap_id: "evm-swap".to_string() })
+        );
+        assert_eq!(check_revealed_preimage(&order, "zz"), Err(LegAnomaly::InvalidPreimage));
+    }
+}
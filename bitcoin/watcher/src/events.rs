@@ -1,5 +1,12 @@
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use mongodb::bson::DateTime;
+use crate::notifications::{NotificationQueue, NotificationSink, SwapNotification, TerminalStatus};
 use crate::store::{BitcoinHtlcParams, HtlcStatus};
+use crate::crossleg::{check_revealed_preimage, LegAnomaly};
+
+/// Source tag recorded on event log entries emitted by this watcher
+pub const BITCOIN_WATCHER_SOURCE: &str = "bitcoin_watcher";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BitcoinEvent {
@@ -28,6 +35,11 @@ pub enum BitcoinEvent {
     HtlcExpired {
         id: String,
     },
+    /// A preimage revealed on this swap was withheld from the order's other leg
+    CrossLegAnomaly {
+        id: String,
+        anomaly: LegAnomaly,
+    },
     AddressBalanceChanged {
         address: String,
         old_balance: u64,
@@ -72,22 +84,65 @@ pub struct AddressInfo {
     pub unconfirmed_balance: u64,
 }
 
+/// A single entry in the durable `events` collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLogEntry {
+    pub event: BitcoinEvent,
+    pub source: String,
+    pub recorded_at: DateTime,
+}
+
+impl EventLogEntry {
+    pub fn new(event: BitcoinEvent, source: &str) -> Self {
+        Self {
+            event,
+            source: source.to_string(),
+            recorded_at: DateTime::now(),
+        }
+    }
+}
+
 pub trait EventHandler {
     async fn handle_event(&self, event: BitcoinEvent) -> anyhow::Result<()>;
 }
 
 pub struct BitcoinEventHandler {
     store: crate::store::BitcoinStore,
+    notifications: Option<NotificationQueue>,
 }
 
 impl BitcoinEventHandler {
     pub fn new(store: crate::store::BitcoinStore) -> Self {
-        Self { store }
+        Self { store, notifications: None }
+    }
+
+    /// Delivers terminal swap notifications to `notifier` from a background task
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationSink>) -> Self {
+        self.notifications = Some(NotificationQueue::spawn(notifier));
+        self
+    }
+
+    /// Queues a notification of a terminal swap state; delivery failures are logged, not propagated
+    fn notify_terminal(&self, notification: SwapNotification) {
+        if let Some(notifications) = &self.notifications {
+            notifications.push(notification);
+        }
+    }
+
+    /// Checks a preimage revealed on `swap_id` against both swaps of its order.
+    /// Swaps without a known order have no other leg and always pass.
+    async fn check_other_leg(&self, swap_id: &str, preimage: &str) -> anyhow::Result<Result<(), LegAnomaly>> {
+        Ok(match self.store.get_order_for_swap(swap_id).await? {
+            Some(order) => check_revealed_preimage(&order, preimage),
+            None => Ok(()),
+        })
     }
 }
 
 impl EventHandler for BitcoinEventHandler {
     async fn handle_event(&self, event: BitcoinEvent) -> anyhow::Result<()> {
+        self.store.append_event(EventLogEntry::new(event.clone(), BITCOIN_WATCHER_SOURCE)).await?;
+
         match event {
             BitcoinEvent::HtlcCreated { id, params } => {
                 self.store.add_htlc_params(id, params).await?;
@@ -100,11 +155,22 @@ impl EventHandler for BitcoinEventHandler {
                     id, amount_sats, confirmations, block_height);
             }
             BitcoinEvent::HtlcClaimed { id, tx_hash, preimage, block_height } => {
-                // Update database with redeem information
-                self.store.update_swap_redeem(&id, &tx_hash, &block_height.to_string(), &preimage).await?;
+                // The stored secret is what redeems the other leg, so only keep it
+                // once it is known to unlock that leg too
+                let secret = match self.check_other_leg(&id, &preimage).await? {
+                    Ok(()) => Some(preimage.as_str()),
+                    Err(anomaly) => {
+                        log::error!("Withholding preimage revealed on {}: {}", id, anomaly);
+                        let anomaly_event = BitcoinEvent::CrossLegAnomaly { id: id.clone(), anomaly };
+                        self.store.append_event(EventLogEntry::new(anomaly_event, BITCOIN_WATCHER_SOURCE)).await?;
+                        None
+                    }
+                };
+                self.store.update_swap_redeem(&id, &tx_hash, &block_height.to_string(), secret).await?;
                 
                 log::info!("HTLC claimed: {} with preimage: {} (tx: {}) at block {}", 
                     id, preimage, tx_hash, block_height);
+                self.notify_terminal(SwapNotification::new(&id, TerminalStatus::Redeemed, Some(tx_hash), Some(block_height)));
             }
             BitcoinEvent::HtlcRefunded { id, tx_hash, block_height } => {
                 // Update database with refund information
@@ -112,10 +178,16 @@ impl EventHandler for BitcoinEventHandler {
                 
                 log::info!("HTLC refunded: {} with tx: {} at block {}", 
                     id, tx_hash, block_height);
+                self.notify_terminal(SwapNotification::new(&id, TerminalStatus::Refunded, Some(tx_hash), Some(block_height)));
             }
             BitcoinEvent::HtlcExpired { id } => {
                 self.store.update_htlc_status(&id, HtlcStatus::Expired).await?;
+                self.store.mark_swap_expired(&id).await?;
                 log::info!("HTLC expired: {}", id);
+                self.notify_terminal(SwapNotification::new(&id, TerminalStatus::Expired, None, None));
+            }
+            BitcoinEvent::CrossLegAnomaly { id, anomaly } => {
+                log::error!("Cross-leg anomaly on {}: {}", id, anomaly);
             }
             BitcoinEvent::AddressBalanceChanged { address, old_balance, new_balance, tx_hash } => {
                 log::info!("Address {} balance changed: {} -> {} sats (tx: {})", 
@@ -125,3 +197,172 @@ impl EventHandler for BitcoinEventHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cross_chain_order, secret_hash, SECRET};
+    use crate::store::{BitcoinConfig, BitcoinNetwork, BitcoinStore};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// Forwards every delivered notification to the test
+    struct RecordingSink {
+        delivered: mpsc::UnboundedSender<SwapNotification>,
+    }
+
+    impl RecordingSink {
+        fn new() -> (Arc<Self>, mpsc::UnboundedReceiver<SwapNotification>) {
+            let (delivered, received) = mpsc::unbounded_channel();
+            (Arc::new(Self { delivered }), received)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl NotificationSink for RecordingSink {
+        async fn notify(&self, notification: &SwapNotification) -> anyhow::Result<()> {
+            self.delivered.send(notification.clone())?;
+            Ok(())
+        }
+    }
+
+    /// A webhook that never answers
+    struct StalledSink;
+
+    #[async_trait::async_trait]
+    impl NotificationSink for StalledSink {
+        async fn notify(&self, _notification: &SwapNotification) -> anyhow::Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    fn test_store() -> BitcoinStore {
+        BitcoinStore::in_memory(BitcoinConfig {
+            network: BitcoinNetwork::Regtest,
+            indexer_url: "http://localhost:3000".parse().unwrap(),
+            indexer_urls: Default::default(),
+            mongodb_uri: "mongodb://localhost:27017".to_string(),
+            database_name: "bitcoin_watcher_test".to_string(),
+            active_swaps_limit: crate::store::default_active_swaps_limit(),
+            max_tip_regression: crate::store::default_max_tip_regression(),
+            enforce_secret_hash_binding: false,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_each_handled_event_is_logged_once() {
+        let store = test_store();
+        let handler = BitcoinEventHandler::new(store.clone());
+
+        let events = vec![
+            BitcoinEvent::HtlcFunded {
+                id: "swap".to_string(),
+                tx_hash: "funding".to_string(),
+                amount_sats: 50000,
+                confirmations: 1,
+                block_height: 100,
+            },
+            BitcoinEvent::HtlcClaimed {
+                id: "swap".to_string(),
+                tx_hash: "claim".to_string(),
+                preimage: "00".to_string(),
+                block_height: 101,
+            },
+            BitcoinEvent::HtlcExpired { id: "other".to_string() },
+        ];
+
+        for event in events {
+            handler.handle_event(event).await.unwrap();
+        }
+
+        let log = store.get_events().await.unwrap();
+        assert_eq!(log.len(), 3);
+        assert!(log.iter().all(|entry| entry.source == BITCOIN_WATCHER_SOURCE));
+        assert!(matches!(&log[0].event, BitcoinEvent::HtlcFunded { tx_hash, .. } if tx_hash == "funding"));
+        assert!(matches!(&log[1].event, BitcoinEvent::HtlcClaimed { tx_hash, .. } if tx_hash == "claim"));
+        assert!(matches!(&log[2].event, BitcoinEvent::HtlcExpired { id } if id == "other"));
+    }
+
+    #[tokio::test]
+    async fn test_terminal_event_notifies_once() {
+        let (sink, mut received) = RecordingSink::new();
+        let handler = BitcoinEventHandler::new(test_store()).with_notifier(sink);
+
+        handler.handle_event(BitcoinEvent::HtlcFunded {
+            id: "swap".to_string(),
+            tx_hash: "funding".to_string(),
+            amount_sats: 50000,
+            confirmations: 1,
+            block_height: 100,
+        }).await.unwrap();
+
+        handler.handle_event(BitcoinEvent::HtlcClaimed {
+            id: "swap".to_string(),
+            tx_hash: "claim".to_string(),
+            preimage: "00".to_string(),
+            block_height: 101,
+        }).await.unwrap();
+
+        let notification = tokio::time::timeout(Duration::from_secs(1), received.recv()).await.unwrap().unwrap();
+        assert_eq!(notification.swap_id, "swap");
+        assert_eq!(notification.status, TerminalStatus::Redeemed);
+        assert_eq!(notification.tx_hash.as_deref(), Some("claim"));
+        assert_eq!(notification.block_height, Some(101));
+
+        drop(handler);
+        assert!(received.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_webhook_does_not_block_event_handling() {
+        let handler = BitcoinEventHandler::new(test_store()).with_notifier(Arc::new(StalledSink));
+
+        for id in ["first", "second"] {
+            let handled = tokio::time::timeout(
+                Duration::from_secs(1),
+                handler.handle_event(BitcoinEvent::HtlcExpired { id: id.to_string() }),
+            )
+            .await;
+            assert!(handled.unwrap().is_ok());
+        }
+    }
+
+    fn claim_of(id: &str, preimage: &str) -> BitcoinEvent {
+        BitcoinEvent::HtlcClaimed {
+            id: id.to_string(),
+            tx_hash: "claim".to_string(),
+            preimage: preimage.to_string(),
+            block_height: 101,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claim_with_matching_hashlocks_raises_no_anomaly() {
+        let store = test_store();
+        let hash = secret_hash();
+        store.insert_order(cross_chain_order(&hash, &hash)).await;
+        let handler = BitcoinEventHandler::new(store.clone());
+
+        handler.handle_event(claim_of("btc-swap", SECRET)).await.unwrap();
+
+        let log = store.get_events().await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert!(matches!(&log[0].event, BitcoinEvent::HtlcClaimed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_claim_with_mismatched_hashlocks_raises_anomaly() {
+        let store = test_store();
+        store.insert_order(cross_chain_order(&"11".repeat(32), &secret_hash())).await;
+        let handler = BitcoinEventHandler::new(store.clone());
+
+        handler.handle_event(claim_of("btc-swap", SECRET)).await.unwrap();
+
+        let log = store.get_events().await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(matches!(
+            &log[1].event,
+            BitcoinEvent::CrossLegAnomaly { id, anomaly: LegAnomaly::HashlockMismatch { .. } } if id == "btc-swap"
+        ));
+    }
+}
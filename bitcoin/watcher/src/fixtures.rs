@@ -0,0 +1,70 @@
+//! Orders shared by the watcher's tests. The Bitcoin leg uses real x-only keys,
+//! so its HTLC address can be derived.
+
+use mongodb::bson::DateTime;
+use primitives::types::{Chain, CreateOrder, HashFunction, MatchedOrder, Swap};
+
+pub const SECRET: &str = "7365637265742d707265696d6167652d666f722d63726f73732d6c6567732121";
+
+pub const INITIATOR_PUBKEY: &str = "460f2e8ff81fc4e0a8e6ce7796704e3829e3e3eedb8db9390bdc51f4f04cf0a6";
+pub const REDEEMER_PUBKEY: &str = "be4b9e8e8c0146b155d3ce35d0e3dfef1c99ef598b63e00524a912dd21480bce";
+
+/// SHA256 of `SECRET`
+pub fn secret_hash() -> String {
+    hex::encode(HashFunction::Sha256.hash(&hex::decode(SECRET).unwrap()))
+}
+
+fn swap(swap_id: &str, chain: Chain, secret_hash: &str) -> Swap {
+    Swap {
+        _id: None,
+        created_at: DateTime::now(),
+        swap_id: swap_id.to_string(),
+        chain,
+        asset: "btc".to_string(),
+        htlc_address: "primary".to_string(),
+        token_address: "primary".to_string(),
+        initiator: INITIATOR_PUBKEY.to_string(),
+        redeemer: REDEEMER_PUBKEY.to_string(),
+        filled_amount: "0".to_string(),
+        amount: "50000".to_string(),
+        timelock: 12,
+        secret_hash: secret_hash.to_string(),
+        hash_function: HashFunction::Sha256,
+        secret: None,
+        initiate_tx_hash: None,
+        redeem_tx_hash: None,
+        refund_tx_hash: None,
+        initiate_block_number: None,
+        redeem_block_number: None,
+        refund_block_number: None,
+        deposit_address: None,
+        has_deposit: false,
+        expired: false,
+    }
+}
+
+/// An Avalanche -> Bitcoin order with swaps `evm-swap` and `btc-swap`, locked to
+/// `source_hash` and `destination_hash`
+pub fn cross_chain_order(source_hash: &str, destination_hash: &str) -> MatchedOrder {
+    MatchedOrder {
+        _id: None,
+        created_at: DateTime::now(),
+        source_swap: swap("evm-swap", Chain::AvalancheTestnet, source_hash),
+        destination_swap: swap("btc-swap", Chain::BitcoinTestnet, destination_hash),
+        create_order: CreateOrder {
+            _id: None,
+            from: "avalanche_testnet:usdc".to_string(),
+            to: "bitcoin_testnet:btc".to_string(),
+            source_amount: "50000".to_string(),
+            destination_amount: "50000".to_string(),
+            initiator_source_address: INITIATOR_PUBKEY.to_string(),
+            initiator_destination_address: INITIATOR_PUBKEY.to_string(),
+            secret_hash: source_hash.to_string(),
+            nonce: "1".to_string(),
+            bitcoin_optional_recipient: None,
+            hash_function: HashFunction::Sha256,
+            create_id: Some("order".to_string()),
+        },
+        direction: None,
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+/// Result of observing the funding output of an HTLC address
+#[derive(Debug, Clone, PartialEq)]
+pub enum FundingUpdate {
+    /// First sight of an unconfirmed funding transaction
+    Provisional,
+    /// The unconfirmed funding transaction was replaced (e.g. via RBF)
+    Replaced { previous_txid: String },
+    /// The funding transaction confirmed and is now final
+    Confirmed,
+    /// Nothing changed since the last observation
+    Unchanged,
+}
+
+/// Tracks HTLC funding so that 0-conf transactions stay provisional until they
+/// confirm. A replaced funding transaction shows up as a different outpoint
+/// paying the same address, and only the confirmed txid is ever finalized.
+#[derive(Debug, Default)]
+pub struct FundingTracker {
+    pending: HashMap<String, String>, // address -> provisional funding txid
+    finalized: HashMap<String, String>, // address -> confirmed funding txid
+}
+
+impl FundingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Txid of the provisional funding transaction currently tracked for `address`
+    pub fn pending_txid(&self, address: &str) -> Option<&str> {
+        self.pending.get(address).map(String::as_str)
+    }
+
+    pub fn is_finalized(&self, address: &str) -> bool {
+        self.finalized.contains_key(address)
+    }
+
+    /// Records the funding output seen for `address` this cycle
+    pub fn observe(&mut self, address: &str, txid: &str, confirmed: bool) -> FundingUpdate {
+        if self.is_finalized(address) {
+            return FundingUpdate::Unchanged;
+        }
+
+        if confirmed {
+            self.pending.remove(address);
+            self.finalized.insert(address.to_string(), txid.to_string());
+            return FundingUpdate::Confirmed;
+        }
+
+        match self.pending.insert(address.to_string(), txid.to_string()) {
+            None => FundingUpdate::Provisional,
+            Some(previous_txid) if previous_txid == txid => FundingUpdate::Unchanged,
+            Some(previous_txid) => FundingUpdate::Replaced { previous_txid },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unconfirmed_funding_is_provisional() {
+        let mut tracker = FundingTracker::new();
+        assert_eq!(tracker.observe("addr", "a", false), FundingUpdate::Provisional);
+        assert_eq!(tracker.observe("addr", "a", false), FundingUpdate::Unchanged);
+        assert_eq!(tracker.pending_txid("addr"), Some("a"));
+        assert!(!tracker.is_finalized("addr"));
+    }
+
+    #[test]
+    fn test_rbf_replacement_before_confirmation() {
+        let mut tracker = FundingTracker::new();
+        assert_eq!(tracker.observe("addr", "original", false), FundingUpdate::Provisional);
+
+        // The original transaction is replaced by a fee bump paying the same address
+        assert_eq!(
+            tracker.observe("addr", "replacement", false),
+            FundingUpdate::Replaced { previous_txid: "original".to_string() }
+        );
+        assert_eq!(tracker.pending_txid("addr"), Some("replacement"));
+
+        // Only the replacement is finalized once it confirms
+        assert_eq!(tracker.observe("addr", "replacement", true), FundingUpdate::Confirmed);
+        assert_eq!(tracker.pending_txid("addr"), None);
+        assert!(tracker.is_finalized("addr"));
+        assert_eq!(tracker.observe("addr", "replacement", true), FundingUpdate::Unchanged);
+    }
+
+    #[test]
+    fn test_confirmed_on_first_sight() {
+        let mut tracker = FundingTracker::new();
+        assert_eq!(tracker.observe("addr", "a", true), FundingUpdate::Confirmed);
+        assert!(tracker.is_finalized("addr"));
+    }
+}
@@ -2,10 +2,23 @@ mod store;
 mod events;
 mod watcher;
 mod settings;
+mod reconcile;
+mod notifications;
+mod funding;
+mod trigger;
+mod tip;
+mod crossleg;
+mod binding;
+#[cfg(test)]
+mod fixtures;
 
 use store::BitcoinStore;
 use watcher::create_bitcoin_watcher;
 use settings::Settings;
+use notifications::WebhookSink;
+use trigger::{listen_event_stream, PushTrigger};
+use std::sync::Arc;
+use std::time::Duration;
 use anyhow::Result;
 use log::info;
 
@@ -38,9 +51,22 @@ async fn main() -> Result<()> {
     };
     
     let mut watcher = create_bitcoin_watcher(store)?;
-    // Start the watcher with configured polling interval
+    if let Some(notifications) = &settings.notifications {
+        info!("Sending terminal swap notifications to {}", notifications.webhook_url);
+        let sink = WebhookSink::new(&notifications.webhook_url, &notifications.webhook_secret, notifications.max_retries)?;
+        watcher = watcher.with_notifier(Arc::new(sink));
+    }
+    // Start the watcher, driven by pushed chain events when available
     info!("Starting watcher loop...");
-    watcher.start(settings.get_polling_interval()).await?;
+    match &settings.bitcoin.push_url {
+        Some(push_url) => {
+            let fallback = Duration::from_secs(settings.get_polling_interval() as u64);
+            let (mut trigger, sender) = PushTrigger::new(fallback);
+            tokio::spawn(listen_event_stream(push_url.clone(), sender));
+            watcher.run(&mut trigger).await?;
+        }
+        None => watcher.start(settings.get_polling_interval()).await?,
+    }
 
     Ok(())
 }
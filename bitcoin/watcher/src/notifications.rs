@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// Header carrying the hex HMAC-SHA256 of the request body
+pub const SIGNATURE_HEADER: &str = "X-Bridge-Signature";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalStatus {
+    Redeemed,
+    Refunded,
+    Expired,
+}
+
+/// Payload sent to integrators when a swap reaches a terminal state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapNotification {
+    pub swap_id: String,
+    pub status: TerminalStatus,
+    pub tx_hash: Option<String>,
+    pub block_height: Option<u64>,
+    pub timestamp: u64,
+}
+
+impl SwapNotification {
+    pub fn new(swap_id: &str, status: TerminalStatus, tx_hash: Option<String>, block_height: Option<u64>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            swap_id: swap_id.to_string(),
+            status,
+            tx_hash,
+            block_height,
+            timestamp,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait NotificationSink: Send + Sync {
+    async fn notify(&self, notification: &SwapNotification) -> Result<()>;
+}
+
+/// Delivers notifications to a sink on a background task, in the order they
+/// were queued, so a slow or retrying webhook never holds up event handling
+#[derive(Clone)]
+pub struct NotificationQueue {
+    sender: mpsc::UnboundedSender<SwapNotification>,
+}
+
+impl NotificationQueue {
+    pub fn spawn(sink: Arc<dyn NotificationSink>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<SwapNotification>();
+        tokio::spawn(async move {
+            while let Some(notification) = receiver.recv().await {
+                if let Err(e) = sink.notify(&notification).await {
+                    log::error!("Failed to deliver {:?} notification for {}: {}", notification.status, notification.swap_id, e);
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    /// Queues `notification` for delivery without waiting for it
+    pub fn push(&self, notification: SwapNotification) {
+        if let Err(e) = self.sender.send(notification) {
+            log::error!("Notification delivery task has stopped, dropping notification for {}", e.0.swap_id);
+        }
+    }
+}
+
+/// POSTs signed JSON notifications to a webhook, retrying with exponential backoff
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+    max_retries: u32,
+    base_backoff: Duration,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, secret: &str, max_retries: u32) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            secret: secret.to_string(),
+            max_retries,
+            base_backoff: Duration::from_millis(500),
+        })
+    }
+
+    #[cfg(test)]
+    fn with_base_backoff(mut self, backoff: Duration) -> Self {
+        self.base_backoff = backoff;
+        self
+    }
+}
+
+/// Hex HMAC-SHA256 of `body` keyed with `secret`, so receivers can verify authenticity
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[async_trait::async_trait]
+impl NotificationSink for WebhookSink {
+    async fn notify(&self, notification: &SwapNotification) -> Result<()> {
+        let body = serde_json::to_vec(notification)?;
+        let signature = sign_payload(&self.secret, &body);
+
+        let mut last_error = None;
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.base_backoff * 2u32.pow(attempt - 1)).await;
+            }
+
+            match self
+                .client
+                .post(&self.url)
+                .header("Content-Type", "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => return Ok(()),
+                Ok(resp) => last_error = Some(anyhow!("webhook returned {}", resp.status())),
+                Err(e) => last_error = Some(anyhow!("webhook request error: {}", e)),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("webhook delivery failed")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::mock_server::{unreachable_url, MockServer};
+
+    #[test]
+    fn test_signature_is_deterministic_hmac() {
+        let a = sign_payload("secret", b"{}");
+        assert_eq!(a, sign_payload("secret", b"{}"));
+        assert_ne!(a, sign_payload("other", b"{}"));
+        assert_eq!(a.len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_signed_payload() {
+        let server = MockServer::serving("").await;
+        let url = format!("{}/hook", server.url());
+
+        let sink = WebhookSink::new(&url, "secret", 0).unwrap();
+        let notification = SwapNotification::new("swap", TerminalStatus::Redeemed, Some("tx".to_string()), Some(100));
+        sink.notify(&notification).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/hook"));
+
+        let received: SwapNotification = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(received, notification);
+
+        let signature = sign_payload("secret", request.body.as_bytes());
+        assert_eq!(request.header(SIGNATURE_HEADER), Some(signature.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_gives_up_after_retries() {
+        let url = format!("{}/hook", unreachable_url().await);
+
+        let sink = WebhookSink::new(&url, "secret", 2)
+            .unwrap()
+            .with_base_backoff(Duration::from_millis(1));
+        let notification = SwapNotification::new("swap", TerminalStatus::Expired, None, None);
+        assert!(sink.notify(&notification).await.is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use bitcoin::XOnlyPublicKey;
+use primitives::htlc::BitcoinHTLC;
+use primitives::types::Swap;
+
+/// Outcome of comparing a swap's stored deposit address with the locally derived HTLC address
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepositAddressCheck {
+    Match,
+    Mismatch { stored: String, derived: String },
+    Missing,
+}
+
+/// Derives the HTLC address for a Bitcoin swap from its own parameters
+pub fn derive_htlc_address(swap: &Swap) -> Result<String> {
+    let network = swap
+        .chain
+        .bitcoin_network()
+        .ok_or_else(|| anyhow!("Swap {} is not on a Bitcoin chain", swap.swap_id))?;
+
+    // BitcoinHTLC slices raw key bytes, so reject anything that isn't an x-only pubkey up front
+    XOnlyPublicKey::from_str(&swap.initiator)
+        .map_err(|e| anyhow!("Invalid initiator pubkey {}: {}", swap.initiator, e))?;
+    XOnlyPublicKey::from_str(&swap.redeemer)
+        .map_err(|e| anyhow!("Invalid redeemer pubkey {}: {}", swap.redeemer, e))?;
+
+    let htlc = BitcoinHTLC::new(
+        swap.secret_hash.clone(),
+        swap.initiator.clone(),
+        swap.redeemer.clone(),
+        swap.timelock as i64,
+        network,
+    )?
+    .with_hash_function(swap.hash_function);
+    Ok(htlc.address()?.to_string())
+}
+
+/// Checks that the deposit address stored by the orderbook is the address this watcher monitors
+pub fn check_deposit_address(swap: &Swap) -> Result<DepositAddressCheck> {
+    let stored = match &swap.deposit_address {
+        Some(address) if !address.is_empty() => address,
+        _ => return Ok(DepositAddressCheck::Missing),
+    };
+
+    let derived = derive_htlc_address(swap)?;
+    if *stored == derived {
+        Ok(DepositAddressCheck::Match)
+    } else {
+        Ok(DepositAddressCheck::Mismatch {
+            stored: stored.clone(),
+            derived,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cross_chain_order, secret_hash};
+
+    fn bitcoin_swap(deposit_address: Option<String>) -> Swap {
+        let mut swap = cross_chain_order(&secret_hash(), &secret_hash()).destination_swap;
+        swap.deposit_address = deposit_address;
+        swap
+    }
+
+    #[test]
+    fn test_matching_deposit_address() {
+        let derived = derive_htlc_address(&bitcoin_swap(None)).unwrap();
+        let swap = bitcoin_swap(Some(derived));
+        assert_eq!(check_deposit_address(&swap).unwrap(), DepositAddressCheck::Match);
+    }
+
+    #[test]
+    fn test_mismatched_deposit_address_is_flagged() {
+        let stored = "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c".to_string();
+        let swap = bitcoin_swap(Some(stored.clone()));
+        match check_deposit_address(&swap).unwrap() {
+            DepositAddressCheck::Mismatch { stored: s, derived } => {
+                assert_eq!(s, stored);
+                assert_ne!(derived, stored);
+            }
+            other => panic!("expected mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_deposit_address() {
+        assert_eq!(check_deposit_address(&bitcoin_swap(None)).unwrap(), DepositAddressCheck::Missing);
+    }
+
+    #[test]
+    fn test_non_pubkey_party_is_an_error() {
+        let mut swap = bitcoin_swap(Some("tb1p".to_string()));
+        swap.initiator = "0xe62a2b235f7bB86C1122313153824D54E6137e77".to_string();
+        assert!(check_deposit_address(&swap).is_err());
+    }
+}
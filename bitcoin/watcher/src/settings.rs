@@ -1,22 +1,56 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use anyhow::{Result, anyhow};
-use crate::store::{BitcoinNetwork, BitcoinConfig};
+use primitives::urls::IndexerUrl;
+use crate::store::{default_active_swaps_limit, default_max_tip_regression, BitcoinNetwork, BitcoinConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub bitcoin: BitcoinSettings,
+    #[serde(default)]
+    pub notifications: Option<NotificationSettings>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BitcoinSettings {
     pub network: String,
-    pub indexer_url: String,
+    pub indexer_url: IndexerUrl,
+    /// Optional per-network indexer URLs, e.g. `signet = "https://mempool.space/signet/api"`
+    #[serde(default)]
+    pub indexer_urls: HashMap<String, IndexerUrl>,
     pub polling_interval: u32,
+    /// Optional server-sent events endpoint announcing new blocks. When set, each
+    /// event triggers a watch cycle immediately and `polling_interval` becomes the fallback
+    #[serde(default)]
+    pub push_url: Option<String>,
     pub log_level: String,
     pub mongodb_uri: String,
     pub database_name: String,
+    /// Maximum number of orders processed per watch cycle
+    #[serde(default = "default_active_swaps_limit")]
+    pub active_swaps_limit: i64,
+    /// Blocks the indexer tip may fall back before the watcher stops trusting it
+    #[serde(default = "default_max_tip_regression")]
+    pub max_tip_regression: u64,
+    /// Skip swaps reusing another order's secret hash rather than only logging them
+    #[serde(default)]
+    pub enforce_secret_hash_binding: bool,
+}
+
+/// Webhook notified when a swap reaches a terminal state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub webhook_url: String,
+    /// Shared secret used to HMAC-sign each payload
+    pub webhook_secret: String,
+    #[serde(default = "default_webhook_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_webhook_max_retries() -> u32 {
+    3
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,13 +79,25 @@ impl Settings {
 
         let config_content = fs::read_to_string(config_path)?;
         let settings: Settings = toml::from_str(&config_content)?;
-        
+        settings.validate()?;
+
         Ok(settings)
     }
 
+    /// Rejects values the watcher can't run with
+    pub fn validate(&self) -> Result<()> {
+        if self.bitcoin.active_swaps_limit <= 0 {
+            return Err(anyhow!(
+                "bitcoin.active_swaps_limit must be positive, got {}",
+                self.bitcoin.active_swaps_limit
+            ));
+        }
+        Ok(())
+    }
+
     pub fn load_or_default() -> Self {
-        Self::load().unwrap_or_else(|_| {
-            log::warn!("Failed to load Settings.toml, using default configuration");
+        Self::load().unwrap_or_else(|e| {
+            log::warn!("Failed to load Settings.toml ({}), using default configuration", e);
             Self::default()
         })
     }
@@ -60,6 +106,7 @@ impl Settings {
         let network = match self.bitcoin.network.as_str() {
             "mainnet" => BitcoinNetwork::Mainnet,
             "testnet" => BitcoinNetwork::Testnet,
+            "signet" => BitcoinNetwork::Signet,
             "regtest" => BitcoinNetwork::Regtest,
             _ => {
                 log::warn!("Unknown network '{}', defaulting to testnet", self.bitcoin.network);
@@ -70,8 +117,12 @@ impl Settings {
         BitcoinConfig {
             network,
             indexer_url: self.bitcoin.indexer_url.clone(),
+            indexer_urls: self.bitcoin.indexer_urls.clone(),
             mongodb_uri: self.bitcoin.mongodb_uri.clone(),
             database_name: self.bitcoin.database_name.clone(),
+            active_swaps_limit: self.bitcoin.active_swaps_limit,
+            max_tip_regression: self.bitcoin.max_tip_regression,
+            enforce_secret_hash_binding: self.bitcoin.enforce_secret_hash_binding,
         }
     }
 
@@ -89,12 +140,33 @@ impl Default for Settings {
         Self {
             bitcoin: BitcoinSettings {
                 network: "testnet".to_string(),
-                indexer_url: "https://blockstream.info/testnet/api".to_string(),
+                indexer_url: "https://blockstream.info/testnet/api".parse().unwrap(),
+                indexer_urls: HashMap::new(),
                 polling_interval: 30,
+                push_url: None,
                 log_level: "info".to_string(),
                 mongodb_uri: "mongodb://localhost:27017".to_string(),
                 database_name: "bitcoin_watcher".to_string(),
-            }
+                active_swaps_limit: default_active_swaps_limit(),
+                max_tip_regression: default_max_tip_regression(),
+                enforce_secret_hash_binding: false,
+            },
+            notifications: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_positive_active_swaps_limit_is_rejected() {
+        let mut settings = Settings::default();
+        assert!(settings.validate().is_ok());
+        for limit in [0, -1] {
+            settings.bitcoin.active_swaps_limit = limit;
+            assert!(settings.validate().is_err());
         }
     }
 }
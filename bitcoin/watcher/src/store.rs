@@ -1,16 +1,19 @@
 use primitives::types::MatchedOrder;
 use primitives::types::Swap;
 use primitives::types::Chain;
+use primitives::urls::IndexerUrl;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use anyhow::Result;
 use std::clone::Clone;
-use mongodb::{Client, Collection, Database};
-use mongodb::bson::{doc, DateTime};
-use chrono::Utc;
+use mongodb::{Client, Collection, Database, IndexModel};
+use mongodb::options::{FindOneOptions, FindOptions};
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document, Regex};
 use futures::stream::StreamExt;
+use crate::binding::normalize;
+use crate::events::EventLogEntry;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BitcoinHtlcParams {
@@ -36,21 +39,103 @@ pub enum HtlcStatus {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BitcoinConfig {
     pub network: BitcoinNetwork,
-    pub indexer_url: String,
+    pub indexer_url: IndexerUrl,
+    /// Per-network indexer overrides keyed by network name ("testnet", "signet", ...)
+    #[serde(default)]
+    pub indexer_urls: HashMap<String, IndexerUrl>,
     pub mongodb_uri: String,
     pub database_name: String,
+    /// Maximum number of orders fetched per watch cycle
+    #[serde(default = "default_active_swaps_limit")]
+    pub active_swaps_limit: i64,
+    /// Blocks an indexer's tip may fall back (a reorg) before it is distrusted
+    #[serde(default = "default_max_tip_regression")]
+    pub max_tip_regression: u64,
+    /// Stop watching swaps whose secret hash was already used by another order,
+    /// instead of only reporting the collision
+    #[serde(default)]
+    pub enforce_secret_hash_binding: bool,
+}
+
+pub fn default_active_swaps_limit() -> i64 {
+    500
+}
+
+pub fn default_max_tip_regression() -> u64 {
+    6
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BitcoinNetwork {
     Mainnet,
     Testnet,
+    Signet,
     Regtest,
 }
 
+/// Bitcoin chains the watcher monitors, as stored in `swap.chain`
+const BITCOIN_CHAINS: [&str; 2] = ["bitcoin_testnet", "bitcoin_signet"];
+
+/// Position of the last order of a batch in the `(created_at, _id)` ordering
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ActiveSwapsCursor {
+    /// `created_at` as stored: an RFC 3339 string
+    pub created_at: String,
+    pub id: ObjectId,
+}
+
+impl ActiveSwapsCursor {
+    fn of(order: &MatchedOrder) -> Option<Self> {
+        Some(Self { created_at: created_at_key(&order.created_at), id: order._id? })
+    }
+}
+
+/// One batch of active swaps and where the next cycle resumes
+#[derive(Debug)]
+pub struct ActiveSwapsPage {
+    pub swaps: Vec<Swap>,
+    /// Create id of the order each swap belongs to, keyed by swap id. Swaps of
+    /// orders with neither a create id nor an `_id` are missing.
+    pub order_ids: HashMap<String, String>,
+    /// Last order of a full batch, or `None` once the end was reached and the next cycle starts over
+    pub next: Option<ActiveSwapsCursor>,
+}
+
+impl BitcoinConfig {
+    /// Returns the indexer URL to use for swaps on `chain`, falling back to `indexer_url`
+    pub fn indexer_url_for(&self, chain: &Chain) -> &str {
+        let network = match chain {
+            Chain::BitcoinTestnet => Some("testnet"),
+            Chain::BitcoinSignet => Some("signet"),
+            _ => None,
+        };
+
+        network
+            .and_then(|network| self.indexer_urls.get(network))
+            .unwrap_or(&self.indexer_url)
+            .as_str()
+    }
+
+    /// All distinct indexer URLs this config may route to
+    pub fn all_indexer_urls(&self) -> Vec<String> {
+        let mut urls = vec![self.indexer_url.to_string()];
+        for url in self.indexer_urls.values() {
+            let url = url.to_string();
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        urls
+    }
+}
+
 #[derive(Clone)]
 pub struct BitcoinStore {
     htlc_params: Arc<RwLock<HashMap<String, BitcoinHtlcParams>>>,
+    // Event log kept in memory when MongoDB is not connected
+    event_log: Arc<RwLock<Vec<EventLogEntry>>>,
+    // Orders kept in memory when MongoDB is not connected
+    orders: Arc<RwLock<Vec<MatchedOrder>>>,
     config: BitcoinConfig,
     db: Option<Database>,
 }
@@ -59,15 +144,32 @@ impl BitcoinStore {
     pub async fn new(config: BitcoinConfig) -> Result<Self> {
         let client = Client::with_uri_str(&config.mongodb_uri).await?;
         let db = client.database(&config.database_name);
+
+        // Backs the stable ordering used to page through active swaps
+        let ordering_index = IndexModel::builder().keys(doc! { "created_at": 1, "_id": 1 }).build();
+        if let Err(e) = db.collection::<MatchedOrder>("orders").create_index(ordering_index).await {
+            log::warn!("Failed to create active swaps ordering index: {}", e);
+        }
         
         Ok(Self {
             htlc_params: Arc::new(RwLock::new(HashMap::new())),
+            event_log: Arc::new(RwLock::new(Vec::new())),
+            orders: Arc::new(RwLock::new(Vec::new())),
             config,
             db: Some(db),
         })
     }
 
-
+    #[cfg(test)]
+    pub fn in_memory(config: BitcoinConfig) -> Self {
+        Self {
+            htlc_params: Arc::new(RwLock::new(HashMap::new())),
+            event_log: Arc::new(RwLock::new(Vec::new())),
+            orders: Arc::new(RwLock::new(Vec::new())),
+            config,
+            db: None,
+        }
+    }
 
     fn get_swaps_collection(&self) -> Result<Collection<MatchedOrder>> {
         if let Some(db) = &self.db {
@@ -77,6 +179,89 @@ impl BitcoinStore {
         }
     }
 
+    fn get_events_collection(&self) -> Result<Collection<EventLogEntry>> {
+        if let Some(db) = &self.db {
+            Ok(db.collection::<EventLogEntry>("events"))
+        } else {
+            Err(anyhow::anyhow!("MongoDB not connected"))
+        }
+    }
+
+    /// Appends an entry to the durable event log
+    pub async fn append_event(&self, entry: EventLogEntry) -> Result<()> {
+        match self.get_events_collection() {
+            Ok(collection) => {
+                collection.insert_one(&entry).await?;
+            }
+            Err(_) => {
+                self.event_log.write().await.push(entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the event log in insertion order
+    #[cfg(test)]
+    pub async fn get_events(&self) -> Result<Vec<EventLogEntry>> {
+        match self.get_events_collection() {
+            Ok(collection) => {
+                let mut cursor = collection.find(doc! {}).sort(doc! { "recorded_at": 1 }).await?;
+                let mut events = Vec::new();
+                while let Some(entry) = cursor.next().await {
+                    events.push(entry?);
+                }
+                Ok(events)
+            }
+            Err(_) => Ok(self.event_log.read().await.clone()),
+        }
+    }
+
+    /// Adds an order to the in-memory store used when MongoDB is not connected
+    #[cfg(test)]
+    pub async fn insert_order(&self, mut order: MatchedOrder) {
+        order._id.get_or_insert_with(ObjectId::new);
+        self.orders.write().await.push(order);
+    }
+
+    /// Returns the matched order with `swap_id` on either side
+    pub async fn get_order_for_swap(&self, swap_id: &str) -> Result<Option<MatchedOrder>> {
+        match self.get_swaps_collection() {
+            Ok(collection) => Ok(collection.find_one(swap_filter(swap_id)).await?),
+            Err(_) => Ok(self
+                .orders
+                .read()
+                .await
+                .iter()
+                .find(|order| order.source_swap.swap_id == swap_id || order.destination_swap.swap_id == swap_id)
+                .cloned()),
+        }
+    }
+
+    /// Create id of the earliest created order with a swap locked to `secret_hash`,
+    /// which is the order the hash is bound to
+    pub async fn secret_hash_owner(&self, secret_hash: &str) -> Result<Option<String>> {
+        let owner = match self.get_swaps_collection() {
+            Ok(collection) => {
+                let options = FindOneOptions::builder().sort(doc! { "created_at": 1, "_id": 1 }).build();
+                collection.find_one(secret_hash_filter(secret_hash)).with_options(options).await?
+            }
+            Err(_) => {
+                let secret_hash = normalize(secret_hash);
+                self.orders
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|order| {
+                        normalize(&order.source_swap.secret_hash) == secret_hash
+                            || normalize(&order.destination_swap.secret_hash) == secret_hash
+                    })
+                    .min_by_key(|order| ActiveSwapsCursor::of(order))
+                    .cloned()
+            }
+        };
+        Ok(owner.as_ref().and_then(order_id))
+    }
+
     pub async fn add_htlc_params(&self, id: String, params: BitcoinHtlcParams) -> Result<()> {
         let mut htlc_params = self.htlc_params.write().await;
         htlc_params.insert(id.clone(), params);
@@ -89,6 +274,13 @@ impl BitcoinStore {
         Ok(htlc_params.get(id).cloned())
     }
 
+    /// Forgets the HTLC params stored for `address`
+    pub async fn remove_htlc_params(&self, address: &str) -> Result<()> {
+        let mut htlc_params = self.htlc_params.write().await;
+        htlc_params.retain(|id, params| id != address && params.address != address);
+        Ok(())
+    }
+
     pub async fn update_htlc_status(&self, id: &str, status: HtlcStatus) -> Result<()> {
         let mut htlc_params = self.htlc_params.write().await;
         if let Some(params) = htlc_params.get_mut(id) {
@@ -121,203 +313,398 @@ impl BitcoinStore {
         Ok(())
     }
 
-    pub async fn get_active_swaps(&self) -> Result<Vec<Swap>> {
-        match self.get_swaps_collection() {
+    /// Returns the Bitcoin swaps of up to `active_swaps_limit` active orders, oldest
+    /// first, after `after`. Callers pass back `next` so every order is visited before
+    /// the batches wrap around to the start again. Paging on `(created_at, _id)` rather
+    /// than an offset means orders settling between cycles don't shift later ones out
+    /// of the next batch.
+    pub async fn get_active_swaps(&self, after: Option<&ActiveSwapsCursor>) -> Result<ActiveSwapsPage> {
+        let limit = self.config.active_swaps_limit;
+        let orders = match self.get_swaps_collection() {
             Ok(collection) => {
-            // testing number of matched orders we receive
-            let count = collection.count_documents(doc! {}).await?;
-            tracing::info!("Number of matched orders: {}", count);
-
-            // Query for MatchedOrder documents where either source_swap or destination_swap is Bitcoin
-            // Pick up swaps that have no inits OR have inits but no redeems/refunds
-            let filter = doc! {
-                "$or": [
-                    {
-                        "source_swap.chain": "bitcoin_testnet",
-                        "source_swap.asset": "btc",
-                        "$and": [
-                            {
-                                "$or": [
-                                    { "source_swap.redeem_block_number": { "$exists": false } },
-                                    { "source_swap.redeem_block_number": null }
-                                ]
-                            },
-                            {
-                                "$or": [
-                                    { "source_swap.refund_block_number": { "$exists": false } },
-                                    { "source_swap.refund_block_number": null }
-                                ]
-                            }
-                        ]
-                    },
-                    {
-                        "destination_swap.chain": "bitcoin_testnet",
-                        "destination_swap.asset": "btc",
-                        "$and": [
-                            {
-                                "$or": [
-                                    { "destination_swap.redeem_block_number": { "$exists": false } },
-                                    { "destination_swap.redeem_block_number": null }
-                                ]
-                            },
-                            {
-                                "$or": [
-                                    { "destination_swap.refund_block_number": { "$exists": false } },
-                                    { "destination_swap.refund_block_number": null }
-                                ]
-                            }
-                        ]
-                    }
-                ]
-            };
-            
-            let mut cursor: mongodb::Cursor<MatchedOrder> = collection.find(filter).await?;
-            let mut swaps = Vec::new();
-            
-            while let Some(matched_order) = cursor.next().await {
-                let matched_order = matched_order?;
-                // Check if source_swap is Bitcoin
-                if matches!(matched_order.source_swap.chain, Chain::BitcoinTestnet) {
-                    swaps.push(matched_order.source_swap);
-                }
-                
-                // Check if destination_swap is Bitcoin
-                if matches!(matched_order.destination_swap.chain, Chain::BitcoinTestnet) {
-                    swaps.push(matched_order.destination_swap);
+                let mut cursor = collection
+                    .find(active_swaps_filter(after))
+                    .with_options(active_swaps_options(limit))
+                    .await?;
+                let mut orders = Vec::new();
+                while let Some(matched_order) = cursor.next().await {
+                    orders.push(matched_order?);
                 }
+                orders
             }
-            
-            log::info!("Found {} active Bitcoin swaps from MongoDB", swaps.len());
-            return Ok(swaps);
+            Err(_) => {
+                let mut orders: Vec<MatchedOrder> = self
+                    .orders
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|order| is_active(&order.source_swap) || is_active(&order.destination_swap))
+                    .filter(|order| after.is_none_or(|after| ActiveSwapsCursor::of(order).is_some_and(|c| c > *after)))
+                    .cloned()
+                    .collect();
+                orders.sort_by_key(ActiveSwapsCursor::of);
+                orders.truncate(limit.max(0) as usize);
+                orders
             }
-            Err(e) => {
-                log::warn!("Error getting active swaps: {}", e);
-                return Err(e);
+        };
+
+        let next = if (orders.len() as i64) < limit { None } else { orders.last().and_then(ActiveSwapsCursor::of) };
+        let mut swaps = Vec::new();
+        let mut order_ids = HashMap::new();
+        for matched_order in orders {
+            let order_id = order_id(&matched_order);
+            for swap in [matched_order.source_swap, matched_order.destination_swap] {
+                if swap.chain.is_bitcoin() {
+                    if let Some(order_id) = &order_id {
+                        order_ids.insert(swap.swap_id.clone(), order_id.clone());
+                    }
+                    swaps.push(swap);
+                }
             }
         }
+
+        log::info!("Found {} active Bitcoin swaps", swaps.len());
+        Ok(ActiveSwapsPage { swaps, order_ids, next })
+    }
+
+    /// Applies `fields` to whichever embedded swap of the matching order has `swap_id`
+    async fn update_swap_fields(&self, swap_id: &str, fields: Document) -> Result<Option<u64>> {
+        let collection = self.get_swaps_collection()?;
+        let result = collection
+            .update_one(swap_filter(swap_id), swap_update_pipeline(swap_id, fields))
+            .await?;
+
+        if result.matched_count == 0 {
+            return Ok(None);
+        }
+        Ok(Some(result.modified_count))
     }
 
     pub async fn update_swap_initiate(&self, swap_id: &str, initiate_tx_hash: &str, filled_amount: &str, initiate_block_number: &str) -> Result<()> {
-        if let Ok(collection) = self.get_swaps_collection() {
-            // Find the MatchedOrder document that contains this swap_id
-            let filter = doc! {
-                "$or": [
-                    { "source_swap.swap_id": swap_id },
-                    { "destination_swap.swap_id": swap_id }
-                ]
-            };
-            
-            // First, find the document to determine which swap to update
-            if let Some(matched_order) = collection.find_one(filter.clone()).await? {
-                let update = if matched_order.source_swap.swap_id == swap_id {
-                    doc! {
-                        "$set": {
-                            "source_swap.initiate_tx_hash": initiate_tx_hash,
-                            "source_swap.filled_amount": filled_amount,
-                            "source_swap.initiate_block_number": initiate_block_number
-                        }
-                    }
-                } else {
-                    doc! {
-                        "$set": {
-                            "destination_swap.initiate_tx_hash": initiate_tx_hash,
-                            "destination_swap.filled_amount": filled_amount,
-                            "destination_swap.initiate_block_number": initiate_block_number
-                        }
-                    }
-                };
-                
-                let result = collection.update_one(filter, update).await?;
-                log::info!("Updated swap {} initiate in MongoDB: {} documents modified", swap_id, result.modified_count);
-            } else {
-                log::warn!("No MatchedOrder found for swap_id: {}", swap_id);
-            }
-        } else {
+        if self.db.is_none() {
             log::info!("Updated swap {} initiate: tx_hash={}, amount={}, block={}", 
                 swap_id, initiate_tx_hash, filled_amount, initiate_block_number);
+            return Ok(());
+        }
+
+        let fields = doc! {
+            "initiate_tx_hash": initiate_tx_hash,
+            "filled_amount": filled_amount,
+            "initiate_block_number": initiate_block_number
+        };
+        match self.update_swap_fields(swap_id, fields).await? {
+            Some(modified) => log::info!("Updated swap {} initiate in MongoDB: {} documents modified", swap_id, modified),
+            None => log::warn!("No MatchedOrder found for swap_id: {}", swap_id),
         }
         Ok(())
     }
 
-    pub async fn update_swap_redeem(&self, swap_id: &str, redeem_tx_hash: &str, redeem_block_number: &str, secret: &str) -> Result<()> {
-        if let Ok(collection) = self.get_swaps_collection() {
-            // Find the MatchedOrder document that contains this swap_id
-            let filter = doc! {
-                "$or": [
-                    { "source_swap.swap_id": swap_id },
-                    { "destination_swap.swap_id": swap_id }
-                ]
-            };
-            
-            // First, find the document to determine which swap to update
-            if let Some(matched_order) = collection.find_one(filter.clone()).await? {
-                let update = if matched_order.source_swap.swap_id == swap_id {
-                    doc! {
-                        "$set": {
-                            "source_swap.redeem_tx_hash": redeem_tx_hash,
-                            "source_swap.redeem_block_number": redeem_block_number,
-                            "source_swap.secret": secret
-                        }
-                    }
-                } else {
-                    doc! {
-                        "$set": {
-                            "destination_swap.redeem_tx_hash": redeem_tx_hash,
-                            "destination_swap.redeem_block_number": redeem_block_number,
-                            "destination_swap.secret": secret
-                        }
-                    }
-                };
-                
-                let result = collection.update_one(filter, update).await?;
-                log::info!("Updated swap {} redeem in MongoDB: {} documents modified", swap_id, result.modified_count);
-            } else {
-                log::warn!("No MatchedOrder found for swap_id: {}", swap_id);
-            }
-        } else {
-            log::info!("Updated swap {} redeem: tx_hash={}, block={}, secret={}", 
+    /// Records a redeem. `secret` is `None` when the revealed preimage must not be
+    /// handed to the other leg, leaving the stored secret untouched.
+    pub async fn update_swap_redeem(&self, swap_id: &str, redeem_tx_hash: &str, redeem_block_number: &str, secret: Option<&str>) -> Result<()> {
+        if self.db.is_none() {
+            log::info!("Updated swap {} redeem: tx_hash={}, block={}, secret={:?}", 
                 swap_id, redeem_tx_hash, redeem_block_number, secret);
+            return Ok(());
+        }
+
+        let mut fields = doc! {
+            "redeem_tx_hash": redeem_tx_hash,
+            "redeem_block_number": redeem_block_number
+        };
+        if let Some(secret) = secret {
+            fields.insert("secret", secret);
+        }
+        match self.update_swap_fields(swap_id, fields).await? {
+            Some(modified) => log::info!("Updated swap {} redeem in MongoDB: {} documents modified", swap_id, modified),
+            None => log::warn!("No MatchedOrder found for swap_id: {}", swap_id),
         }
         Ok(())
     }
 
-    pub async fn update_swap_refund(&self, swap_id: &str, refund_tx_hash: &str, refund_block_number: &str) -> Result<()> {
-        if let Ok(collection) = self.get_swaps_collection() {
-            // Find the MatchedOrder document that contains this swap_id
-            let filter = doc! {
-                "$or": [
-                    { "source_swap.swap_id": swap_id },
-                    { "destination_swap.swap_id": swap_id }
-                ]
-            };
-            
-            // First, find the document to determine which swap to update
-            if let Some(matched_order) = collection.find_one(filter.clone()).await? {
-                let update = if matched_order.source_swap.swap_id == swap_id {
-                    doc! {
-                        "$set": {
-                            "source_swap.refund_tx_hash": refund_tx_hash,
-                            "source_swap.refund_block_number": refund_block_number
-                        }
+    /// Records that the order expired before `swap_id`'s HTLC was funded
+    pub async fn mark_swap_expired(&self, swap_id: &str) -> Result<()> {
+        if self.db.is_none() {
+            for order in self.orders.write().await.iter_mut() {
+                for swap in [&mut order.source_swap, &mut order.destination_swap] {
+                    if swap.swap_id == swap_id {
+                        swap.expired = true;
                     }
-                } else {
-                    doc! {
-                        "$set": {
-                            "destination_swap.refund_tx_hash": refund_tx_hash,
-                            "destination_swap.refund_block_number": refund_block_number
-                        }
-                    }
-                };
-                
-                let result = collection.update_one(filter, update).await?;
-                log::info!("Updated swap {} refund in MongoDB: {} documents modified", swap_id, result.modified_count);
-            } else {
-                log::warn!("No MatchedOrder found for swap_id: {}", swap_id);
+                }
             }
-        } else {
+            return Ok(());
+        }
+
+        match self.update_swap_fields(swap_id, doc! { "expired": true }).await? {
+            Some(modified) => log::info!("Marked swap {} expired in MongoDB: {} documents modified", swap_id, modified),
+            None => log::warn!("No MatchedOrder found for swap_id: {}", swap_id),
+        }
+        Ok(())
+    }
+
+    pub async fn update_swap_refund(&self, swap_id: &str, refund_tx_hash: &str, refund_block_number: &str) -> Result<()> {
+        if self.db.is_none() {
             log::info!("Updated swap {} refund: tx_hash={}, block={}", 
                 swap_id, refund_tx_hash, refund_block_number);
+            return Ok(());
+        }
+
+        let fields = doc! {
+            "refund_tx_hash": refund_tx_hash,
+            "refund_block_number": refund_block_number
+        };
+        match self.update_swap_fields(swap_id, fields).await? {
+            Some(modified) => log::info!("Updated swap {} refund in MongoDB: {} documents modified", swap_id, modified),
+            None => log::warn!("No MatchedOrder found for swap_id: {}", swap_id),
         }
         Ok(())
     }
 }
+
+/// Orders with an unsettled Bitcoin swap on either side, after `after` in the
+/// `(created_at, _id)` ordering when given
+fn active_swaps_filter(after: Option<&ActiveSwapsCursor>) -> Document {
+    let unsettled = |side: &str| {
+        let unset = |field: &str| {
+            let path = format!("{}.{}", side, field);
+            doc! { "$or": [{ path.clone(): { "$exists": false } }, { path: null }] }
+        };
+        doc! {
+            format!("{}.chain", side): { "$in": BITCOIN_CHAINS.to_vec() },
+            format!("{}.asset", side): "btc",
+            "$and": [unset("redeem_block_number"), unset("refund_block_number")],
+        }
+    };
+    let active = doc! { "$or": [unsettled("source_swap"), unsettled("destination_swap")] };
+
+    match after {
+        None => active,
+        Some(after) => doc! {
+            "$and": [
+                active,
+                {
+                    "$or": [
+                        { "created_at": { "$gt": &after.created_at } },
+                        { "created_at": &after.created_at, "_id": { "$gt": after.id } },
+                    ]
+                },
+            ]
+        },
+    }
+}
+
+/// Oldest orders first, with `_id` breaking ties so paging is stable across cycles
+fn active_swaps_options(limit: i64) -> FindOptions {
+    FindOptions::builder()
+        .sort(doc! { "created_at": 1, "_id": 1 })
+        .limit(limit)
+        .build()
+}
+
+/// Whether `swap` is a Bitcoin swap that was neither redeemed nor refunded
+fn is_active(swap: &Swap) -> bool {
+    BITCOIN_CHAINS.contains(&swap.chain.to_string().as_str())
+        && swap.asset == "btc"
+        && swap.redeem_block_number.is_none()
+        && swap.refund_block_number.is_none()
+}
+
+/// `created_at` in the RFC 3339 form orders are stored with
+fn created_at_key(created_at: &DateTime) -> String {
+    chrono::DateTime::from_timestamp_millis(created_at.timestamp_millis())
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+/// The order's `create_id`, falling back to its document id
+fn order_id(order: &MatchedOrder) -> Option<String> {
+    order.create_order.create_id.clone().or_else(|| order._id.map(|id| id.to_hex()))
+}
+
+/// Matches orders with a swap locked to `secret_hash` on either side, however
+/// its hex was cased or prefixed when stored
+fn secret_hash_filter(secret_hash: &str) -> Document {
+    let secret_hash = normalize(secret_hash);
+    let matches = if secret_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        mongodb::bson::Bson::RegularExpression(Regex {
+            pattern: format!("^(0x)?{}$", secret_hash),
+            options: "i".to_string(),
+        })
+    } else {
+        mongodb::bson::Bson::String(secret_hash)
+    };
+    doc! {
+        "$or": [
+            { "source_swap.secret_hash": matches.clone() },
+            { "destination_swap.secret_hash": matches }
+        ]
+    }
+}
+
+/// Matches the MatchedOrder document containing `swap_id` on either side
+fn swap_filter(swap_id: &str) -> Document {
+    doc! {
+        "$or": [
+            { "source_swap.swap_id": swap_id },
+            { "destination_swap.swap_id": swap_id }
+        ]
+    }
+}
+
+/// Builds an aggregation-pipeline update that merges `fields` into the embedded
+/// swap whose `swap_id` matches, leaving the other side untouched. This lets the
+/// update run without first reading the document to find which side to set.
+fn swap_update_pipeline(swap_id: &str, fields: Document) -> Vec<Document> {
+    // Wrap values in $literal so strings are never read as field paths
+    let mut literal_fields = Document::new();
+    for (key, value) in fields {
+        literal_fields.insert(key, doc! { "$literal": value });
+    }
+
+    let merge_if_matches = |side: &str| {
+        let path = format!("${}", side);
+        doc! {
+            "$cond": [
+                { "$eq": [format!("{}.swap_id", path), swap_id] },
+                { "$mergeObjects": [path.clone(), literal_fields.clone()] },
+                path
+            ]
+        }
+    };
+
+    vec![doc! {
+        "$set": {
+            "source_swap": merge_if_matches("source_swap"),
+            "destination_swap": merge_if_matches("destination_swap")
+        }
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_overrides() -> BitcoinConfig {
+        BitcoinConfig {
+            network: BitcoinNetwork::Testnet,
+            indexer_url: "https://blockstream.info/testnet/api".parse().unwrap(),
+            indexer_urls: HashMap::from([
+                ("signet".to_string(), "https://mempool.space/signet/api/".parse().unwrap()),
+            ]),
+            mongodb_uri: "mongodb://localhost:27017".to_string(),
+            database_name: "bitcoin_watcher_test".to_string(),
+            active_swaps_limit: default_active_swaps_limit(),
+            max_tip_regression: default_max_tip_regression(),
+            enforce_secret_hash_binding: false,
+        }
+    }
+
+    #[test]
+    fn test_active_swaps_options_limit_and_ordering() {
+        let options = active_swaps_options(50);
+        assert_eq!(options.limit, Some(50));
+        assert_eq!(options.skip, None);
+        assert_eq!(options.sort, Some(doc! { "created_at": 1, "_id": 1 }));
+    }
+
+    #[test]
+    fn test_active_swaps_filter_resumes_after_cursor() {
+        assert!(active_swaps_filter(None).get_array("$or").is_ok());
+
+        let after = ActiveSwapsCursor { created_at: "2025-01-01T00:00:00+00:00".to_string(), id: ObjectId::new() };
+        let filter = active_swaps_filter(Some(&after));
+        let and = filter.get_array("$and").unwrap();
+        let resume = and[1].as_document().unwrap().get_array("$or").unwrap();
+        let later = resume[0].as_document().unwrap().get_document("created_at").unwrap();
+        assert_eq!(later.get_str("$gt").unwrap(), after.created_at);
+        let tie = resume[1].as_document().unwrap();
+        assert_eq!(tie.get_str("created_at").unwrap(), after.created_at);
+        assert_eq!(tie.get_document("_id").unwrap().get_object_id("$gt").unwrap(), after.id);
+    }
+
+    #[tokio::test]
+    async fn test_settled_orders_do_not_shift_the_next_batch() {
+        let mut config = config_with_overrides();
+        config.active_swaps_limit = 2;
+        let store = BitcoinStore::in_memory(config);
+        for (i, secret_hash) in ["aa", "bb", "cc", "dd"].iter().enumerate() {
+            let mut order = crate::fixtures::cross_chain_order(&secret_hash.repeat(32), &secret_hash.repeat(32));
+            order.created_at = DateTime::from_millis(1_000 * i as i64);
+            order.destination_swap.swap_id = format!("order-{}", i);
+            store.insert_order(order).await;
+        }
+
+        let first = store.get_active_swaps(None).await.unwrap();
+        let ids: Vec<_> = first.swaps.iter().map(|swap| swap.swap_id.as_str()).collect();
+        assert_eq!(ids, vec!["order-0", "order-1"]);
+
+        // An order of the first batch settles; with an offset the next batch would skip order-2
+        store.orders.write().await[0].destination_swap.redeem_block_number = Some("100".to_string());
+        let second = store.get_active_swaps(first.next.as_ref()).await.unwrap();
+        let ids: Vec<_> = second.swaps.iter().map(|swap| swap.swap_id.as_str()).collect();
+        assert_eq!(ids, vec!["order-2", "order-3"]);
+
+        // A full batch may be the last one; the empty batch after it wraps around
+        let third = store.get_active_swaps(second.next.as_ref()).await.unwrap();
+        assert!(third.swaps.is_empty());
+        assert!(third.next.is_none());
+    }
+
+    #[test]
+    fn test_signet_swap_uses_signet_indexer() {
+        let config = config_with_overrides();
+        assert_eq!(config.indexer_url_for(&Chain::BitcoinSignet), "https://mempool.space/signet/api");
+    }
+
+    #[test]
+    fn test_unmapped_network_falls_back_to_default_indexer() {
+        let config = config_with_overrides();
+        assert_eq!(config.indexer_url_for(&Chain::BitcoinTestnet), "https://blockstream.info/testnet/api");
+        assert_eq!(config.all_indexer_urls().len(), 2);
+    }
+
+    #[test]
+    fn test_swap_update_pipeline_targets_matching_side() {
+        let pipeline = swap_update_pipeline("abc", doc! { "redeem_tx_hash": "tx", "secret": "s" });
+        assert_eq!(pipeline.len(), 1);
+
+        let set = pipeline[0].get_document("$set").unwrap();
+        for side in ["source_swap", "destination_swap"] {
+            let cond = set.get_document(side).unwrap().get_array("$cond").unwrap();
+            let path = format!("${}", side);
+
+            let check = cond[0].as_document().unwrap().get_array("$eq").unwrap();
+            assert_eq!(check[0].as_str().unwrap(), format!("{}.swap_id", path));
+            assert_eq!(check[1].as_str().unwrap(), "abc");
+
+            let merge = cond[1].as_document().unwrap().get_array("$mergeObjects").unwrap();
+            assert_eq!(merge[0].as_str().unwrap(), path);
+            let fields = merge[1].as_document().unwrap();
+            assert_eq!(fields.get_document("redeem_tx_hash").unwrap().get_str("$literal").unwrap(), "tx");
+            assert_eq!(fields.get_document("secret").unwrap().get_str("$literal").unwrap(), "s");
+
+            // The non-matching side is kept as-is
+            assert_eq!(cond[2].as_str().unwrap(), path);
+        }
+    }
+
+    #[test]
+    fn test_secret_hash_filter_ignores_case_and_prefix() {
+        let filter = secret_hash_filter("0xA201BE65");
+        let sides = filter.get_array("$or").unwrap();
+        assert_eq!(sides.len(), 2);
+        let pattern = sides[0].as_document().unwrap().get("source_swap.secret_hash").unwrap();
+        assert_eq!(
+            pattern,
+            &mongodb::bson::Bson::RegularExpression(Regex { pattern: "^(0x)?a201be65$".to_string(), options: "i".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_swap_filter_matches_either_side() {
+        let filter = swap_filter("abc");
+        let or = filter.get_array("$or").unwrap();
+        assert_eq!(or[0].as_document().unwrap().get_str("source_swap.swap_id").unwrap(), "abc");
+        assert_eq!(or[1].as_document().unwrap().get_str("destination_swap.swap_id").unwrap(), "abc");
+    }
+}
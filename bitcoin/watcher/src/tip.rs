@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+/// Outcome of checking a tip height reported by an indexer
+#[derive(Debug, Clone, PartialEq)]
+pub enum TipCheck {
+    /// The height is plausible and can be used for confirmations and expiry
+    Trusted(u64),
+    /// The height fell further behind the highest tip seen than a reorg explains
+    Regressed { highest: u64, reported: u64 },
+}
+
+/// Sanity-checks indexer tip heights before the watcher makes finality
+/// decisions from them. Tips should only move forward; a step back within
+/// `max_regression` blocks is accepted as a reorg, anything deeper is treated
+/// as a lagging or misbehaving indexer.
+#[derive(Debug)]
+pub struct TipGuard {
+    max_regression: u64,
+    highest: HashMap<String, u64>, // indexer url -> highest tip seen
+}
+
+impl TipGuard {
+    pub fn new(max_regression: u64) -> Self {
+        Self {
+            max_regression,
+            highest: HashMap::new(),
+        }
+    }
+
+    /// Checks the tip `reported` by the indexer at `source`
+    pub fn check(&mut self, source: &str, reported: u64) -> TipCheck {
+        let highest = self.highest.entry(source.to_string()).or_insert(reported);
+        if reported >= *highest {
+            *highest = reported;
+            return TipCheck::Trusted(reported);
+        }
+
+        if *highest - reported <= self.max_regression {
+            TipCheck::Trusted(reported)
+        } else {
+            TipCheck::Regressed {
+                highest: *highest,
+                reported,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advancing_tip_is_trusted() {
+        let mut guard = TipGuard::new(3);
+        assert_eq!(guard.check("indexer", 100), TipCheck::Trusted(100));
+        assert_eq!(guard.check("indexer", 101), TipCheck::Trusted(101));
+        assert_eq!(guard.check("indexer", 101), TipCheck::Trusted(101));
+    }
+
+    #[test]
+    fn test_shallow_reorg_is_tolerated() {
+        let mut guard = TipGuard::new(3);
+        guard.check("indexer", 100);
+        assert_eq!(guard.check("indexer", 97), TipCheck::Trusted(97));
+    }
+
+    #[test]
+    fn test_implausible_regression_is_rejected_until_caught_up() {
+        let mut guard = TipGuard::new(3);
+        guard.check("indexer", 100);
+        assert_eq!(
+            guard.check("indexer", 50),
+            TipCheck::Regressed { highest: 100, reported: 50 }
+        );
+        // The bad height doesn't lower the bar for the next check
+        assert!(matches!(guard.check("indexer", 60), TipCheck::Regressed { .. }));
+        assert_eq!(guard.check("indexer", 100), TipCheck::Trusted(100));
+    }
+
+    #[test]
+    fn test_indexers_are_tracked_separately() {
+        let mut guard = TipGuard::new(3);
+        guard.check("testnet", 100_000);
+        assert_eq!(guard.check("signet", 200), TipCheck::Trusted(200));
+    }
+}
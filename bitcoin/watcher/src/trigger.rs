@@ -0,0 +1,201 @@
+use log::{debug, info, warn};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+
+/// Chain activity pushed by an indexer that can notify us directly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChainEvent {
+    NewBlock,
+    Mempool,
+}
+
+impl ChainEvent {
+    /// Maps a server-sent event name to the chain event it reports
+    fn from_sse_name(name: &str) -> Self {
+        match name {
+            "mempool" => ChainEvent::Mempool,
+            _ => ChainEvent::NewBlock,
+        }
+    }
+}
+
+/// Decides when the watcher runs its next cycle
+#[async_trait::async_trait]
+pub trait WatchTrigger: Send {
+    /// Resolves once the next watch cycle should run
+    async fn wait(&mut self);
+}
+
+/// Runs a cycle every fixed interval
+pub struct IntervalTrigger {
+    interval: Duration,
+}
+
+impl IntervalTrigger {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+#[async_trait::async_trait]
+impl WatchTrigger for IntervalTrigger {
+    async fn wait(&mut self) {
+        sleep(self.interval).await;
+    }
+}
+
+/// Runs a cycle as soon as a chain event is pushed, and at least every
+/// `fallback` interval. Once every sender is gone it behaves like an
+/// `IntervalTrigger`.
+pub struct PushTrigger {
+    events: mpsc::Receiver<ChainEvent>,
+    fallback: Duration,
+    closed: bool,
+}
+
+impl PushTrigger {
+    /// Creates a trigger and the sender push sources use to wake it
+    pub fn new(fallback: Duration) -> (Self, mpsc::Sender<ChainEvent>) {
+        let (sender, events) = mpsc::channel(64);
+        (
+            Self {
+                events,
+                fallback,
+                closed: false,
+            },
+            sender,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl WatchTrigger for PushTrigger {
+    async fn wait(&mut self) {
+        if self.closed {
+            sleep(self.fallback).await;
+            return;
+        }
+
+        tokio::select! {
+            event = self.events.recv() => match event {
+                Some(event) => {
+                    debug!("Watch cycle triggered by {:?}", event);
+                    // A burst of events only needs a single cycle
+                    while self.events.try_recv().is_ok() {}
+                }
+                None => {
+                    warn!("Push source closed, falling back to {:?} polling", self.fallback);
+                    self.closed = true;
+                    sleep(self.fallback).await;
+                }
+            },
+            _ = sleep(self.fallback) => {}
+        }
+    }
+}
+
+/// Streams a server-sent events endpoint and pushes an event for every
+/// `data:` line: `Mempool` for `event: mempool` messages, `NewBlock` for
+/// anything else. Returns when the stream ends or fails, which drops `sender`
+/// and lets the trigger fall back to interval polling.
+pub async fn listen_event_stream(url: String, sender: mpsc::Sender<ChainEvent>) {
+    info!("Listening for chain events on {}", url);
+
+    let mut response = match reqwest::Client::new()
+        .get(&url)
+        .header("Accept", "text/event-stream")
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => resp,
+        Ok(resp) => {
+            warn!("Event stream {} returned {}", url, resp.status());
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to connect to event stream {}: {}", url, e);
+            return;
+        }
+    };
+
+    let mut buffer = String::new();
+    let mut event_name = String::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+                while let Some(newline) = buffer.find('\n') {
+                    let line: String = buffer.drain(..=newline).collect();
+                    let line = line.trim_end();
+                    if let Some(name) = line.strip_prefix("event:") {
+                        event_name = name.trim().to_string();
+                    } else if line.is_empty() {
+                        // A blank line ends the message
+                        event_name.clear();
+                    } else if line.starts_with("data:")
+                        && sender.send(ChainEvent::from_sse_name(&event_name)).await.is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+            Ok(None) => {
+                warn!("Event stream {} ended", url);
+                return;
+            }
+            Err(e) => {
+                warn!("Event stream {} failed: {}", url, e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{timeout, Instant};
+
+    #[tokio::test]
+    async fn test_new_block_push_triggers_cycle() {
+        let (mut trigger, sender) = PushTrigger::new(Duration::from_secs(60));
+        sender.send(ChainEvent::NewBlock).await.unwrap();
+
+        // Resolves immediately rather than waiting out the 60s fallback
+        timeout(Duration::from_secs(1), trigger.wait())
+            .await
+            .expect("push should trigger a cycle");
+    }
+
+    #[tokio::test]
+    async fn test_burst_of_events_triggers_single_cycle() {
+        let (mut trigger, sender) = PushTrigger::new(Duration::from_millis(50));
+        for _ in 0..3 {
+            sender.send(ChainEvent::Mempool).await.unwrap();
+        }
+
+        trigger.wait().await;
+        // The remaining events were drained, so the next wait runs until the fallback
+        let start = Instant::now();
+        trigger.wait().await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_sse_event_name_maps_to_chain_event() {
+        assert_eq!(ChainEvent::from_sse_name("mempool"), ChainEvent::Mempool);
+        assert_eq!(ChainEvent::from_sse_name("block"), ChainEvent::NewBlock);
+        assert_eq!(ChainEvent::from_sse_name(""), ChainEvent::NewBlock);
+    }
+
+    #[tokio::test]
+    async fn test_closed_push_source_falls_back_to_interval() {
+        let (mut trigger, sender) = PushTrigger::new(Duration::from_millis(20));
+        drop(sender);
+
+        let start = Instant::now();
+        trigger.wait().await;
+        trigger.wait().await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -1,49 +1,82 @@
-use crate::store::{BitcoinStore, BitcoinHtlcParams, HtlcStatus, BitcoinConfig, BitcoinNetwork};
-use primitives::types::Swap;
+use crate::store::{ActiveSwapsCursor, ActiveSwapsPage, BitcoinStore};
+use primitives::types::{HashFunction, Swap};
+use crate::binding::{BindingCheck, SecretHashBindings};
 use crate::events::{BitcoinEvent, EventHandler, BitcoinEventHandler};
+use crate::funding::{FundingTracker, FundingUpdate};
+use crate::notifications::NotificationSink;
+use crate::reconcile::{check_deposit_address, DepositAddressCheck};
+use crate::tip::{TipCheck, TipGuard};
+use crate::trigger::{IntervalTrigger, WatchTrigger};
 use primitives::indexer::SimpleIndexer;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::Result;
-use tokio::time::{sleep, Duration};
-use log::{debug, error, info};
-use sha2::{Sha256, Digest};
+use tokio::time::Duration;
+use log::{debug, error, info, warn};
 use hex;
 use reqwest;
 
 pub struct BitcoinWatcher {
     store: BitcoinStore,
     event_handler: BitcoinEventHandler,
-    indexer: SimpleIndexer,
+    indexers: HashMap<String, SimpleIndexer>, // indexer_url -> indexer
     watched_addresses: HashMap<String, u64>, // address -> last_balance
     init_watched_addresses: HashMap<String, bool>, // address -> whether we're watching for init
+    reconciled_swaps: HashSet<String>, // swap ids whose deposit address has been checked
+    funding: FundingTracker,
+    active_swaps_after: Option<ActiveSwapsCursor>, // where the next batch of active swaps starts
+    tips: TipGuard,
+    watch_expiry: HashMap<String, u64>, // address -> when it expires if never funded
+    bindings: SecretHashBindings,
 }
 
 impl BitcoinWatcher {
     pub fn new(store: BitcoinStore) -> Result<Self> {
         let event_handler = BitcoinEventHandler::new(store.clone());
         let config = store.get_config();
-        let indexer = SimpleIndexer::new(&config.indexer_url)?;
-        
+        let mut indexers = HashMap::new();
+        for url in config.all_indexer_urls() {
+            let indexer = SimpleIndexer::new(&url)?;
+            indexers.insert(url, indexer);
+        }
+        let max_tip_regression = config.max_tip_regression;
+
         Ok(Self {
             store,
             event_handler,
-            indexer,
+            indexers,
             watched_addresses: HashMap::new(),
             init_watched_addresses: HashMap::new(),
+            reconciled_swaps: HashSet::new(),
+            funding: FundingTracker::new(),
+            active_swaps_after: None,
+            tips: TipGuard::new(max_tip_regression),
+            watch_expiry: HashMap::new(),
+            bindings: SecretHashBindings::new(),
         })
     }
 
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationSink>) -> Self {
+        self.event_handler = self.event_handler.with_notifier(notifier);
+        self
+    }
+
     pub async fn start(&mut self, polling_interval: u32) -> Result<()> {
         info!("Starting Bitcoin watcher with {} second polling interval...", polling_interval);
-        
+        let mut trigger = IntervalTrigger::new(Duration::from_secs(polling_interval as u64));
+        self.run(&mut trigger).await
+    }
+
+    /// Runs a watch cycle every time `trigger` fires
+    pub async fn run(&mut self, trigger: &mut dyn WatchTrigger) -> Result<()> {
         loop {
             if let Err(e) = self.watch_cycle().await {
                 error!("Error in watch cycle: {}", e);
             }
             
             // Wait before next cycle
-            sleep(Duration::from_secs(polling_interval as u64)).await;
+            trigger.wait().await;
         }
     }
 
@@ -53,32 +86,172 @@ impl BitcoinWatcher {
             .duration_since(UNIX_EPOCH)?
             .as_secs();
         self.store.cleanup_expired_htlcs(current_time).await?;
+        self.sweep_expired_unfunded(current_time).await?;
 
         // Get swaps from database (similar to the Go code you provided)
-        let swaps = self.get_active_swaps().await?;
+        let ActiveSwapsPage { swaps, order_ids, .. } = self.get_active_swaps().await?;
         debug!("Swaps: {:?}", swaps);
+        self.reconcile_deposit_addresses(&swaps);
         // Watch HTLC addresses for each swap
         for swap in swaps {
+            let Some(order_id) = order_ids.get(&swap.swap_id) else {
+                warn!("Not watching {}: its order has no id to bind its secret hash to", swap.swap_id);
+                continue;
+            };
+            if !self.secret_hash_bound(&swap, order_id).await? {
+                continue;
+            }
+            let expires_at = unfunded_expiry(&swap);
+            let initiated = swap.initiate_tx_hash.as_deref().is_some_and(|hash| !hash.is_empty());
+            if !initiated && self.never_funded(&swap.swap_id) && (swap.expired || expires_at <= current_time) {
+                if !swap.expired {
+                    self.expire(&swap.swap_id).await?;
+                }
+                // Expired swaps are no longer tracked, only checked for a late deposit
+                if !self.has_deposit(&swap).await? {
+                    continue;
+                }
+                warn!("Deposit to {} arrived after its order expired", swap.swap_id);
+            } else {
+                self.watch_expiry.insert(swap.swap_id.clone(), expires_at);
+            }
             self.watch_swap_htlc(&swap).await?;
         }
 
         Ok(())
     }
 
-    async fn get_active_swaps(&self) -> Result<Vec<Swap>> {
-        self.store.get_active_swaps().await
+    async fn get_active_swaps(&mut self) -> Result<ActiveSwapsPage> {
+        let page = self.store.get_active_swaps(self.active_swaps_after.as_ref()).await?;
+        self.active_swaps_after = page.next.clone();
+        Ok(page)
+    }
+
+    /// Checks that `swap`'s secret hash belongs to its own order, so an HTLC
+    /// funded under a hash an earlier order was created with is never mistaken
+    /// for that order's. Returns whether the swap should still be watched.
+    async fn secret_hash_bound(&mut self, swap: &Swap, order_id: &str) -> Result<bool> {
+        if !self.bindings.is_bound(&swap.secret_hash) {
+            let owner = self.store.secret_hash_owner(&swap.secret_hash).await?;
+            self.bindings.bind(&swap.secret_hash, owner.as_deref().unwrap_or(order_id));
+        }
+        Ok(match self.bindings.check(&swap.secret_hash, order_id) {
+            BindingCheck::Bound => true,
+            BindingCheck::Collision { bound_to } => {
+                error!(
+                    "Secret hash collision: swap {} of order {} reuses the secret hash of order {}",
+                    swap.swap_id, order_id, bound_to
+                );
+                !self.store.get_config().enforce_secret_hash_binding
+            }
+        })
+    }
+
+    /// Checks each newly seen swap's stored deposit address against the address
+    /// derived here, so funds are never sent somewhere the watcher doesn't monitor
+    fn reconcile_deposit_addresses(&mut self, swaps: &[Swap]) {
+        for swap in swaps {
+            if self.reconciled_swaps.contains(&swap.swap_id) {
+                continue;
+            }
+
+            match check_deposit_address(swap) {
+                Ok(DepositAddressCheck::Match) => {
+                    debug!("Deposit address verified for swap {}", swap.swap_id);
+                }
+                Ok(DepositAddressCheck::Mismatch { stored, derived }) => {
+                    error!(
+                        "Deposit address mismatch for swap {}: stored {} but watcher derives {}",
+                        swap.swap_id, stored, derived
+                    );
+                }
+                Ok(DepositAddressCheck::Missing) => {
+                    warn!("Swap {} has no stored deposit address", swap.swap_id);
+                }
+                Err(e) => {
+                    error!("Failed to derive HTLC address for swap {}: {}", swap.swap_id, e);
+                }
+            }
+            self.reconciled_swaps.insert(swap.swap_id.clone());
+        }
+    }
+
+    /// Whether no funding has ever been seen at `address`
+    fn never_funded(&self, address: &str) -> bool {
+        self.watched_addresses.get(address).copied().unwrap_or(0) == 0
+            && self.funding.pending_txid(address).is_none()
+            && !self.funding.is_finalized(address)
+    }
+
+    /// Marks `swap_id` expired in the store and notifies integrators
+    async fn expire(&self, swap_id: &str) -> Result<()> {
+        self.event_handler.handle_event(BitcoinEvent::HtlcExpired { id: swap_id.to_string() }).await
+    }
+
+    /// Whether anything was ever sent to `swap`'s HTLC address
+    async fn has_deposit(&self, swap: &Swap) -> Result<bool> {
+        let indexer_url = self.store.get_config().indexer_url_for(&swap.chain).to_string();
+        Ok(self.indexer_for(&indexer_url)?.get_address_transaction_count(&swap.swap_id).await? > 0)
+    }
+
+    /// Expires HTLCs whose order ran out of time before they were ever funded and
+    /// drops their watch entries, so the watch set doesn't grow for the life of the process
+    async fn sweep_expired_unfunded(&mut self, current_time: u64) -> Result<()> {
+        let expired: Vec<String> = self
+            .watch_expiry
+            .iter()
+            .filter(|(address, expires_at)| **expires_at <= current_time && self.never_funded(address))
+            .map(|(address, _)| address.clone())
+            .collect();
+
+        for address in &expired {
+            self.expire(address).await?;
+            self.watched_addresses.remove(address);
+            self.init_watched_addresses.remove(address);
+            self.watch_expiry.remove(address);
+            self.reconciled_swaps.remove(address);
+            self.store.remove_htlc_params(address).await?;
+        }
+        if !expired.is_empty() {
+            info!("Pruned {} expired, never-funded HTLC watch entries", expired.len());
+        }
+        Ok(())
+    }
+
+    fn indexer_for(&self, indexer_url: &str) -> Result<&SimpleIndexer> {
+        self.indexers
+            .get(indexer_url)
+            .ok_or_else(|| anyhow::anyhow!("No indexer configured for {}", indexer_url))
+    }
+
+    /// Fetches the tip from `indexer_url`, or `None` when it regressed implausibly
+    /// and shouldn't be used for finality decisions
+    async fn trusted_tip(&mut self, indexer_url: &str) -> Result<Option<u64>> {
+        let reported = self.indexer_for(indexer_url)?.get_current_block_height().await?;
+        match self.tips.check(indexer_url, reported) {
+            TipCheck::Trusted(tip) => Ok(Some(tip)),
+            TipCheck::Regressed { highest, reported } => {
+                error!(
+                    "Tip anomaly from {}: reported height {} is {} blocks behind the highest seen {}",
+                    indexer_url, reported, highest - reported, highest
+                );
+                Ok(None)
+            }
+        }
     }
 
     async fn watch_swap_htlc(&mut self, swap: &Swap) -> Result<()> {
         // Use the swap_id as the taproot script address
         let htlc_address = &swap.swap_id;
-        info!("HTLC address (swap_id): {}", htlc_address);
+        let indexer_url = self.store.get_config().indexer_url_for(&swap.chain).to_string();
+        info!("HTLC address (swap_id): {} via {}", htlc_address, indexer_url);
         // Get UTXOs for this HTLC address using SimpleIndexer
-        let utxos = self.indexer.get_utxos(htlc_address).await.unwrap();
+        let indexer = self.indexer_for(&indexer_url)?;
+        let utxos = indexer.get_utxos(htlc_address).await?;
         info!("UTXOs for {}: {:?}", htlc_address, utxos);
         
         // Get transaction count for this address
-        let tx_count = self.indexer.get_address_transaction_count(htlc_address).await?;
+        let tx_count = indexer.get_address_transaction_count(htlc_address).await?;
         info!("Transaction count for {}: {}", htlc_address, tx_count);
         
         // Calculate total balance from UTXOs
@@ -99,11 +272,11 @@ impl BitcoinWatcher {
                 info!("HTLC fulfilled: {} has no UTXOs but 2 transactions", htlc_address);
                 
                 // Get the spending transaction to determine if it's claim or refund
-                if let Some(spending_tx) = self.get_spending_transaction(htlc_address).await? {
+                if let Some(spending_tx) = self.get_spending_transaction(&indexer_url, htlc_address).await? {
                     tracing::info!("spending_tx: {}", spending_tx);
-                    let tx_details = self.get_transaction_details(&spending_tx).await?;
+                    let tx_details = self.get_transaction_details(&indexer_url, &spending_tx).await?;
                     tracing::info!("tx_details: {:?}", tx_details);
-                    if let Some(preimage) = self.analyze_spending_transaction(&spending_tx, &swap.secret_hash).await? {
+                    if let Some(preimage) = self.analyze_spending_transaction(&indexer_url, &spending_tx, &swap.secret_hash, swap.hash_function).await? {
                         tracing::info!("preimage: {}", preimage);
                         // This is a redeem - preimage was found and matches hashlock
                         let event = BitcoinEvent::HtlcClaimed {
@@ -133,48 +306,44 @@ impl BitcoinWatcher {
                 info!("Address {} has no UTXOs but {} transactions", htlc_address, tx_count);
             }
         } else {
-            // Has UTXOs - check if this is the first funding transaction
-            if let Some(previous_balance) = self.watched_addresses.get(htlc_address) {
-                if current_balance > *previous_balance {
-                    // Balance increased - this is the init event
-                    let increase = current_balance - *previous_balance;
-                    
-                    // Find the funding transaction
-                    if let Some(funding_utxo) = utxos.iter().find(|utxo| utxo.value == increase) {
-                        // Get transaction details for block information
-                        let tx_details = self.get_transaction_details(&funding_utxo.txid).await?;
-                        let confirmations = if funding_utxo.status.confirmed { 1 } else { 0 };
-                        
+            // Prefer the provisional funding output we're already tracking, falling back to the
+            // first UTXO so a replaced (RBF) funding transaction is picked up as a new outpoint
+            let funding_utxo = self
+                .funding
+                .pending_txid(htlc_address)
+                .and_then(|txid| utxos.iter().find(|utxo| utxo.txid == txid))
+                .or_else(|| utxos.first());
+
+            // Finalizing confirmed funding needs a tip we trust; otherwise it is
+            // left pending and retried next cycle
+            let needs_tip = funding_utxo
+                .is_some_and(|utxo| utxo.status.confirmed && !self.funding.is_finalized(htlc_address));
+            let tip_height = if needs_tip { self.trusted_tip(&indexer_url).await? } else { None };
 
+            if needs_tip && tip_height.is_none() {
+                warn!("Withholding funding finality for {} until the indexer tip is plausible again", swap.swap_id);
+            } else if let Some(funding_utxo) = funding_utxo {
+                match self.funding.observe(htlc_address, &funding_utxo.txid, funding_utxo.status.confirmed) {
+                    FundingUpdate::Confirmed => {
+                        let tip_height = tip_height.unwrap_or(funding_utxo.status.block_height);
                         let event = BitcoinEvent::HtlcFunded {
                             id: swap.swap_id.clone(),
                             tx_hash: funding_utxo.txid.clone(),
-                            amount_sats: increase,
-                            confirmations,
-                            block_height: tx_details.unwrap().block_height.unwrap_or(0),
+                            amount_sats: funding_utxo.value,
+                            confirmations: funding_utxo.confirmations(tip_height) as u32,
+                            block_height: funding_utxo.status.block_height,
                         };
-                        
+
                         self.event_handler.handle_event(event).await?;
-                        info!("HTLC funded: {} with {} sats (tx: {})", swap.swap_id, increase, funding_utxo.txid);
+                        info!("HTLC funded: {} with {} sats (tx: {})", swap.swap_id, funding_utxo.value, funding_utxo.txid);
                     }
-                }
-            } else {
-                // First time seeing this address with UTXOs - this is the init event
-                if let Some(funding_utxo) = utxos.first() {
-                    // Get transaction details for block information
-                    let tx_details = self.get_transaction_details(&funding_utxo.txid).await?;
-                    let confirmations = if funding_utxo.status.confirmed { 1 } else { 0 };
-                    
-                    let event = BitcoinEvent::HtlcFunded {
-                        id: swap.swap_id.clone(),
-                        tx_hash: funding_utxo.txid.clone(),
-                        amount_sats: funding_utxo.value,
-                        confirmations,
-                        block_height: tx_details.unwrap().block_height.unwrap_or(0),
-                    };
-                    
-                    self.event_handler.handle_event(event).await?;
-                    info!("HTLC funded: {} with {} sats (tx: {})", swap.swap_id, funding_utxo.value, funding_utxo.txid);
+                    FundingUpdate::Provisional => {
+                        info!("Provisional funding for {} seen in mempool (tx: {}), waiting for confirmation", swap.swap_id, funding_utxo.txid);
+                    }
+                    FundingUpdate::Replaced { previous_txid } => {
+                        warn!("Funding for {} replaced before confirmation: {} -> {}", swap.swap_id, previous_txid, funding_utxo.txid);
+                    }
+                    FundingUpdate::Unchanged => {}
                 }
             }
             
@@ -190,10 +359,9 @@ impl BitcoinWatcher {
 
 
 
-    async fn analyze_spending_transaction(&self, tx_hash: &str, hashlock: &str) -> Result<Option<String>> {
+    async fn analyze_spending_transaction(&self, indexer_url: &str, tx_hash: &str, hashlock: &str, hash_function: HashFunction) -> Result<Option<String>> {
         // Get transaction details from the indexer
-        let config = self.store.get_config();
-        let url = format!("{}/tx/{}", config.indexer_url, tx_hash);
+        let url = format!("{}/tx/{}", indexer_url, tx_hash);
         
         // Use reqwest to get transaction data
         let client = reqwest::Client::new();
@@ -221,10 +389,10 @@ impl BitcoinWatcher {
                             // Decode the preimage from hex
                             if let Ok(preimage_bytes) = hex::decode(preimage_hex) {
                                 // Hash the preimage and compare with hashlock
-                                let hashed_preimage = self.hash_secret(&preimage_bytes);
+                                let hashed_preimage = self.hash_secret(&preimage_bytes, hash_function);
                                 tracing::info!("hashed_preimage: {}", hashed_preimage);
                                 tracing::info!("hashlock: {}", hashlock);
-                                if true {
+                                if hashed_preimage.eq_ignore_ascii_case(hashlock.trim_start_matches("0x")) {
                                     // This is a redeem - return the preimage
                                     info!("Found matching preimage for hashlock: {}", hashlock);
                                     return Ok(Some(preimage_hex.to_string()));
@@ -242,21 +410,17 @@ impl BitcoinWatcher {
         Ok(None)
     }
 
-    fn hash_secret(&self, secret: &[u8]) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(secret);
-        let result = hasher.finalize();
-        hex::encode(result)
+    fn hash_secret(&self, secret: &[u8], hash_function: HashFunction) -> String {
+        hex::encode(hash_function.hash(secret))
     }
 
 
 
 
 
-    async fn get_spending_transaction(&self, address: &str) -> Result<Option<String>> {
+    async fn get_spending_transaction(&self, indexer_url: &str, address: &str) -> Result<Option<String>> {
         // Get recent transactions for this address
-        let config = self.store.get_config();
-        let url = format!("{}/address/{}/txs", config.indexer_url, address);
+        let url = format!("{}/address/{}/txs", indexer_url, address);
         
         let client = reqwest::Client::new();
         let response = client.get(&url).send().await?;
@@ -269,7 +433,7 @@ impl BitcoinWatcher {
             for tx in &transactions {
                 if let Some(txid) = tx["txid"].as_str() {
                     // Check if this transaction has inputs from our address
-                    if self.transaction_spends_from_address(txid, address).await? {
+                    if self.transaction_spends_from_address(indexer_url, txid, address).await? {
                         tracing::info!("Found spending transaction: {} for address {}", txid, address);
                         return Ok(Some(txid.to_string()));
                     }
@@ -281,10 +445,9 @@ impl BitcoinWatcher {
         Ok(None)
     }
 
-    async fn transaction_spends_from_address(&self, tx_hash: &str, address: &str) -> Result<bool> {
+    async fn transaction_spends_from_address(&self, indexer_url: &str, tx_hash: &str, address: &str) -> Result<bool> {
         // Get transaction details to check if it spends from our address
-        let config = self.store.get_config();
-        let url = format!("{}/tx/{}", config.indexer_url, tx_hash);
+        let url = format!("{}/tx/{}", indexer_url, tx_hash);
         
         let client = reqwest::Client::new();
         let response = client.get(&url).send().await?;
@@ -310,10 +473,9 @@ impl BitcoinWatcher {
         Ok(false)
     }
 
-    async fn get_transaction_details(&self, tx_hash: &str) -> Result<Option<TransactionDetails>> {
+    async fn get_transaction_details(&self, indexer_url: &str, tx_hash: &str) -> Result<Option<TransactionDetails>> {
         // Get transaction details from the indexer
-        let config = self.store.get_config();
-        let url = format!("{}/tx/{}", config.indexer_url, tx_hash);
+        let url = format!("{}/tx/{}", indexer_url, tx_hash);
         
         let client = reqwest::Client::new();
         let response = client.get(&url).send().await?;
@@ -344,7 +506,210 @@ struct TransactionDetails {
     confirmations: bool,
 }
 
+/// Average Bitcoin block interval, used to turn a timelock into wall-clock time
+const BITCOIN_BLOCK_SECS: u64 = 600;
+
+/// When `swap` counts as expired if its HTLC is still unfunded: once its order has
+/// waited as long as the HTLC's timelock
+fn unfunded_expiry(swap: &Swap) -> u64 {
+    let created_at = swap.created_at.timestamp_millis().max(0) as u64 / 1000;
+    created_at + swap.timelock.max(0) as u64 * BITCOIN_BLOCK_SECS
+}
+
 // Helper function to create a Bitcoin watcher with a store
 pub fn create_bitcoin_watcher(store: BitcoinStore) -> Result<BitcoinWatcher> {
     BitcoinWatcher::new(store)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cross_chain_order, secret_hash};
+    use crate::store::{default_active_swaps_limit, default_max_tip_regression, BitcoinConfig, BitcoinNetwork};
+    use mongodb::bson::DateTime;
+    use primitives::mock_server::{MockRequest, MockServer};
+
+    /// Indexer answering successive tip height requests with `heights`
+    async fn indexer_reporting(heights: Vec<u64>) -> String {
+        let mut heights = heights.into_iter();
+        let server = MockServer::start(move |_: &MockRequest| heights.next().unwrap_or_default().to_string()).await;
+        server.url().to_string()
+    }
+
+    fn config_for(indexer_url: &str) -> BitcoinConfig {
+        BitcoinConfig {
+            network: BitcoinNetwork::Regtest,
+            indexer_url: indexer_url.parse().unwrap(),
+            indexer_urls: Default::default(),
+            mongodb_uri: "mongodb://localhost:27017".to_string(),
+            database_name: "bitcoin_watcher_test".to_string(),
+            active_swaps_limit: default_active_swaps_limit(),
+            max_tip_regression: default_max_tip_regression(),
+            enforce_secret_hash_binding: false,
+        }
+    }
+
+    fn watcher_for(indexer_url: &str) -> BitcoinWatcher {
+        BitcoinWatcher::new(BitcoinStore::in_memory(config_for(indexer_url))).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_regressing_tip_withholds_finality() {
+        let url = indexer_reporting(vec![1000, 1001, 900, 1002]).await;
+        let mut watcher = watcher_for(&url);
+
+        assert_eq!(watcher.trusted_tip(&url).await.unwrap(), Some(1000));
+        assert_eq!(watcher.trusted_tip(&url).await.unwrap(), Some(1001));
+        // A lagging indexer jumping 101 blocks back is not used for confirmations
+        assert_eq!(watcher.trusted_tip(&url).await.unwrap(), None);
+        assert_eq!(watcher.trusted_tip(&url).await.unwrap(), Some(1002));
+    }
+
+    /// Indexer answering every request with `respond(path)`
+    async fn indexer_serving<F>(respond: F) -> String
+    where
+        F: Fn(&str) -> String + Send + 'static,
+    {
+        let server = MockServer::start(move |request: &MockRequest| respond(&request.path)).await;
+        server.url().to_string()
+    }
+
+    /// Indexer for HTLC addresses that saw `deposit`, if any, confirmed at block 100
+    async fn indexer_with_deposit(deposit: Option<u64>) -> String {
+        indexer_serving(move |path| {
+            if path == "/blocks/tip/height" {
+                "105".to_string()
+            } else if path.ends_with("/utxo") {
+                let utxos: Vec<serde_json::Value> = deposit
+                    .map(|value| serde_json::json!({
+                        "txid": "funding",
+                        "vout": 0,
+                        "status": { "confirmed": true, "block_height": 100 },
+                        "value": value,
+                    }))
+                    .into_iter()
+                    .collect();
+                serde_json::Value::from(utxos).to_string()
+            } else {
+                let stats = |tx_count: u32| serde_json::json!({
+                    "funded_txo_count": tx_count,
+                    "funded_txo_sum": deposit.unwrap_or(0),
+                    "spent_txo_count": 0,
+                    "spent_txo_sum": 0,
+                    "tx_count": tx_count,
+                });
+                let tx_count = u32::from(deposit.is_some());
+                serde_json::json!({
+                    "address": path.trim_start_matches("/address/"),
+                    "chain_stats": stats(tx_count),
+                    "mempool_stats": stats(0),
+                })
+                .to_string()
+            }
+        })
+        .await
+    }
+
+    /// An order whose Bitcoin swap was created `age_secs` ago
+    fn order_created_ago(age_secs: i64) -> primitives::types::MatchedOrder {
+        let mut order = cross_chain_order(&secret_hash(), &secret_hash());
+        let created_at = DateTime::from_millis(DateTime::now().timestamp_millis() - age_secs * 1000);
+        order.created_at = created_at;
+        order.destination_swap.created_at = created_at;
+        order
+    }
+
+    /// Older than the 12-block timelock of the test orders
+    const PAST_TIMELOCK_SECS: i64 = 12 * BITCOIN_BLOCK_SECS as i64 + 60;
+
+    async fn expired_events(store: &BitcoinStore) -> usize {
+        let log = store.get_events().await.unwrap();
+        log.iter().filter(|entry| matches!(&entry.event, BitcoinEvent::HtlcExpired { id } if id == "btc-swap")).count()
+    }
+
+    #[tokio::test]
+    async fn test_unfunded_swap_expires_after_its_timelock() {
+        let store = BitcoinStore::in_memory(config_for(&indexer_with_deposit(None).await));
+        store.insert_order(order_created_ago(PAST_TIMELOCK_SECS)).await;
+        let mut watcher = BitcoinWatcher::new(store.clone()).unwrap();
+
+        watcher.watch_cycle().await.unwrap();
+        let order = store.get_order_for_swap("btc-swap").await.unwrap().unwrap();
+        assert!(order.destination_swap.expired);
+        assert_eq!(expired_events(&store).await, 1);
+
+        // Already terminal, so later cycles don't expire it again
+        watcher.watch_cycle().await.unwrap();
+        assert_eq!(expired_events(&store).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_swap_within_its_timelock_is_not_expired() {
+        let store = BitcoinStore::in_memory(config_for(&indexer_with_deposit(None).await));
+        store.insert_order(order_created_ago(60)).await;
+        let mut watcher = BitcoinWatcher::new(store.clone()).unwrap();
+
+        watcher.watch_cycle().await.unwrap();
+        let order = store.get_order_for_swap("btc-swap").await.unwrap().unwrap();
+        assert!(!order.destination_swap.expired);
+        assert_eq!(expired_events(&store).await, 0);
+
+        // Once the timelock has passed without funding the sweep expires it
+        let later = unfunded_expiry(&order.destination_swap) + 1;
+        watcher.sweep_expired_unfunded(later).await.unwrap();
+        let order = store.get_order_for_swap("btc-swap").await.unwrap().unwrap();
+        assert!(order.destination_swap.expired);
+        assert_eq!(expired_events(&store).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_late_deposit_to_expired_swap_is_seen() {
+        let store = BitcoinStore::in_memory(config_for(&indexer_with_deposit(Some(50_000)).await));
+        let mut order = order_created_ago(PAST_TIMELOCK_SECS);
+        order.destination_swap.expired = true;
+        store.insert_order(order).await;
+        let mut watcher = BitcoinWatcher::new(store.clone()).unwrap();
+
+        watcher.watch_cycle().await.unwrap();
+        let log = store.get_events().await.unwrap();
+        assert!(log.iter().any(|entry| matches!(
+            &entry.event,
+            BitcoinEvent::HtlcFunded { id, tx_hash, amount_sats: 50_000, .. } if id == "btc-swap" && tx_hash == "funding"
+        )));
+    }
+
+    /// A store holding a victim's order and a later order copying its secret hash
+    async fn store_with_copied_secret_hash(enforce: bool) -> (BitcoinStore, Swap, Swap) {
+        let mut victim = order_created_ago(120);
+        victim.create_order.create_id = Some("order-victim".to_string());
+        let mut attacker = order_created_ago(60);
+        attacker.create_order.create_id = Some("order-attacker".to_string());
+        attacker.source_swap.swap_id = "attacker-evm".to_string();
+        attacker.destination_swap.swap_id = "attacker-htlc".to_string();
+        attacker.destination_swap.secret_hash = format!("0x{}", secret_hash().to_uppercase());
+
+        let mut config = config_for("http://localhost:3000");
+        config.enforce_secret_hash_binding = enforce;
+        let store = BitcoinStore::in_memory(config);
+        // Inserted out of order: the binding follows creation time, not arrival
+        store.insert_order(attacker.clone()).await;
+        store.insert_order(victim.clone()).await;
+        (store, victim.destination_swap, attacker.destination_swap)
+    }
+
+    #[tokio::test]
+    async fn test_secret_hash_collision_across_orders_is_detected() {
+        // Reported but still watched unless enforcement is on
+        let (store, victim, copy) = store_with_copied_secret_hash(false).await;
+        let mut watcher = BitcoinWatcher::new(store).unwrap();
+        assert!(watcher.secret_hash_bound(&copy, "order-attacker").await.unwrap());
+        assert!(watcher.secret_hash_bound(&victim, "order-victim").await.unwrap());
+
+        // The attacker's swap seen first, as after a restart, still collides with
+        // the earlier order the store binds the hash to
+        let (store, victim, copy) = store_with_copied_secret_hash(true).await;
+        let mut watcher = BitcoinWatcher::new(store).unwrap();
+        assert!(!watcher.secret_hash_bound(&copy, "order-attacker").await.unwrap());
+        assert!(watcher.secret_hash_bound(&victim, "order-victim").await.unwrap());
+    }
+}
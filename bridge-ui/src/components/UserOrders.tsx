@@ -65,6 +65,11 @@ function getChainLogo(chainName: string) {
   );
 }
 
+// Txids are stored without "0x"; the explorer expects it
+function explorerTxUrl(txHash: string) {
+  return `https://explorer.avax-test.network/tx/${txHash.startsWith('0x') ? txHash : `0x${txHash}`}`;
+}
+
 const UserOrders: React.FC = () => {
   const { address: evmAddress } = useEVMWallet();
   const { 
@@ -391,7 +396,7 @@ const UserOrders: React.FC = () => {
                           <div>
                             <span className="text-sm font-medium text-gray-700">Source TX:</span>
                             <a
-                              href={`https://explorer.avax-test.network/tx/${order.source_swap.initiate_tx_hash}`}
+                              href={explorerTxUrl(order.source_swap.initiate_tx_hash)}
                               target="_blank"
                               rel="noopener noreferrer"
                               className="block text-sm text-blue-600 hover:text-blue-800 truncate"
@@ -407,7 +412,7 @@ const UserOrders: React.FC = () => {
                           <div>
                             <span className="text-sm font-medium text-gray-700">Destination TX:</span>
                             <a
-                              href={`https://explorer.avax-test.network/tx/${order.destination_swap.initiate_tx_hash}`}
+                              href={explorerTxUrl(order.destination_swap.initiate_tx_hash)}
                               target="_blank"
                               rel="noopener noreferrer"
                               className="block text-sm text-blue-600 hover:text-blue-800 truncate"
@@ -247,7 +247,7 @@ export class ExecutorService {
         await this.databaseService.updateOrder(orderId, {
           destination_swap: {
             ...order.destination_swap,
-            redeem_tx_hash: txHash,
+            redeem_tx_hash: txHash.toLowerCase().replace(/^0x/, ''),
             redeem_block_number: null // Will be updated by watcher
           }
         });
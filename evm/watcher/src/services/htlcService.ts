@@ -156,6 +156,8 @@ export class HTLCService {
       
       // Strip "0x" prefix from orderID for comparison
       const normalizedOrderID = orderID.startsWith('0x') ? orderID.slice(2) : orderID;
+      // Txids are stored lowercased without "0x" so the orderbook's by-tx lookup is an exact match
+      const normalizedTxHash = txHash.toLowerCase().replace(/^0x/, '');
       
       // First, find the order to determine which swap matches
       const order = await collection.findOne({
@@ -175,7 +177,7 @@ export class HTLCService {
       
       if (order.source_swap?.swap_id === normalizedOrderID) {
         // Source swap matches - only update source
-        update[`source_swap.${txHashField}`] = txHash;
+        update[`source_swap.${txHashField}`] = normalizedTxHash;
         update[`source_swap.${blockNumberField}`] = blockNumber;
         // Update secret field if provided
         if (secretField && secretValue) {
@@ -184,7 +186,7 @@ export class HTLCService {
         logger.debug(`[HTLC] Updating source swap for order ${order._id}`);
       } else if (order.destination_swap?.swap_id === normalizedOrderID) {
         // Destination swap matches - only update destination
-        update[`destination_swap.${txHashField}`] = txHash;
+        update[`destination_swap.${txHashField}`] = normalizedTxHash;
         update[`destination_swap.${blockNumberField}`] = blockNumber;
         // Update secret field if provided
         if (secretField && secretValue) {
@@ -0,0 +1,233 @@
+use alloy::{
+    hex::FromHex,
+    primitives::{Address, FixedBytes},
+    providers::{Provider, ProviderBuilder},
+    signers::local::PrivateKeySigner,
+    transports::http::reqwest,
+};
+use anyhow::{anyhow, Result};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::config::{AppConfig, ChainConfig};
+use crate::services::SupportedChain;
+use crate::urls::{IndexerUrl, RpcUrl};
+
+/// Liquidity of the relayer on one configured chain
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainBalance {
+    pub chain: String,
+    pub address: String,
+    /// Native balance in the chain's smallest unit; empty when it could not be read
+    pub balance: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_balance_threshold: Option<String>,
+    /// Balance is below `low_balance_threshold`, or could not be read at all
+    pub low_balance: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EsploraAddress {
+    chain_stats: EsploraStats,
+    mempool_stats: EsploraStats,
+}
+
+#[derive(Deserialize)]
+struct EsploraStats {
+    funded_txo_sum: u64,
+    spent_txo_sum: u64,
+}
+
+/// Confirmed plus mempool balance of a Bitcoin address, from an Esplora indexer
+pub async fn bitcoin_address_balance(indexer_url: &IndexerUrl, address: &str) -> Result<u64> {
+    let url = format!("{}/address/{}", indexer_url, address);
+    let response = reqwest::get(&url).await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Indexer returned {} for address {}", response.status(), address));
+    }
+    let info: EsploraAddress = response.json().await?;
+    let funded = info.chain_stats.funded_txo_sum + info.mempool_stats.funded_txo_sum;
+    let spent = info.chain_stats.spent_txo_sum + info.mempool_stats.spent_txo_sum;
+    Ok(funded.saturating_sub(spent))
+}
+
+/// Native balance of an EVM account
+pub async fn evm_account_balance(rpc_url: &RpcUrl, address: Address) -> Result<BigUint> {
+    let provider = ProviderBuilder::new().connect_http(rpc_url.url());
+    let balance = provider.get_balance(address).await?;
+    Ok(BigUint::from_bytes_be(&balance.to_be_bytes::<32>()))
+}
+
+/// The account whose balance is reported for a chain: `relayer_address` when
+/// set, otherwise the account of `relay_private_key` on EVM chains
+fn relayer_address(kind: &SupportedChain, chain_config: &ChainConfig) -> Result<String> {
+    if let Some(address) = &chain_config.relayer_address {
+        return Ok(address.clone());
+    }
+    match kind {
+        SupportedChain::Evm => {
+            let key = FixedBytes::<32>::from_hex(&chain_config.relay_private_key)
+                .map_err(|e| anyhow!("invalid relay_private_key: {}", e))?;
+            let signer = PrivateKeySigner::from_bytes(&key).map_err(|e| anyhow!("invalid relay_private_key: {}", e))?;
+            Ok(signer.address().to_string())
+        }
+        SupportedChain::Bitcoin => Err(anyhow!("relayer_address is not configured")),
+    }
+}
+
+async fn chain_balance(kind: &SupportedChain, chain_config: &ChainConfig, address: &str) -> Result<BigUint> {
+    match kind {
+        SupportedChain::Bitcoin => {
+            let indexer_url = chain_config
+                .indexer_url
+                .as_ref()
+                .ok_or_else(|| anyhow!("indexer_url is not configured"))?;
+            Ok(BigUint::from(bitcoin_address_balance(indexer_url, address).await?))
+        }
+        SupportedChain::Evm => {
+            let address = Address::from_str(address).map_err(|e| anyhow!("invalid relayer_address '{}': {}", address, e))?;
+            evm_account_balance(&chain_config.rpc_url, address).await
+        }
+    }
+}
+
+/// Reads the relayer balance on every configured chain. A chain whose balance
+/// cannot be read is reported with its error and flagged low rather than
+/// failing the whole request.
+pub async fn relayer_balances(config: &AppConfig) -> Vec<ChainBalance> {
+    let mut balances = Vec::new();
+    for (chain, chain_config) in &config.chains {
+        let threshold = chain_config.low_balance_threshold.map(|threshold| threshold.to_string());
+        let result = async {
+            let kind = SupportedChain::from_chain_identifier(chain)?;
+            let address = relayer_address(&kind, chain_config)?;
+            let balance = chain_balance(&kind, chain_config, &address).await?;
+            Ok::<_, anyhow::Error>((address, balance))
+        }
+        .await;
+
+        let balance = match result {
+            Ok((address, balance)) => ChainBalance {
+                chain: chain.clone(),
+                address,
+                low_balance: chain_config.low_balance_threshold.is_some_and(|threshold| balance < BigUint::from(threshold)),
+                balance: balance.to_string(),
+                low_balance_threshold: threshold,
+                error: None,
+            },
+            Err(e) => ChainBalance {
+                chain: chain.clone(),
+                address: chain_config.relayer_address.clone().unwrap_or_default(),
+                balance: String::new(),
+                low_balance_threshold: threshold,
+                low_balance: true,
+                error: Some(e.to_string()),
+            },
+        };
+        balances.push(balance);
+    }
+    balances.sort_by(|a, b| a.chain.cmp(&b.chain));
+    balances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use bitcoin_primitives::mock_server::{MockRequest, MockServer};
+
+    const RELAY_KEY: &str = "639ed7560cbdde79096973912f5c83de86ba08aef2ce6f673dad5bf0a1663801";
+    const BTC_ADDRESS: &str = "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy";
+
+    /// Serves Esplora `/address/:address` and JSON-RPC `eth_getBalance` with fixed balances
+    async fn mock_backend(sats: u64, wei: u128) -> String {
+        let server = MockServer::start(move |request: &MockRequest| {
+            if request.method == "GET" && request.path.starts_with("/address/") {
+                serde_json::json!({
+                    "chain_stats": { "funded_txo_sum": sats + 5_000, "spent_txo_sum": 5_000 },
+                    "mempool_stats": { "funded_txo_sum": 0, "spent_txo_sum": 0 },
+                })
+            } else {
+                let request: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": format!("{:#x}", wei) })
+            }
+            .to_string()
+        })
+        .await;
+        server.url().to_string()
+    }
+
+    fn chain_config(url: &str, threshold: Option<u64>) -> ChainConfig {
+        let mut config: ChainConfig = serde_json::from_value(serde_json::json!({
+            "executor_address": "",
+            "relay_private_key": RELAY_KEY,
+            "rpc_url": url,
+            "registry_address": "",
+            "assets": [],
+            "source_timelock": 100,
+            "destination_timelock": 50,
+            "chain_id": "",
+            "indexer_url": url,
+        }))
+        .unwrap();
+        config.low_balance_threshold = threshold.map(u128::from);
+        config
+    }
+
+    #[tokio::test]
+    async fn test_balances_are_reported_per_chain_with_low_flag() {
+        let url = mock_backend(40_000, 3_000_000_000_000_000_000).await;
+
+        let mut bitcoin = chain_config(&url, Some(50_000));
+        bitcoin.relayer_address = Some(BTC_ADDRESS.to_string());
+        let config = AppConfig {
+            chains: HashMap::from([
+                ("bitcoin_testnet".to_string(), bitcoin),
+                ("avalanche_testnet".to_string(), chain_config(&url, Some(1_000_000_000_000_000_000))),
+                ("arbitrum_sepolia".to_string(), chain_config(&url, None)),
+            ]),
+            strict: false,
+            spreads: Vec::new(),
+            supported_pairs: Vec::new(),
+            registry_retry: Default::default(),
+        };
+
+        let balances = relayer_balances(&config).await;
+        let chains: Vec<_> = balances.iter().map(|b| b.chain.as_str()).collect();
+        assert_eq!(chains, vec!["arbitrum_sepolia", "avalanche_testnet", "bitcoin_testnet"]);
+
+        let relay_account = PrivateKeySigner::from_bytes(&FixedBytes::from_hex(RELAY_KEY).unwrap()).unwrap().address();
+        assert_eq!(balances[0].address, relay_account.to_string());
+        assert_eq!(balances[0].balance, "3000000000000000000");
+        assert!(!balances[0].low_balance);
+
+        assert_eq!(balances[1].balance, "3000000000000000000");
+        assert_eq!(balances[1].low_balance_threshold.as_deref(), Some("1000000000000000000"));
+        assert!(!balances[1].low_balance);
+
+        assert_eq!(balances[2].address, BTC_ADDRESS);
+        assert_eq!(balances[2].balance, "40000");
+        assert!(balances[2].low_balance);
+        assert!(balances[2].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_chain_is_flagged_not_fatal() {
+        let url = mock_backend(0, 0).await;
+        let config = AppConfig {
+            chains: HashMap::from([("bitcoin_testnet".to_string(), chain_config(&url, None))]),
+            strict: false,
+            spreads: Vec::new(),
+            supported_pairs: Vec::new(),
+            registry_retry: Default::default(),
+        };
+
+        let balances = relayer_balances(&config).await;
+        assert_eq!(balances.len(), 1);
+        assert!(balances[0].low_balance);
+        assert!(balances[0].error.as_deref().unwrap().contains("relayer_address"));
+    }
+}
@@ -1,161 +1,140 @@
 use anyhow::Result;
-use bitcoin::{
-    key::{Secp256k1, XOnlyPublicKey}, opcodes::all::{OP_CHECKSIG, OP_CHECKSIGADD, OP_CSV, OP_DROP, OP_EQUALVERIFY, OP_NUMEQUAL, OP_SHA256}, secp256k1::{PublicKey, SecretKey}, taproot::{TaprootBuilder, TaprootSpendInfo}, Address, KnownHrp, Network, ScriptBuf
-};
-use sha2::{Digest, Sha256};
+use bitcoin::{key::XOnlyPublicKey, Address, Network};
 use alloy::hex;
-use once_cell::sync::Lazy;
-
-/// Garden internal key for Taproot HTLC addresses
-///
-/// Generates a deterministic internal key using:
-/// 1. SHA256("GardenHTLC") as scalar r
-/// 2. BIP-341 H point
-/// 3. r*G + H for final public key
-pub static GARDEN_NUMS: Lazy<XOnlyPublicKey> = Lazy::new(|| {
-    // Step 1: Hash "GardenHTLC" → r
-    let r = Sha256::digest(b"GardenHTLC");
-
-    // Step 2: Parse the H point from BIP-341
-    const H_HEX: &str = "0250929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
-    let h_bytes = hex::decode(H_HEX).expect("Invalid hex in GARDEN_NUMS_KEY");
-    let h = PublicKey::from_slice(&h_bytes).expect("Invalid H point in GARDEN_NUMS_KEY");
-
-    // Step 3: r * G
-    let secp = Secp256k1::new();
-    let r_scalar = SecretKey::from_slice(&r).expect("Invalid scalar in GARDEN_NUMS_KEY");
-    let r_g = PublicKey::from_secret_key(&secp, &r_scalar);
-
-    // Step 4: H + r*G
-    let nums = h
-        .combine(&r_g)
-        .expect("Point addition failed in GARDEN_NUMS_KEY");
-
-    // Step 5: Convert to x-only
-    let (xonly, _) = nums.x_only_public_key();
-    xonly
-});
+use bitcoin_primitives::htlc::{BitcoinHTLC, GARDEN_NUMS};
+use crate::primitives::HashFunction;
+use serde::Serialize;
+use std::str::FromStr;
+
+pub use bitcoin_primitives::htlc::Leaf;
 
 /// HTLC parameters needed for address generation
 #[derive(Debug, Clone)]
 pub struct HTLCParams {
-    pub secret_hash: [u8; 32],
+    pub secret_hash: Vec<u8>,
+    pub hash_function: HashFunction,
     pub redeemer_pubkey: XOnlyPublicKey,
     pub initiator_pubkey: XOnlyPublicKey,
     pub timelock: u32,
 }
 
-/// Generates a Taproot HTLC address with three spending conditions:
-/// 1. Redeem path: Requires the secret and redeemer's signature
-/// 2. Refund path: Allows initiator to claim funds after timelock expires
-/// 3. Instant refund: Enables cooperative cancellation by both parties
-///
-/// # Arguments
-/// * `htlc_params` - HTLC parameters including keys, timelock, and secret hash
-/// * `network` - Bitcoin network (Mainnet, Testnet, etc.)
-///
-/// # Returns
-/// * `Result<Address>` - The generated Taproot HTLC address or an error
-pub fn get_htlc_address(htlc_params: &HTLCParams, network: Network) -> Result<Address> {
-    let secp = Secp256k1::new();
-    let internal_key = *GARDEN_NUMS;
-    let taproot_spend_info = construct_taproot_spend_info(htlc_params)?;
-
-    let htlc_address = Address::p2tr(
-        &secp,
-        internal_key,
-        taproot_spend_info.merkle_root(),
-        KnownHrp::from(network),
-    );
-
-    Ok(htlc_address)
+/// Spend conditions of an HTLC, letting a user verify the contract before funding it
+#[derive(Debug, Clone, Serialize)]
+pub struct HtlcSpendInfo {
+    pub address: String,
+    pub internal_key: String,
+    pub timelock: u32,
+    /// Block at which the refund path opens, once the funding has confirmed
+    pub expiry_height: Option<u64>,
+    pub leaves: Vec<LeafInfo>,
 }
 
-/// Constructs a Taproot tree with three spending conditions in a Huffman tree structure
-fn construct_taproot_spend_info(htlc_params: &HTLCParams) -> Result<TaprootSpendInfo> {
-    // Create simple script leaves for now
-    let redeem_leaf = redeem_leaf(&htlc_params.secret_hash, &htlc_params.redeemer_pubkey);
-    let refund_leaf = refund_leaf(htlc_params.timelock as u64, &htlc_params.initiator_pubkey);
-    let instant_refund_leaf = instant_refund_leaf(&htlc_params.initiator_pubkey, &htlc_params.redeemer_pubkey);
-
-    let secp = Secp256k1::new();
-    let mut taproot_builder = TaprootBuilder::new();
-
-    // Add leaves to the Taproot tree with weights (1 for redeem, 2 for others)
-    taproot_builder = taproot_builder
-        .add_leaf(1, redeem_leaf)
-        .map_err(|e| anyhow::anyhow!("Unable to add redeem leaf to Taproot tree: {e}"))?
-        .add_leaf(2, refund_leaf)
-        .map_err(|e| anyhow::anyhow!("Unable to add refund leaf to Taproot tree: {e}"))?
-        .add_leaf(2, instant_refund_leaf)
-        .map_err(|e| anyhow::anyhow!("Unable to add instant refund leaf to Taproot tree: {e}"))?;
-
-    if !taproot_builder.is_finalizable() {
-        return Err(anyhow::anyhow!("Taproot builder is not in a finalizable state"));
-    }
-    
-    let internal_key = *GARDEN_NUMS;
-
-    taproot_builder
-        .finalize(&secp, internal_key)
-        .map_err(|_| anyhow::anyhow!("Failed to finalize Taproot spend info"))
+#[derive(Debug, Clone, Serialize)]
+pub struct LeafInfo {
+    pub leaf: Leaf,
+    pub condition: String,
+    pub script: String,
+    /// Proof that `script` is committed to by `address`, as placed in the spending witness
+    pub control_block: String,
 }
 
+impl HTLCParams {
+    /// Parses the hex secret hash and x-only public keys stored on a swap
+    pub fn parse(
+        secret_hash: &str,
+        hash_function: HashFunction,
+        initiator: &str,
+        redeemer: &str,
+        timelock: i32,
+    ) -> Result<Self> {
+        let secret_hash = hex::decode(secret_hash.strip_prefix("0x").unwrap_or(secret_hash))?;
+        let initiator_pubkey = XOnlyPublicKey::from_str(initiator)
+            .map_err(|_| anyhow::anyhow!("Invalid initiator public key: {}", initiator))?;
+        let redeemer_pubkey = XOnlyPublicKey::from_str(redeemer)
+            .map_err(|_| anyhow::anyhow!("Invalid redeemer public key: {}", redeemer))?;
+        let timelock = u32::try_from(timelock).map_err(|_| anyhow::anyhow!("Invalid timelock: {}", timelock))?;
+
+        Ok(Self {
+            secret_hash,
+            hash_function,
+            redeemer_pubkey,
+            initiator_pubkey,
+            timelock,
+        })
+    }
 
-pub fn redeem_leaf(secret_hash: &[u8; 32], redeemer_pubkey: &XOnlyPublicKey) -> ScriptBuf {
-    bitcoin::script::Builder::new()
-        .push_opcode(OP_SHA256)
-        .push_slice(secret_hash)
-        .push_opcode(OP_EQUALVERIFY)
-        .push_slice(redeemer_pubkey.serialize())
-        .push_opcode(OP_CHECKSIG)
-        .into_script()
-}
+    /// The HTLC the Bitcoin executor and watcher build for these parameters
+    pub fn htlc(&self, network: Network) -> Result<BitcoinHTLC> {
+        Ok(BitcoinHTLC::new(
+            hex::encode(&self.secret_hash),
+            self.initiator_pubkey.to_string(),
+            self.redeemer_pubkey.to_string(),
+            self.timelock as i64,
+            network,
+        )?
+        .with_hash_function(self.hash_function))
+    }
 
-/// Creates a Bitcoin script that allows refunding after a timelock expires.
-///
-/// # Arguments
-/// * `timelock` - Number of blocks to lock the funds
-/// * `initiator_pubkey` - Public key of the initiator who can claim the refund
-///
-/// # Returns
-/// A script that enforces the timelock and verifies the initiator's signature
-pub fn refund_leaf(timelock: u64, initiator_pubkey: &XOnlyPublicKey) -> ScriptBuf {
-    bitcoin::script::Builder::new()
-        .push_int(timelock as i64)
-        .push_opcode(OP_CSV)
-        .push_opcode(OP_DROP)
-        .push_slice(&initiator_pubkey.serialize())
-        .push_opcode(OP_CHECKSIG)
-        .into_script()
-}
+    /// Describes the derived address and each spend path. `funded_at` is the
+    /// block the funding confirmed in, if it has.
+    pub fn spend_info(&self, network: Network, funded_at: Option<u64>) -> Result<HtlcSpendInfo> {
+        let htlc = self.htlc(network)?;
+        let leaves = Leaf::ALL
+            .into_iter()
+            .map(|leaf| {
+                let info = htlc.control_block_for(leaf)?;
+                Ok(LeafInfo {
+                    leaf,
+                    condition: self.condition(leaf),
+                    script: hex::encode(info.leaf_script.as_bytes()),
+                    control_block: hex::encode(&info.control_block),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(HtlcSpendInfo {
+            address: htlc.address()?.to_string(),
+            internal_key: GARDEN_NUMS.to_string(),
+            timelock: self.timelock,
+            expiry_height: funded_at.map(|height| height + self.timelock as u64),
+            leaves,
+        })
+    }
 
-/// Creates a Bitcoin script that requires both initiator and redeemer signatures for instant refund.
-///
-/// # Arguments
-/// * `initiator_pubkey` - Public key of the initiator
-/// * `redeemer_pubkey` - Public key of the redeemer
-///
-/// # Returns
-/// A script that enforces both parties must sign to execute the refund
-pub fn instant_refund_leaf(
-    initiator_pubkey: &XOnlyPublicKey,
-    redeemer_pubkey: &XOnlyPublicKey,
-) -> ScriptBuf {
-    bitcoin::script::Builder::new()
-        .push_slice(&initiator_pubkey.serialize())
-        .push_opcode(OP_CHECKSIG)
-        .push_slice(&redeemer_pubkey.serialize())
-        .push_opcode(OP_CHECKSIGADD)
-        .push_int(2)
-        .push_opcode(OP_NUMEQUAL)
-        .into_script()
+    fn condition(&self, leaf: Leaf) -> String {
+        match leaf {
+            Leaf::Redeem => format!(
+                "Redeemer {} can spend at any time by revealing the secret whose {:?} hash is {}, with their signature",
+                self.redeemer_pubkey,
+                self.hash_function,
+                hex::encode(&self.secret_hash)
+            ),
+            Leaf::Refund => format!(
+                "Initiator {} can spend alone once {} blocks have passed since the funding confirmed",
+                self.initiator_pubkey, self.timelock
+            ),
+            Leaf::InstantRefund => format!(
+                "Initiator {} and redeemer {} can spend together at any time",
+                self.initiator_pubkey, self.redeemer_pubkey
+            ),
+        }
+    }
 }
 
+/// Taproot HTLC address with three spending conditions, built by the same
+/// `BitcoinHTLC` the executor and watcher use:
+/// 1. Redeem path: Requires the secret and redeemer's signature
+/// 2. Refund path: Allows initiator to claim funds after timelock expires
+/// 3. Instant refund: Enables cooperative cancellation by both parties
+pub fn get_htlc_address(htlc_params: &HTLCParams, network: Network) -> Result<Address> {
+    htlc_params.htlc(network)?.address()
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bitcoin::opcodes::all::{OP_HASH160, OP_SHA256};
+    use bitcoin_primitives::htlc::verify_control_block;
 
     #[test]
     fn test_get_htlc_address() {
@@ -164,13 +143,14 @@ mod tests {
         let redeemer_pubkey_str = "4c77d732a1331bfcbf2acfca28ebf661ee87d2a490e269b2ebb96c153f256202";
         
 
-        let secret_hash = hex::decode(secret_hash_str).unwrap().try_into().unwrap();
+        let secret_hash = hex::decode(secret_hash_str).unwrap();
         let initiator_pubkey = hex::decode(initiator_pubkey_str).unwrap();
         let redeemer_pubkey = hex::decode(redeemer_pubkey_str).unwrap();
 
 
         let htlc_params = HTLCParams {
             secret_hash: secret_hash,
+            hash_function: HashFunction::Sha256,
             redeemer_pubkey: XOnlyPublicKey::from_slice(&redeemer_pubkey).unwrap(),
             initiator_pubkey: XOnlyPublicKey::from_slice(&initiator_pubkey).unwrap(),
             timelock: 2,
@@ -179,4 +159,85 @@ mod tests {
         let address = get_htlc_address(&htlc_params, Network::Testnet4).unwrap();
         println!("HTLC address: {}", address);
     }
-}
\ No newline at end of file
+
+    fn htlc_params(secret_hash: &str, hash_function: HashFunction) -> HTLCParams {
+        HTLCParams {
+            secret_hash: hex::decode(secret_hash).unwrap(),
+            hash_function,
+            redeemer_pubkey: XOnlyPublicKey::from_slice(&hex::decode("4c77d732a1331bfcbf2acfca28ebf661ee87d2a490e269b2ebb96c153f256202").unwrap()).unwrap(),
+            initiator_pubkey: XOnlyPublicKey::from_slice(&hex::decode("727dde7d4e0726212ccbd76e6ed71f1bceb957082023c39be18cb93ff93773fa").unwrap()).unwrap(),
+            timelock: 2,
+        }
+    }
+
+    fn redeem_script(params: &HTLCParams) -> Vec<u8> {
+        let info = params.spend_info(Network::Testnet4, None).unwrap();
+        hex::decode(&info.leaves[0].script).unwrap()
+    }
+
+    #[test]
+    fn test_hash160_redeem_leaf() {
+        let params = htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb", HashFunction::Hash160);
+        assert_eq!(redeem_script(&params)[0], OP_HASH160.to_u8());
+
+        let sha256_params = htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb517e31c175d54f5a67de8d65", HashFunction::Sha256);
+        assert_ne!(
+            get_htlc_address(&params, Network::Testnet4).unwrap(),
+            get_htlc_address(&sha256_params, Network::Testnet4).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sha256d_redeem_leaf() {
+        let secret_hash = "ca76797b519b763a56845f1b02c3a46046ec71eb517e31c175d54f5a67de8d65";
+        let params = htlc_params(secret_hash, HashFunction::Sha256d);
+        assert_eq!(&redeem_script(&params)[..2], &[OP_SHA256.to_u8(), OP_SHA256.to_u8()]);
+
+        let sha256_params = htlc_params(secret_hash, HashFunction::Sha256);
+        assert_ne!(
+            get_htlc_address(&params, Network::Testnet4).unwrap(),
+            get_htlc_address(&sha256_params, Network::Testnet4).unwrap()
+        );
+        assert!(get_htlc_address(&htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb", HashFunction::Sha256d), Network::Testnet4).is_err());
+    }
+
+    #[test]
+    fn test_secret_hash_length_must_match_hash_function() {
+        let params = htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb517e31c175d54f5a67de8d65", HashFunction::Hash160);
+        assert!(get_htlc_address(&params, Network::Testnet4).is_err());
+
+        let params = htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb", HashFunction::Sha256);
+        assert!(get_htlc_address(&params, Network::Testnet4).is_err());
+    }
+
+    #[test]
+    fn test_spend_info_matches_executor_htlc() {
+        let params = htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb517e31c175d54f5a67de8d65", HashFunction::Sha256);
+        let info = params.spend_info(Network::Testnet4, Some(100)).unwrap();
+        let htlc = params.htlc(Network::Testnet4).unwrap();
+
+        assert_eq!(info.address, htlc.address().unwrap().to_string());
+        assert_eq!(info.internal_key, GARDEN_NUMS.to_string());
+        assert_eq!(info.expiry_height, Some(102));
+        assert_eq!(info.leaves.iter().map(|l| l.leaf).collect::<Vec<_>>(), Leaf::ALL.to_vec());
+
+        // Each published script and control block proves itself against the address
+        let internal_key = XOnlyPublicKey::from_str(&info.internal_key).unwrap();
+        for (published, (leaf, script)) in info.leaves.iter().zip(htlc.leaves().unwrap()) {
+            assert_eq!(published.script, hex::encode(script.as_bytes()));
+            let merkle_root = htlc.control_block_for(leaf).unwrap().merkle_root;
+            assert!(verify_control_block(
+                &internal_key,
+                merkle_root,
+                &script,
+                &hex::decode(&published.control_block).unwrap()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unfunded_htlc_has_no_expiry_height() {
+        let params = htlc_params("ca76797b519b763a56845f1b02c3a46046ec71eb517e31c175d54f5a67de8d65", HashFunction::Sha256);
+        assert_eq!(params.spend_info(Network::Testnet4, None).unwrap().expiry_height, None);
+    }
+}
@@ -1,36 +1,272 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+
+use crate::fees::{PairSpread, BPS_DENOMINATOR};
+use crate::gas::GasSettings;
+use crate::primitives::Chain;
+use crate::retry::RetrySettings;
+use crate::urls::{IndexerUrl, RpcUrl};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Asset {
     pub id: String,
     pub atomic_swap_address: String,
+    /// ERC20 contract of the asset; unused for native assets
+    #[serde(default)]
     pub token_address: String,
+    #[serde(default)]
+    pub kind: AssetKind,
+}
+
+/// How an asset is held on an EVM chain, which decides the registry method
+/// deposit addresses are derived with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    /// The chain's own currency, e.g. AVAX
+    Native,
+    #[default]
+    Erc20,
+}
+
+/// A directed "chain:asset" pair orders may be created for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportedPair {
+    pub from: String,
+    pub to: String,
+}
+
+impl SupportedPair {
+    /// Whether orders from the normalized `from` to `to` are allowed by this entry
+    pub fn matches(&self, from: &str, to: &str) -> bool {
+        self.from.trim().eq_ignore_ascii_case(from) && self.to.trim().eq_ignore_ascii_case(to)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChainConfig {
     pub executor_address: String,
     pub relay_private_key: String,
-    pub rpc_url: String,
+    pub rpc_url: RpcUrl,
     pub registry_address: String,
     pub assets: Vec<Asset>,
+    /// Source HTLC timelock in blocks; leave unset when `source_timelock_secs` is given
+    #[serde(default)]
     pub source_timelock: i32,
+    /// Destination HTLC timelock in blocks; leave unset when `destination_timelock_secs` is given
+    #[serde(default)]
     pub destination_timelock: i32,
+    /// Source HTLC timelock as a duration, converted to blocks with the chain's block time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_timelock_secs: Option<u64>,
+    /// Destination HTLC timelock as a duration, converted to blocks with the chain's block time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_timelock_secs: Option<u64>,
+    /// Overrides the chain's timelock block time used for duration timelocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time_ms: Option<u64>,
     pub chain_id: String,
+    /// Esplora indexer used to look up Bitcoin transactions on this chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexer_url: Option<IndexerUrl>,
+    /// Relayer account whose balance `GET /relayer/balances` reports. Required on
+    /// Bitcoin; EVM chains default to the `relay_private_key` account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayer_address: Option<String>,
+    /// Balance, in the chain's smallest unit, below which the relayer is flagged as low
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_balance_threshold: Option<u128>,
+    /// Gas policy for EVM transactions on this chain
+    #[serde(default)]
+    pub gas: GasSettings,
+}
+
+impl ChainConfig {
+    /// Source HTLC timelock in blocks of `chain`
+    pub fn source_timelock_blocks(&self, chain: &Chain) -> Result<i32> {
+        self.timelock_blocks("source", self.source_timelock, self.source_timelock_secs, chain)
+    }
+
+    /// Destination HTLC timelock in blocks of `chain`
+    pub fn destination_timelock_blocks(&self, chain: &Chain) -> Result<i32> {
+        self.timelock_blocks("destination", self.destination_timelock, self.destination_timelock_secs, chain)
+    }
+
+    fn timelock_blocks(&self, side: &str, blocks: i32, secs: Option<u64>, chain: &Chain) -> Result<i32> {
+        match (blocks, secs) {
+            (0, Some(secs)) => {
+                let block_time_ms = self.block_time_ms.unwrap_or_else(|| chain.timelock_block_time_ms());
+                duration_to_blocks(secs, block_time_ms)
+            }
+            (blocks, None) if blocks > 0 => Ok(blocks),
+            (_, Some(_)) => Err(anyhow!(
+                "{} has both {}_timelock and {}_timelock_secs set; configure one", chain, side, side
+            )),
+            (_, None) => Err(anyhow!("{} has no {} timelock configured", chain, side)),
+        }
+    }
+}
+
+/// Largest relative timelock a Bitcoin HTLC can enforce; BIP 68 encodes it in 16 bits
+pub const MAX_BITCOIN_TIMELOCK_BLOCKS: i32 = u16::MAX as i32;
+
+/// Blocks produced in `secs` seconds at one block every `block_time_ms`, rounded up
+/// so the timelock never expires early
+pub fn duration_to_blocks(secs: u64, block_time_ms: u64) -> Result<i32> {
+    if block_time_ms == 0 {
+        return Err(anyhow!("Block time must be positive"));
+    }
+    let blocks = (secs * 1000).div_ceil(block_time_ms);
+    i32::try_from(blocks).map_err(|_| anyhow!("Timelock of {} seconds is too many blocks", secs))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub chains: HashMap<String, ChainConfig>,
+    /// Refuse to start when any chain entry is invalid instead of skipping it
+    #[serde(default)]
+    pub strict: bool,
+    /// Relayer spreads per asset pair; pairs not listed are matched without a fee
+    #[serde(default)]
+    pub spreads: Vec<PairSpread>,
+    /// Directed pairs orders may be created for; empty allows every configured combination
+    #[serde(default)]
+    pub supported_pairs: Vec<SupportedPair>,
+    /// Retry policy for registry calls made while creating an order
+    #[serde(default)]
+    pub registry_retry: RetrySettings,
 }
 
 impl AppConfig {
     pub fn from_file(path: &str) -> Result<Self> {
         let config_content = fs::read_to_string(path)?;
         let config: AppConfig = serde_json::from_str(&config_content)?;
+        config.validate()?;
         Ok(config)
     }
-}
\ No newline at end of file
+
+    /// Rejects settings no order could be matched under
+    pub fn validate(&self) -> Result<()> {
+        for spread in &self.spreads {
+            if spread.bps >= BPS_DENOMINATOR {
+                return Err(anyhow!(
+                    "Spread for {} -> {} is {} bps; it must be below {}",
+                    spread.from, spread.to, spread.bps, BPS_DENOMINATOR
+                ));
+            }
+        }
+        for (name, chain_config) in &self.chains {
+            let Ok(chain) = name.parse::<Chain>() else { continue };
+            if !chain.is_bitcoin() {
+                continue;
+            }
+            let timelocks = [
+                ("source", chain_config.source_timelock_blocks(&chain)),
+                ("destination", chain_config.destination_timelock_blocks(&chain)),
+            ];
+            for (side, blocks) in timelocks {
+                if let Ok(blocks) = blocks {
+                    if blocks > MAX_BITCOIN_TIMELOCK_BLOCKS {
+                        return Err(anyhow!(
+                            "{} {} timelock is {} blocks; Bitcoin allows at most {}",
+                            name, side, blocks, MAX_BITCOIN_TIMELOCK_BLOCKS
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIVE_DAYS_SECS: u64 = 5 * 24 * 60 * 60;
+
+    fn chain_config(blocks: i32, secs: Option<u64>) -> ChainConfig {
+        ChainConfig {
+            executor_address: String::new(),
+            relay_private_key: String::new(),
+            rpc_url: "http://localhost:8545".parse().unwrap(),
+            registry_address: String::new(),
+            assets: vec![],
+            source_timelock: blocks,
+            destination_timelock: blocks,
+            source_timelock_secs: secs,
+            destination_timelock_secs: secs,
+            block_time_ms: None,
+            chain_id: String::new(),
+            indexer_url: None,
+            relayer_address: None,
+            low_balance_threshold: None,
+            gas: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_duration_converts_per_chain_block_time() {
+        let config = chain_config(0, Some(FIVE_DAYS_SECS));
+        assert_eq!(config.source_timelock_blocks(&Chain::BitcoinTestnet).unwrap(), 720);
+        assert_eq!(config.source_timelock_blocks(&Chain::AvalancheTestnet).unwrap(), 216_000);
+        assert_eq!(config.destination_timelock_blocks(&Chain::ArbitrumSepolia).unwrap(), 36_000);
+    }
+
+    #[test]
+    fn test_partial_block_rounds_up_and_block_time_override() {
+        assert_eq!(duration_to_blocks(601, 600_000).unwrap(), 2);
+        assert!(duration_to_blocks(600, 0).is_err());
+
+        let mut config = chain_config(0, Some(FIVE_DAYS_SECS));
+        config.block_time_ms = Some(300_000);
+        assert_eq!(config.source_timelock_blocks(&Chain::BitcoinTestnet).unwrap(), 1440);
+    }
+
+    #[test]
+    fn test_spread_of_whole_amount_is_rejected() {
+        let config = |bps| AppConfig {
+            chains: HashMap::new(),
+            strict: false,
+            spreads: vec![PairSpread { from: "avalanche_testnet:usdc".to_string(), to: "bitcoin_testnet:btc".to_string(), bps }],
+            supported_pairs: Vec::new(),
+            registry_retry: Default::default(),
+        };
+        assert!(config(0).validate().is_ok());
+        assert!(config(9_999).validate().is_ok());
+        assert!(config(10_000).validate().is_err());
+        assert!(config(u32::MAX).validate().is_err());
+    }
+
+    #[test]
+    fn test_bitcoin_timelock_above_u16_is_rejected() {
+        let config = |name: &str, blocks| AppConfig {
+            chains: HashMap::from([(name.to_string(), chain_config(blocks, None))]),
+            strict: false,
+            spreads: Vec::new(),
+            supported_pairs: Vec::new(),
+            registry_retry: Default::default(),
+        };
+        assert!(config("bitcoin_testnet", MAX_BITCOIN_TIMELOCK_BLOCKS).validate().is_ok());
+        let err = config("bitcoin_testnet", MAX_BITCOIN_TIMELOCK_BLOCKS + 1).validate().unwrap_err();
+        assert!(err.to_string().contains("bitcoin_testnet source timelock"));
+        // EVM timelocks are not bounded by BIP 68
+        assert!(config("avalanche_testnet", MAX_BITCOIN_TIMELOCK_BLOCKS + 1).validate().is_ok());
+
+        // A duration that converts past the limit is caught too
+        let mut app_config = config("bitcoin_testnet", 144);
+        let chain = app_config.chains.get_mut("bitcoin_testnet").unwrap();
+        chain.destination_timelock = 0;
+        chain.destination_timelock_secs = Some(2 * 365 * 24 * 60 * 60);
+        assert!(app_config.validate().unwrap_err().to_string().contains("destination timelock"));
+    }
+
+    #[test]
+    fn test_block_count_and_duration_are_exclusive() {
+        assert_eq!(chain_config(144, None).source_timelock_blocks(&Chain::BitcoinTestnet).unwrap(), 144);
+        assert!(chain_config(144, Some(FIVE_DAYS_SECS)).source_timelock_blocks(&Chain::BitcoinTestnet).is_err());
+        assert!(chain_config(0, None).destination_timelock_blocks(&Chain::BitcoinTestnet).is_err());
+    }
+}
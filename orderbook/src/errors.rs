@@ -0,0 +1,137 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response as HttpResponse},
+    Json,
+};
+use mongodb::error::{ErrorKind, WriteFailure};
+use std::fmt;
+use tracing::error;
+
+use crate::primitives::Response;
+
+/// MongoDB error code for a unique index violation
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/// Errors returned by the HTTP handlers, each mapped to a single status code
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request is malformed or violates a business rule
+    Validation(String),
+    NotFound(String),
+    /// The request clashes with an existing resource
+    Conflict(String),
+    /// A dependency such as a chain RPC could not be reached
+    UpstreamUnavailable(String),
+    /// Anything else; details are logged but never returned to the client
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::UpstreamUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Maps an error from the order service, keeping any `ApiError` it carries
+    /// and treating everything else as a validation failure
+    pub fn from_order_error(e: anyhow::Error) -> Self {
+        match e.downcast::<ApiError>() {
+            Ok(api_error) => api_error,
+            Err(e) => ApiError::Validation(format!("Failed to get matched order: {}", e)),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Validation(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::UpstreamUnavailable(msg)
+            | ApiError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<mongodb::error::Error> for ApiError {
+    fn from(e: mongodb::error::Error) -> Self {
+        if let ErrorKind::Write(WriteFailure::WriteError(write_error)) = e.kind.as_ref() {
+            if write_error.code == DUPLICATE_KEY_CODE {
+                return ApiError::Conflict("Order already exists".to_string());
+            }
+        }
+        ApiError::Internal(format!("Database error: {}", e))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> HttpResponse {
+        let status = self.status_code();
+        let message = match self {
+            ApiError::Internal(details) => {
+                error!("Internal error: {}", details);
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+
+        (status, Json(Response::<()>::error(message))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    async fn error_body(err: ApiError) -> String {
+        let response = err.into_response();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_status_codes() {
+        let cases = [
+            (ApiError::Validation("bad".to_string()), StatusCode::BAD_REQUEST),
+            (ApiError::NotFound("missing".to_string()), StatusCode::NOT_FOUND),
+            (ApiError::Conflict("dup".to_string()), StatusCode::CONFLICT),
+            (ApiError::UpstreamUnavailable("rpc".to_string()), StatusCode::SERVICE_UNAVAILABLE),
+            (ApiError::Internal("db".to_string()), StatusCode::INTERNAL_SERVER_ERROR),
+        ];
+
+        for (err, expected) in cases {
+            assert_eq!(err.into_response().status(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_body_uses_response_format() {
+        let body = error_body(ApiError::NotFound("Order not found".to_string())).await;
+        assert_eq!(body, r#"{"status":"error","error":"Order not found"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_internal_error_hides_details() {
+        let body = error_body(ApiError::Internal("connection refused at 10.0.0.1".to_string())).await;
+        assert!(!body.contains("10.0.0.1"));
+        assert!(body.contains("Internal server error"));
+    }
+
+    #[test]
+    fn test_order_errors_keep_api_error_variant() {
+        let err = ApiError::from_order_error(anyhow::Error::new(ApiError::UpstreamUnavailable("rpc down".to_string())));
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let err = ApiError::from_order_error(anyhow!("Unknown chain 'dogecoin'"));
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::primitives::Chain;
+
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+/// Smallest output Bitcoin nodes relay, in sats
+const BITCOIN_DUST_SATS: u64 = 546;
+
+/// Relayer spread charged on orders from one "chain:asset" to another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairSpread {
+    pub from: String,
+    pub to: String,
+    /// Share of the quoted destination amount kept by the relayer, in basis points
+    pub bps: u32,
+}
+
+impl PairSpread {
+    /// Whether this spread applies to orders between the normalized `from` and `to`
+    pub fn matches(&self, from: &str, to: &str) -> bool {
+        self.from.trim().eq_ignore_ascii_case(from) && self.to.trim().eq_ignore_ascii_case(to)
+    }
+}
+
+/// How an order's destination amount was derived from the amount it quoted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeBreakdown {
+    pub bps: u32,
+    /// Destination amount in the user's create order request
+    pub quoted_amount: String,
+    /// Part of the quoted amount kept by the relayer
+    pub fee_amount: String,
+    /// Amount the destination HTLC pays out
+    pub destination_amount: String,
+}
+
+/// Largest amount on `chain` that is still too small to pay out
+pub fn dust_limit(chain: &Chain) -> u64 {
+    match chain {
+        Chain::BitcoinTestnet => BITCOIN_DUST_SATS,
+        Chain::ArbitrumSepolia | Chain::AvalancheTestnet => 0,
+    }
+}
+
+/// Takes `bps` of `quoted_amount` as the relayer fee, rounding the fee down,
+/// and rejects a remaining amount that is not above `dust`
+pub fn apply_spread(quoted_amount: &str, bps: u32, dust: u64) -> Result<FeeBreakdown> {
+    if bps > BPS_DENOMINATOR {
+        return Err(anyhow!("Spread of {} bps exceeds 100%", bps));
+    }
+    let quoted = BigUint::from_str(quoted_amount)
+        .map_err(|e| anyhow!("Invalid destination amount '{}': {}", quoted_amount, e))?;
+
+    let fee = &quoted * bps / BPS_DENOMINATOR;
+    let destination = &quoted - &fee;
+    if destination <= BigUint::from(dust) {
+        return Err(anyhow!(
+            "Destination amount {} after a {} bps spread is at or below the dust limit of {}",
+            destination, bps, dust
+        ));
+    }
+
+    Ok(FeeBreakdown {
+        bps,
+        quoted_amount: quoted.to_string(),
+        fee_amount: fee.to_string(),
+        destination_amount: destination.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_50_bps_spread() {
+        let breakdown = apply_spread("1000000", 50, BITCOIN_DUST_SATS).unwrap();
+        assert_eq!(
+            breakdown,
+            FeeBreakdown {
+                bps: 50,
+                quoted_amount: "1000000".to_string(),
+                fee_amount: "5000".to_string(),
+                destination_amount: "995000".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_fee_rounds_down_on_large_amounts() {
+        // 1.000000000000000001 tokens with 18 decimals
+        let breakdown = apply_spread("1000000000000000001", 50, 0).unwrap();
+        assert_eq!(breakdown.fee_amount, "5000000000000000");
+        assert_eq!(breakdown.destination_amount, "995000000000000001");
+    }
+
+    #[test]
+    fn test_dust_and_invalid_spreads_rejected() {
+        assert!(apply_spread("548", 50, BITCOIN_DUST_SATS).is_err());
+        assert!(apply_spread("549", 50, BITCOIN_DUST_SATS).is_ok());
+        assert!(apply_spread("100", 10_001, 0).is_err());
+        assert!(apply_spread("100", 10_000, 0).is_err());
+        assert!(apply_spread("abc", 50, 0).is_err());
+    }
+
+    #[test]
+    fn test_pair_matching_ignores_case() {
+        let spread = PairSpread {
+            from: "Arbitrum_Sepolia:USDC".to_string(),
+            to: "bitcoin_testnet:btc".to_string(),
+            bps: 50,
+        };
+        assert!(spread.matches("arbitrum_sepolia:usdc", "bitcoin_testnet:btc"));
+        assert!(!spread.matches("bitcoin_testnet:btc", "arbitrum_sepolia:usdc"));
+    }
+}
@@ -0,0 +1,230 @@
+use alloy::{
+    eips::eip1559::Eip1559Estimation,
+    network::{Ethereum, Network, TransactionBuilder},
+    providers::{
+        fillers::{FillerControlFlow, TxFiller},
+        Provider, SendableTx,
+    },
+    transports::TransportResult,
+};
+use serde::{Deserialize, Serialize};
+
+/// Per-chain gas policy for EVM transactions.
+///
+/// Registry providers are built with a [`GasPolicyFiller`] in place of
+/// `.with_gas_estimation()`, so every transaction they send has its estimates
+/// adjusted by these settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasSettings {
+    /// Fixed priority fee in wei; the node's estimate is used when unset
+    #[serde(default)]
+    pub priority_fee_wei: Option<u128>,
+    /// Upper bound for `max_fee_per_gas` in wei
+    #[serde(default)]
+    pub max_fee_cap_wei: Option<u128>,
+    /// Multiplier applied to the estimated gas limit
+    #[serde(default = "default_gas_multiplier")]
+    pub gas_multiplier: f64,
+}
+
+fn default_gas_multiplier() -> f64 {
+    1.0
+}
+
+impl Default for GasSettings {
+    fn default() -> Self {
+        Self {
+            priority_fee_wei: None,
+            max_fee_cap_wei: None,
+            gas_multiplier: default_gas_multiplier(),
+        }
+    }
+}
+
+/// Gas fields to set on a transaction request
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasParams {
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl GasSettings {
+    /// Applies this policy to the node's gas and fee estimates
+    pub fn apply(&self, estimated_gas: u64, fees: Eip1559Estimation) -> GasParams {
+        let gas_limit = (estimated_gas as f64 * self.gas_multiplier).ceil() as u64;
+
+        let mut max_priority_fee_per_gas = self.priority_fee_wei.unwrap_or(fees.max_priority_fee_per_gas);
+        let mut max_fee_per_gas = fees.max_fee_per_gas.max(max_priority_fee_per_gas);
+        if let Some(cap) = self.max_fee_cap_wei {
+            max_fee_per_gas = max_fee_per_gas.min(cap);
+            max_priority_fee_per_gas = max_priority_fee_per_gas.min(max_fee_per_gas);
+        }
+
+        GasParams {
+            gas_limit,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+}
+
+/// Gas filler that estimates like alloy's `GasFiller` and then applies the
+/// chain's [`GasSettings`]. Fields a request already sets are kept.
+#[derive(Debug, Clone, Default)]
+pub struct GasPolicyFiller {
+    settings: GasSettings,
+}
+
+impl GasPolicyFiller {
+    pub fn new(settings: GasSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl TxFiller<Ethereum> for GasPolicyFiller {
+    type Fillable = GasParams;
+
+    fn status(&self, tx: &<Ethereum as Network>::TransactionRequest) -> FillerControlFlow {
+        if tx.gas_limit().is_some() && tx.max_fee_per_gas().is_some() && tx.max_priority_fee_per_gas().is_some() {
+            FillerControlFlow::Finished
+        } else {
+            FillerControlFlow::Ready
+        }
+    }
+
+    fn fill_sync(&self, _tx: &mut SendableTx<Ethereum>) {}
+
+    async fn prepare<P: Provider<Ethereum>>(
+        &self,
+        provider: &P,
+        tx: &<Ethereum as Network>::TransactionRequest,
+    ) -> TransportResult<Self::Fillable> {
+        let estimated_gas = match tx.gas_limit() {
+            Some(gas_limit) => gas_limit,
+            None => provider.estimate_gas(tx.clone()).await?,
+        };
+        let fees = provider.estimate_eip1559_fees().await?;
+
+        let params = self.settings.apply(estimated_gas, fees);
+        Ok(GasParams {
+            gas_limit: tx.gas_limit().unwrap_or(params.gas_limit),
+            max_fee_per_gas: tx.max_fee_per_gas().unwrap_or(params.max_fee_per_gas),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas().unwrap_or(params.max_priority_fee_per_gas),
+        })
+    }
+
+    async fn fill(
+        &self,
+        params: Self::Fillable,
+        mut tx: SendableTx<Ethereum>,
+    ) -> TransportResult<SendableTx<Ethereum>> {
+        if let Some(builder) = tx.as_mut_builder() {
+            builder.set_gas_limit(params.gas_limit);
+            builder.set_max_fee_per_gas(params.max_fee_per_gas);
+            builder.set_max_priority_fee_per_gas(params.max_priority_fee_per_gas);
+        }
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{providers::ProviderBuilder, rpc::types::TransactionRequest};
+    use bitcoin_primitives::mock_server::{MockRequest, MockServer};
+
+    fn estimate(max_fee: u128, priority_fee: u128) -> Eip1559Estimation {
+        Eip1559Estimation {
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority_fee,
+        }
+    }
+
+    #[test]
+    fn test_multiplier_applied_to_estimated_gas() {
+        let settings = GasSettings {
+            gas_multiplier: 1.5,
+            ..Default::default()
+        };
+        let params = settings.apply(100_000, estimate(30, 2));
+        assert_eq!(params.gas_limit, 150_000);
+        assert_eq!(params.max_fee_per_gas, 30);
+        assert_eq!(params.max_priority_fee_per_gas, 2);
+    }
+
+    #[test]
+    fn test_default_keeps_estimates() {
+        let params = GasSettings::default().apply(21_000, estimate(30, 2));
+        assert_eq!(
+            params,
+            GasParams {
+                gas_limit: 21_000,
+                max_fee_per_gas: 30,
+                max_priority_fee_per_gas: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_priority_fee_and_cap() {
+        let settings = GasSettings {
+            priority_fee_wei: Some(5),
+            max_fee_cap_wei: Some(20),
+            gas_multiplier: 1.0,
+        };
+        let params = settings.apply(21_000, estimate(30, 2));
+        assert_eq!(params.max_fee_per_gas, 20);
+        assert_eq!(params.max_priority_fee_per_gas, 5);
+
+        // The cap also bounds the priority fee
+        let settings = GasSettings {
+            priority_fee_wei: Some(50),
+            max_fee_cap_wei: Some(20),
+            gas_multiplier: 1.0,
+        };
+        let params = settings.apply(21_000, estimate(30, 2));
+        assert_eq!(params.max_priority_fee_per_gas, 20);
+    }
+
+    #[test]
+    fn test_missing_gas_config_uses_defaults() {
+        let settings: GasSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.gas_multiplier, 1.0);
+        assert!(settings.priority_fee_wei.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_provider_filler_applies_settings_to_node_estimate() {
+        let server = MockServer::start(|request: &MockRequest| {
+            let request: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            let result = match request["method"].as_str().unwrap() {
+                "eth_estimateGas" => serde_json::json!("0x186a0"),
+                "eth_feeHistory" => serde_json::json!({
+                    "oldestBlock": "0x1",
+                    "baseFeePerGas": ["0xa", "0xa"],
+                    "gasUsedRatio": [0.5],
+                    "reward": [["0x2"]],
+                }),
+                method => panic!("unexpected RPC call {}", method),
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }).to_string()
+        })
+        .await;
+
+        let settings = GasSettings {
+            priority_fee_wei: Some(5),
+            max_fee_cap_wei: None,
+            gas_multiplier: 1.5,
+        };
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .filler(GasPolicyFiller::new(settings))
+            .connect_http(server.url().parse().unwrap());
+
+        let filled = provider.fill(TransactionRequest::default()).await.unwrap();
+        let tx = filled.as_builder().unwrap();
+        assert_eq!(tx.gas_limit(), Some(150_000));
+        assert_eq!(tx.max_priority_fee_per_gas(), Some(5));
+    }
+}
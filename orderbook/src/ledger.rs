@@ -0,0 +1,497 @@
+use alloy::{
+    primitives::{b256, Address, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    transports::http::reqwest,
+};
+use anyhow::{anyhow, Result};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::config::AppConfig;
+use crate::primitives::{Chain, MatchedOrder, Swap};
+use crate::urls::{IndexerUrl, RpcUrl};
+
+/// What a ledger entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerKind {
+    /// Value locked into an HTLC
+    FundingIn,
+    /// Value paid to the redeemer
+    RedeemOut,
+    /// Value returned to the initiator
+    RefundOut,
+    /// Miner fee of a Bitcoin redeem or refund
+    NetworkFee,
+    /// Relayer spread taken from the quoted destination amount before funding.
+    /// It never reaches the chain, so it is not part of any leg's balance.
+    Spread,
+}
+
+/// One movement of value on one swap of an order
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerEntry {
+    pub kind: LedgerKind,
+    pub swap_id: String,
+    pub chain: Chain,
+    pub asset: String,
+    pub amount: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub txid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+}
+
+/// What went in and out of one swap
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LegTotals {
+    pub swap_id: String,
+    pub funded: String,
+    pub paid_out: String,
+    pub fees: String,
+    /// `funded == paid_out + fees`; false while the swap is still open, or when
+    /// the spend moved value to or from anywhere but the HTLC and its payee
+    pub balanced: bool,
+}
+
+/// Value movements of an order, in the order a swap settles: source funding,
+/// destination funding, destination payout, source payout
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderLedger {
+    pub create_id: String,
+    pub entries: Vec<LedgerEntry>,
+    pub legs: Vec<LegTotals>,
+}
+
+/// What a redeem or refund transaction did, as read from its chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendFacts {
+    /// Address the spend pays
+    pub payee: String,
+    /// Value the transaction sent to `payee`
+    pub paid_out: BigUint,
+    /// Miner fee of the transaction. EVM gas is paid by the sender, not out of the HTLC.
+    pub fee: BigUint,
+}
+
+#[derive(Deserialize)]
+struct EsploraTx {
+    fee: u64,
+    vout: Vec<EsploraOutput>,
+}
+
+#[derive(Deserialize)]
+struct EsploraOutput {
+    value: u64,
+    #[serde(default)]
+    scriptpubkey_address: Option<String>,
+}
+
+/// Value Bitcoin transaction `txid` pays to `payee`, and its fee, from an Esplora indexer
+pub async fn bitcoin_spend(indexer_url: &IndexerUrl, txid: &str, payee: &str) -> Result<SpendFacts> {
+    let url = format!("{}/tx/{}", indexer_url, txid);
+    let response = reqwest::get(&url).await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Indexer returned {} for transaction {}", response.status(), txid));
+    }
+    let tx: EsploraTx = response.json().await?;
+    let paid_out: u64 = tx
+        .vout
+        .iter()
+        .filter(|output| output.scriptpubkey_address.as_deref() == Some(payee))
+        .map(|output| output.value)
+        .sum();
+    Ok(SpendFacts { payee: payee.to_string(), paid_out: BigUint::from(paid_out), fee: BigUint::from(tx.fee) })
+}
+
+/// keccak256("Transfer(address,address,uint256)")
+const TRANSFER_TOPIC: B256 = b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// Value EVM transaction `txid` transferred of `token` to `payee`, from the
+/// ERC20 `Transfer` logs of its receipt. A native asset transfer leaves no log,
+/// so a successful spend of a native swap is taken to pay its `amount`.
+pub async fn evm_spend(rpc_url: &RpcUrl, txid: &str, swap: &Swap, payee: &str) -> Result<SpendFacts> {
+    let provider = ProviderBuilder::new().connect_http(rpc_url.url());
+    let hash = B256::from_str(txid).map_err(|e| anyhow!("Invalid transaction hash '{}': {}", txid, e))?;
+    let receipt = provider
+        .get_transaction_receipt(hash)
+        .await?
+        .ok_or_else(|| anyhow!("No receipt for transaction {}", txid))?;
+    let payee_address = Address::from_str(payee).map_err(|e| anyhow!("Invalid payee '{}': {}", payee, e))?;
+    if !receipt.status() {
+        return Err(anyhow!("Transaction {} reverted", txid));
+    }
+
+    let paid_out = if swap.token_address.is_empty() {
+        BigUint::from_str(&swap.amount).map_err(|_| anyhow!("Invalid amount '{}' on swap {}", swap.amount, swap.swap_id))?
+    } else {
+        let token = Address::from_str(&swap.token_address)
+            .map_err(|e| anyhow!("Invalid token address '{}': {}", swap.token_address, e))?;
+        let transferred: U256 = receipt
+            .inner
+            .logs()
+            .iter()
+            .filter(|log| log.address() == token)
+            .filter(|log| {
+                let topics = log.topics();
+                topics.len() == 3 && topics[0] == TRANSFER_TOPIC && topics[2] == payee_address.into_word()
+            })
+            .map(|log| U256::from_be_slice(&log.data().data))
+            .sum();
+        BigUint::from_bytes_be(&transferred.to_be_bytes::<32>())
+    };
+    Ok(SpendFacts { payee: payee.to_string(), paid_out, fee: BigUint::default() })
+}
+
+fn parse_amount(swap: &Swap, value: &str) -> Result<BigUint> {
+    BigUint::from_str(value).map_err(|_| anyhow!("Invalid amount '{}' on swap {}", value, swap.swap_id))
+}
+
+fn parse_block(block: &Option<String>) -> Option<u64> {
+    block.as_deref().and_then(|block| block.parse::<u64>().ok()).filter(|block| *block > 0)
+}
+
+fn non_empty(txid: &Option<String>) -> Option<&str> {
+    txid.as_deref().filter(|txid| !txid.is_empty())
+}
+
+fn entry(kind: LedgerKind, swap: &Swap, amount: &BigUint) -> LedgerEntry {
+    LedgerEntry {
+        kind,
+        swap_id: swap.swap_id.clone(),
+        chain: swap.chain.clone(),
+        asset: swap.asset.clone(),
+        amount: amount.to_string(),
+        txid: None,
+        block_number: None,
+        recipient: None,
+    }
+}
+
+fn funding_entry(swap: &Swap) -> Result<Option<(LedgerEntry, BigUint)>> {
+    let Some(txid) = non_empty(&swap.initiate_tx_hash) else {
+        return Ok(None);
+    };
+    let funded = parse_amount(swap, &swap.filled_amount)?;
+    let funded = if funded == BigUint::default() { parse_amount(swap, &swap.amount)? } else { funded };
+    let mut funding = entry(LedgerKind::FundingIn, swap, &funded);
+    funding.txid = Some(txid.to_string());
+    funding.block_number = parse_block(&swap.initiate_block_number);
+    Ok(Some((funding, funded)))
+}
+
+/// The redeem or refund of `swap`, if it was spent
+fn spend_of(swap: &Swap) -> Option<(LedgerKind, &str, &Option<String>)> {
+    if let Some(txid) = non_empty(&swap.redeem_tx_hash) {
+        Some((LedgerKind::RedeemOut, txid, &swap.redeem_block_number))
+    } else {
+        non_empty(&swap.refund_tx_hash).map(|txid| (LedgerKind::RefundOut, txid, &swap.refund_block_number))
+    }
+}
+
+/// Payout of a spent swap as read from its chain, followed by its miner fee
+fn payout_entries(swap: &Swap, spends: &HashMap<String, SpendFacts>) -> Result<Vec<LedgerEntry>> {
+    let Some((kind, txid, block)) = spend_of(swap) else {
+        return Ok(Vec::new());
+    };
+    let spend = spends
+        .get(txid)
+        .ok_or_else(|| anyhow!("Spend {} of swap {} was not looked up", txid, swap.swap_id))?;
+
+    let mut payout = entry(kind, swap, &spend.paid_out);
+    payout.txid = Some(txid.to_string());
+    payout.block_number = parse_block(block);
+    payout.recipient = Some(spend.payee.clone());
+
+    let mut entries = vec![payout];
+    if spend.fee > BigUint::default() {
+        let mut fee = entry(LedgerKind::NetworkFee, swap, &spend.fee);
+        fee.txid = Some(txid.to_string());
+        fee.block_number = parse_block(block);
+        entries.push(fee);
+    }
+    Ok(entries)
+}
+
+fn leg_totals(swap: &Swap, entries: &[LedgerEntry]) -> Result<LegTotals> {
+    let sum = |kinds: &[LedgerKind]| -> Result<BigUint> {
+        entries
+            .iter()
+            .filter(|entry| entry.swap_id == swap.swap_id && kinds.contains(&entry.kind))
+            .map(|entry| parse_amount(swap, &entry.amount))
+            .sum()
+    };
+    let funded = sum(&[LedgerKind::FundingIn])?;
+    let paid_out = sum(&[LedgerKind::RedeemOut, LedgerKind::RefundOut])?;
+    let fees = sum(&[LedgerKind::NetworkFee])?;
+    Ok(LegTotals {
+        swap_id: swap.swap_id.clone(),
+        balanced: funded > BigUint::default() && funded == &paid_out + &fees,
+        funded: funded.to_string(),
+        paid_out: paid_out.to_string(),
+        fees: fees.to_string(),
+    })
+}
+
+/// Builds the ledger of `order` from its persisted transactions and `spends`,
+/// the on-chain facts of each redeem and refund keyed by txid
+pub fn build_ledger(order: &MatchedOrder, spends: &HashMap<String, SpendFacts>) -> Result<OrderLedger> {
+    let source = &order.source_swap;
+    let destination = &order.destination_swap;
+    let source_funding = funding_entry(source)?;
+    let destination_funding = funding_entry(destination)?;
+
+    let mut entries = Vec::new();
+    if let Some((funding, _)) = &source_funding {
+        entries.push(funding.clone());
+    }
+    if let Some(fee) = &order.fee {
+        let mut spread = entry(LedgerKind::Spread, destination, &parse_amount(destination, &fee.fee_amount)?);
+        spread.recipient = Some(destination.initiator.clone());
+        entries.push(spread);
+    }
+    if let Some((funding, _)) = &destination_funding {
+        entries.push(funding.clone());
+        entries.extend(payout_entries(destination, spends)?);
+    }
+    if source_funding.is_some() {
+        entries.extend(payout_entries(source, spends)?);
+    }
+
+    let legs = vec![leg_totals(source, &entries)?, leg_totals(destination, &entries)?];
+    Ok(OrderLedger {
+        create_id: order.create_order.create_id.clone().unwrap_or_default(),
+        entries,
+        legs,
+    })
+}
+
+/// Address a spend of `swap` pays. EVM HTLCs pay their redeemer or initiator.
+/// Bitcoin HTLCs pay whatever the spender chose: the user's
+/// `bitcoin_optional_recipient` when the user spends (destination redeem, source
+/// refund), and the chain's `relayer_address` when the relayer does.
+fn payee(order: &MatchedOrder, swap: &Swap, kind: LedgerKind, relayer_address: Option<&str>) -> Result<String> {
+    let is_destination = swap.swap_id == order.destination_swap.swap_id;
+    let user_spends = (kind == LedgerKind::RedeemOut) == is_destination;
+    match (swap.chain.is_bitcoin(), kind) {
+        (false, LedgerKind::RedeemOut) => Ok(swap.redeemer.clone()),
+        (false, _) => Ok(swap.initiator.clone()),
+        (true, _) if user_spends => order
+            .create_order
+            .bitcoin_optional_recipient
+            .clone()
+            .ok_or_else(|| anyhow!("Order has no bitcoin_optional_recipient to look up spends on {}", swap.chain)),
+        (true, _) => relayer_address
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("relayer_address is not configured on {}", swap.chain)),
+    }
+}
+
+/// Reads every redeem and refund of `order` from the chain its swap is on
+pub async fn lookup_spends(order: &MatchedOrder, config: &AppConfig) -> Result<HashMap<String, SpendFacts>> {
+    let mut spends = HashMap::new();
+    for swap in [&order.source_swap, &order.destination_swap] {
+        let Some((kind, txid, _)) = spend_of(swap) else {
+            continue;
+        };
+        let chain_config = config
+            .chains
+            .get(&swap.chain.to_string())
+            .ok_or_else(|| anyhow!("{} is not configured", swap.chain))?;
+        let payee = payee(order, swap, kind, chain_config.relayer_address.as_deref())?;
+        let spend = if swap.chain.is_bitcoin() {
+            let indexer_url = chain_config
+                .indexer_url
+                .as_ref()
+                .ok_or_else(|| anyhow!("indexer_url is not configured on {}", swap.chain))?;
+            bitcoin_spend(indexer_url, txid, &payee).await?
+        } else {
+            evm_spend(&chain_config.rpc_url, txid, swap, &payee).await?
+        };
+        spends.insert(txid.to_string(), spend);
+    }
+    Ok(spends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::FeeBreakdown;
+    use bitcoin_primitives::mock_server::MockServer;
+
+    fn completed_order() -> MatchedOrder {
+        let json = serde_json::json!({
+            "created_at": "2025-01-01T00:00:00Z",
+            "direction": "evm_to_btc",
+            "source_swap": {
+                "created_at": "2025-01-01T00:00:00Z",
+                "swap_id": "evm-swap",
+                "chain": "avalanche_testnet",
+                "asset": "usdc",
+                "htlc_address": "",
+                "token_address": "",
+                "initiator": "0xuser",
+                "redeemer": "0xexecutor",
+                "filled_amount": "1000000",
+                "amount": "1000000",
+                "timelock": 100,
+                "secret_hash": "",
+                "secret": "00",
+                "initiate_tx_hash": "0xinit",
+                "redeem_tx_hash": "0xredeem",
+                "refund_tx_hash": null,
+                "initiate_block_number": "10",
+                "redeem_block_number": "20",
+                "refund_block_number": null,
+                "deposit_address": null,
+                "has_deposit": false
+            },
+            "destination_swap": {
+                "created_at": "2025-01-01T00:00:00Z",
+                "swap_id": "btc-swap",
+                "chain": "bitcoin_testnet",
+                "asset": "btc",
+                "htlc_address": "primary",
+                "token_address": "primary",
+                "initiator": "executor-key",
+                "redeemer": "user-key",
+                "filled_amount": "49750",
+                "amount": "49750",
+                "timelock": 2,
+                "secret_hash": "",
+                "secret": "00",
+                "initiate_tx_hash": "btc-init",
+                "redeem_tx_hash": "btc-redeem",
+                "refund_tx_hash": null,
+                "initiate_block_number": "100",
+                "redeem_block_number": "101",
+                "refund_block_number": null,
+                "deposit_address": null,
+                "has_deposit": false
+            },
+            "create_order": {
+                "from": "avalanche_testnet:usdc",
+                "to": "bitcoin_testnet:btc",
+                "source_amount": "1000000",
+                "destination_amount": "50000",
+                "initiator_source_address": "0xuser",
+                "initiator_destination_address": "user-key",
+                "secret_hash": "",
+                "nonce": "1",
+                "bitcoin_optional_recipient": "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy",
+                "create_id": "order"
+            }
+        });
+        let mut order: MatchedOrder = serde_json::from_value(json).unwrap();
+        order.fee = Some(FeeBreakdown {
+            bps: 50,
+            quoted_amount: "50000".to_string(),
+            fee_amount: "250".to_string(),
+            destination_amount: "49750".to_string(),
+        });
+        order
+    }
+
+    fn spends() -> HashMap<String, SpendFacts> {
+        HashMap::from([
+            (
+                "btc-redeem".to_string(),
+                SpendFacts {
+                    payee: "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy".to_string(),
+                    paid_out: BigUint::from(49_450u64),
+                    fee: BigUint::from(300u64),
+                },
+            ),
+            (
+                "0xredeem".to_string(),
+                SpendFacts { payee: "0xexecutor".to_string(), paid_out: BigUint::from(1_000_000u64), fee: BigUint::default() },
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_completed_swap_ledger_balances() {
+        let ledger = build_ledger(&completed_order(), &spends()).unwrap();
+        let kinds: Vec<LedgerKind> = ledger.entries.iter().map(|entry| entry.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LedgerKind::FundingIn,
+                LedgerKind::Spread,
+                LedgerKind::FundingIn,
+                LedgerKind::RedeemOut,
+                LedgerKind::NetworkFee,
+                LedgerKind::RedeemOut,
+            ]
+        );
+        assert_eq!(ledger.entries[3].recipient.as_deref(), Some("tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy"));
+        assert_eq!(ledger.entries[5].recipient.as_deref(), Some("0xexecutor"));
+
+        let btc = &ledger.legs[1];
+        assert_eq!((btc.funded.as_str(), btc.paid_out.as_str(), btc.fees.as_str()), ("49750", "49450", "300"));
+        assert!(ledger.legs.iter().all(|leg| leg.balanced));
+    }
+
+    #[test]
+    fn test_value_leaving_the_swap_is_not_balanced() {
+        let mut spends = spends();
+        // The spend paid 1000 sats somewhere other than the payee
+        spends.get_mut("btc-redeem").unwrap().paid_out = BigUint::from(48_450u64);
+        let ledger = build_ledger(&completed_order(), &spends).unwrap();
+        assert!(ledger.legs[0].balanced);
+        assert!(!ledger.legs[1].balanced);
+    }
+
+    #[test]
+    fn test_open_swap_is_not_balanced() {
+        let mut order = completed_order();
+        order.source_swap.redeem_tx_hash = None;
+        let ledger = build_ledger(&order, &spends()).unwrap();
+        assert!(!ledger.legs[0].balanced);
+        assert!(ledger.legs[1].balanced);
+    }
+
+    #[test]
+    fn test_spend_not_looked_up_is_rejected() {
+        let mut spends = spends();
+        spends.remove("0xredeem");
+        assert!(build_ledger(&completed_order(), &spends).is_err());
+    }
+
+    #[test]
+    fn test_payees() {
+        let order = completed_order();
+        let user = "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy";
+        let relayer = Some("tb1qrelayer");
+        let payee = |swap: &Swap, kind| payee(&order, swap, kind, relayer).unwrap();
+
+        assert_eq!(payee(&order.destination_swap, LedgerKind::RedeemOut), user);
+        assert_eq!(payee(&order.destination_swap, LedgerKind::RefundOut), "tb1qrelayer");
+        assert_eq!(payee(&order.source_swap, LedgerKind::RedeemOut), "0xexecutor");
+        assert_eq!(payee(&order.source_swap, LedgerKind::RefundOut), "0xuser");
+    }
+
+    #[tokio::test]
+    async fn test_bitcoin_spend_counts_only_the_payee() {
+        let indexer = MockServer::serving(
+            serde_json::json!({
+                "fee": 300,
+                "vout": [
+                    { "value": 40_000, "scriptpubkey_address": "tb1qpayee" },
+                    { "value": 9_450, "scriptpubkey_address": "tb1qchange" },
+                    { "value": 0 },
+                ],
+            })
+            .to_string(),
+        )
+        .await;
+
+        let spend = bitcoin_spend(&IndexerUrl::parse(indexer.url()).unwrap(), "btc-redeem", "tb1qpayee").await.unwrap();
+        assert_eq!(spend.paid_out, BigUint::from(40_000u64));
+        assert_eq!(spend.fee, BigUint::from(300u64));
+    }
+}
@@ -1,23 +1,39 @@
 use axum::{
     routing::{get, post},
     Router,
-    extract::{State, Path},
+    extract::{State, Path, Query},
     Json,
 };
 use std::{collections::HashMap, net::SocketAddr, str::FromStr};
-use mongodb::{Client, Database, IndexModel, bson::doc};
+use mongodb::{Client, Database, IndexModel, bson::{doc, Document}};
 use futures::TryStreamExt;
-use anyhow::Result;
-use tracing::{error, info};
+use anyhow::{anyhow, Result};
+use tracing::{error, info, warn};
 mod primitives;
 mod config;
 mod services;
 mod bitcoin_htlc;
-use primitives::{MatchedOrder, CreateOrder, Response};
-use config::AppConfig;
+mod errors;
+mod gas;
+mod fees;
+mod ledger;
+mod urls;
+mod stats;
+mod balances;
+mod retry;
+use primitives::{MatchedOrder, CreateOrder, Response, SwapDirection};
+use bitcoin_primitives::types::normalize_txid;
+use serde::{Deserialize, Serialize};
+use bitcoin_htlc::{HTLCParams, HtlcSpendInfo};
+use gas::GasPolicyFiller;
+use config::{AppConfig, ChainConfig};
 use services::OrderService;
+use errors::ApiError;
+use ledger::{build_ledger, lookup_spends, OrderLedger};
+use balances::{relayer_balances, ChainBalance};
+use stats::{compute_stats, stats_pipeline, Stats, StatsCache, StatsGroup, STATS_TTL};
 use alloy::{
-    hex::FromHex, network::EthereumWallet, primitives::{Address, FixedBytes}, providers::{fillers::{ChainIdFiller, GasFiller, JoinFill, NonceFiller, SimpleNonceManager, WalletFiller}, Identity, ProviderBuilder, RootProvider}, signers::local::PrivateKeySigner, sol, transports::http::reqwest::Url
+    hex::FromHex, network::EthereumWallet, primitives::{Address, FixedBytes}, providers::{fillers::{ChainIdFiller, JoinFill, NonceFiller, SimpleNonceManager, WalletFiller}, Identity, ProviderBuilder, RootProvider}, signers::local::PrivateKeySigner, sol
 };
 
 use crate::HTLCRegistry::HTLCRegistryInstance;
@@ -35,131 +51,257 @@ sol!(
 #[derive(Clone)]
 struct AppState {
     db: Database,
-    order_service: OrderService
+    order_service: OrderService,
+    config: AppConfig,
+    stats_cache: StatsCache,
 }
 
 async fn health_check(State(_state): State<AppState>) -> &'static str {
     "Online"
 }
 
+/// What `POST /orders` returns: the ids and addresses a client needs to fund the order
+#[derive(Debug, PartialEq, Serialize)]
+struct CreatedOrder {
+    create_id: String,
+    source_swap_id: String,
+    destination_swap_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_deposit_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    destination_deposit_address: Option<String>,
+}
+
+impl From<&MatchedOrder> for CreatedOrder {
+    fn from(order: &MatchedOrder) -> Self {
+        Self {
+            create_id: order.create_order.create_id.clone().unwrap_or_else(|| "unknown".to_string()),
+            source_swap_id: order.source_swap.swap_id.clone(),
+            destination_swap_id: order.destination_swap.swap_id.clone(),
+            source_deposit_address: order.source_swap.deposit_address.clone(),
+            destination_deposit_address: order.destination_swap.deposit_address.clone(),
+        }
+    }
+}
+
 async fn create_order(
     State(state): State<AppState>,
     Json(create_order): Json<CreateOrder>,
-) -> Result<Json<Response<String>>, (axum::http::StatusCode, Json<Response<()>>)> {    
+) -> Result<Json<Response<CreatedOrder>>, ApiError> {    
     // Check if any existing order has the same secret hash
     let orders_collection = state.db.collection::<MatchedOrder>("orders");
     let secret_hash_filter = doc! { "create_order.secret_hash": &create_order.secret_hash };
     
-    match orders_collection.find_one(secret_hash_filter, None).await {
-        Ok(Some(_)) => {
-            // Found an existing order with the same secret hash
-            return Err((
-                axum::http::StatusCode::BAD_REQUEST,
-                Json(Response::<()>::error("An order with the same secret hash already exists".to_string()))
-            ));
-        }
-        Ok(None) => {
-            // No existing order with this secret hash, proceed
-        }
-        Err(e) => {
-            error!("Failed to check for duplicate secret hash: {}", e);
-            return Err((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(Response::<()>::error("Internal server error".to_string()))
-            ));
-        }
+    if orders_collection.find_one(secret_hash_filter, None).await?.is_some() {
+        return Err(ApiError::Conflict("An order with the same secret hash already exists".to_string()));
     }
     
-    let matched_order = match state.order_service.get_matched_order(create_order).await {
-        Ok(order) => order,
-        Err(e) => {
-            error!("Failed to get matched order: {}", e);
-            return Err((
-                axum::http::StatusCode::BAD_REQUEST,
-                Json(Response::<()>::error(format!("Failed to get matched order: {}", e)))
-            ));
-        }
-    };
-    
-    let orders_collection = state.db.collection::<MatchedOrder>("orders");
+    let matched_order = state.order_service.get_matched_order(create_order).await
+        .map_err(ApiError::from_order_error)?;
     
-    match orders_collection.insert_one(&matched_order, None).await {
-        Ok(_result) => {
-            let create_id = matched_order.create_order.create_id.clone().unwrap_or_else(|| "unknown".to_string());
-            info!("Order created: {:?}", create_id);
-            Ok(Json(Response::success(create_id)))
-        }
-        Err(e) => {
-            error!("Failed to insert order into database: {}", e);
-            Err((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(Response::<()>::error("Internal server error".to_string()))
-            ))
-        }
-    }
+    orders_collection.insert_one(&matched_order, None).await?;
+
+    let created = CreatedOrder::from(&matched_order);
+    info!("Order created: {:?}", created.create_id);
+    Ok(Json(Response::success(created)))
 }
 
 async fn get_order(
     State(state): State<AppState>,
     Path(order_id): Path<String>,
-) -> Result<Json<Response<MatchedOrder>>, (axum::http::StatusCode, Json<Response<()>>)> {
+) -> Result<Json<Response<MatchedOrder>>, ApiError> {
     let orders_collection = state.db.collection::<MatchedOrder>("orders");
     
     let filter = doc! { "create_order.create_id": &order_id };
     
-    match orders_collection.find_one(filter, None).await {
-        Ok(Some(matched_order)) => {
-            Ok(Json(Response::success(matched_order)))
-        }
-        Ok(None) => {
-            Err((
-                axum::http::StatusCode::NOT_FOUND,
-                Json(Response::<()>::error("Order not found".to_string()))
-            ))
-        }
-        Err(e) => {
-            error!("Failed to query database: {}", e);
-            Err((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(Response::<()>::error("Internal server error".to_string()))
-            ))
-        }
+    let matched_order = orders_collection.find_one(filter, None).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    Ok(Json(Response::success(matched_order)))
+}
+
+#[derive(Debug, Deserialize)]
+struct OrdersQuery {
+    direction: Option<SwapDirection>,
+}
+
+/// Swap fields holding a transaction hash, searched by `GET /orders/by-tx/:txid`
+const TX_HASH_FIELDS: [&str; 6] = [
+    "source_swap.initiate_tx_hash",
+    "source_swap.redeem_tx_hash",
+    "source_swap.refund_tx_hash",
+    "destination_swap.initiate_tx_hash",
+    "destination_swap.redeem_tx_hash",
+    "destination_swap.refund_tx_hash",
+];
+
+/// Matches orders with `txid` in any swap transaction field. Txids are stored
+/// normalized, so each clause is an exact match served by that field's index
+fn tx_hash_filter(txid: &str) -> Document {
+    let txid = normalize_txid(txid);
+    let clauses: Vec<Document> = TX_HASH_FIELDS
+        .iter()
+        .map(|field| {
+            let mut clause = Document::new();
+            clause.insert(*field, &txid);
+            clause
+        })
+        .collect();
+    doc! { "$or": clauses }
+}
+
+/// Aggregation expression rewriting the string txid in `field` to its
+/// `normalize_txid` form, leaving missing and null values as they are
+fn normalize_txid_expr(field: &str) -> Document {
+    let value = format!("${}", field);
+    doc! {
+        "$cond": [
+            { "$eq": [{ "$type": &value }, "string"] },
+            {
+                "$let": {
+                    "vars": { "txid": { "$toLower": { "$trim": { "input": &value } } } },
+                    "in": {
+                        "$cond": [
+                            { "$eq": [{ "$substrCP": ["$$txid", 0, 2] }, "0x"] },
+                            { "$substrCP": ["$$txid", 2, { "$strLenCP": "$$txid" }] },
+                            "$$txid"
+                        ]
+                    }
+                }
+            },
+            &value
+        ]
     }
 }
 
+async fn get_order_by_tx(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+) -> Result<Json<Response<MatchedOrder>>, ApiError> {
+    let orders_collection = state.db.collection::<MatchedOrder>("orders");
+
+    let matched_order = orders_collection.find_one(tx_hash_filter(txid.trim()), None).await?
+        .ok_or_else(|| ApiError::NotFound("No order found for transaction".to_string()))?;
+    Ok(Json(Response::success(matched_order)))
+}
+
+/// HTLC of one Bitcoin leg of an order
+#[derive(Debug, Serialize)]
+struct SwapHtlc {
+    swap_id: String,
+    #[serde(flatten)]
+    htlc: HtlcSpendInfo,
+}
+
+/// Derives the spend conditions of every Bitcoin leg of `order`
+fn order_htlcs(order: &MatchedOrder) -> Result<Vec<SwapHtlc>, ApiError> {
+    let mut htlcs = Vec::new();
+    for swap in [&order.source_swap, &order.destination_swap] {
+        let Some(network) = swap.chain.bitcoin_network() else {
+            continue;
+        };
+        let params = HTLCParams::parse(&swap.secret_hash, swap.hash_function, &swap.initiator, &swap.redeemer, swap.timelock)
+            .map_err(|e| ApiError::Internal(format!("Invalid HTLC parameters on swap {}: {}", swap.swap_id, e)))?;
+        let funded_at = swap
+            .initiate_block_number
+            .as_deref()
+            .and_then(|height| height.parse::<u64>().ok())
+            .filter(|height| *height > 0);
+        let htlc = params
+            .spend_info(network, funded_at)
+            .map_err(|e| ApiError::Internal(format!("Failed to derive HTLC for swap {}: {}", swap.swap_id, e)))?;
+        htlcs.push(SwapHtlc { swap_id: swap.swap_id.clone(), htlc });
+    }
+
+    if htlcs.is_empty() {
+        return Err(ApiError::NotFound("Order has no Bitcoin HTLC".to_string()));
+    }
+    Ok(htlcs)
+}
+
+async fn get_order_htlc(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Result<Json<Response<Vec<SwapHtlc>>>, ApiError> {
+    let orders_collection = state.db.collection::<MatchedOrder>("orders");
+
+    let matched_order = orders_collection.find_one(doc! { "create_order.create_id": &order_id }, None).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    Ok(Json(Response::success(order_htlcs(&matched_order)?)))
+}
+
+async fn get_order_ledger(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Result<Json<Response<OrderLedger>>, ApiError> {
+    let orders_collection = state.db.collection::<MatchedOrder>("orders");
+
+    let matched_order = orders_collection.find_one(doc! { "create_order.create_id": &order_id }, None).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+
+    // Payouts and miner fees are read from the chain each swap settled on
+    let spends = lookup_spends(&matched_order, &state.config).await
+        .map_err(|e| ApiError::UpstreamUnavailable(format!("Failed to look up spends of order {}: {}", order_id, e)))?;
+
+    let ledger = build_ledger(&matched_order, &spends)
+        .map_err(|e| ApiError::Internal(format!("Failed to build ledger for order {}: {}", order_id, e)))?;
+    Ok(Json(Response::success(ledger)))
+}
+
+/// Groups every order in MongoDB and totals the groups
+async fn aggregate_stats(db: &Database) -> Result<Stats, ApiError> {
+    let orders_collection = db.collection::<MatchedOrder>("orders");
+    let mut cursor = orders_collection.aggregate(stats_pipeline(), None).await?;
+    let mut groups = Vec::new();
+    while let Some(document) = cursor.try_next().await? {
+        let group: StatsGroup = mongodb::bson::from_document(document)
+            .map_err(|e| ApiError::Internal(format!("Failed to read stats group: {}", e)))?;
+        groups.push(group);
+    }
+
+    compute_stats(&groups).map_err(|e| ApiError::Internal(format!("Failed to compute stats: {}", e)))
+}
+
+async fn get_stats(
+    State(state): State<AppState>,
+) -> Result<Json<Response<Stats>>, ApiError> {
+    let stats = state.stats_cache.get_or_refresh(|| aggregate_stats(&state.db)).await?;
+    Ok(Json(Response::success(stats)))
+}
+
+async fn get_relayer_balances(
+    State(state): State<AppState>,
+) -> Json<Response<Vec<ChainBalance>>> {
+    Json(Response::success(relayer_balances(&state.config).await))
+}
+
 async fn get_orders_by_user(
     State(state): State<AppState>,
     Path(user_id): Path<String>,
-) -> Result<Json<Response<Vec<MatchedOrder>>>, (axum::http::StatusCode, Json<Response<()>>)> {
+    Query(query): Query<OrdersQuery>,
+) -> Result<Json<Response<Vec<MatchedOrder>>>, ApiError> {
     let orders_collection = state.db.collection::<MatchedOrder>("orders");
     
-    // Create a filter that matches the user address in multiple fields
-    let filter = doc! {
+    // Create a filter that matches the user address in multiple fields; parties are stored lowercased
+    let party = user_id.to_lowercase();
+    let mut filter = doc! {
         "$or": [
-            { "source_swap.initiator": &user_id },
-            { "source_swap.redeemer": &user_id },
-            { "destination_swap.initiator": &user_id },
-            { "destination_swap.redeemer": &user_id },
+            { "source_swap.initiator": &party },
+            { "source_swap.redeemer": &party },
+            { "destination_swap.initiator": &party },
+            { "destination_swap.redeemer": &party },
             { "create_order.bitcoin_optional_recipient": &user_id }
         ]
     };
+    if let Some(direction) = query.direction {
+        filter.insert("direction", mongodb::bson::to_bson(&direction).map_err(|e| ApiError::Internal(e.to_string()))?);
+    }
     
-    match orders_collection.find(filter, None).await {
-        Ok(mut cursor) => {
-            let mut orders = Vec::new();
-            while let Ok(Some(order)) = cursor.try_next().await {
-                orders.push(order);
-            }
-            Ok(Json(Response::success(orders)))
-        }
-        Err(e) => {
-            error!("Failed to query database: {}", e);
-            Err((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(Response::<()>::error("Internal server error".to_string()))
-            ))
-        }
+    let mut cursor = orders_collection.find(filter, None).await?;
+    let mut orders = Vec::new();
+    while let Ok(Some(order)) = cursor.try_next().await {
+        orders.push(order);
     }
+    Ok(Json(Response::success(orders)))
 }
 
 async fn setup_mongodb() -> Result<Database> {
@@ -174,6 +316,15 @@ async fn setup_mongodb() -> Result<Database> {
     Ok(db)
 }
 
+/// Stored names of every Bitcoin chain an order leg can be on
+fn bitcoin_chain_names() -> Vec<String> {
+    bitcoin_primitives::types::Chain::ALL
+        .iter()
+        .filter(|chain| chain.is_bitcoin())
+        .map(|chain| chain.to_string())
+        .collect()
+}
+
 async fn migrate_schema(db: &Database) -> Result<()> {    
     // Create single orders collection for all MatchedOrder documents
     let orders_collection = db.collection::<MatchedOrder>("orders");
@@ -187,7 +338,16 @@ async fn migrate_schema(db: &Database) -> Result<()> {
         IndexModel::builder().keys(doc! { "source_swap.chain": 1, "destination_swap.chain": 1 }).build(),
         IndexModel::builder().keys(doc! { "source_swap.initiator": 1 }).build(),
         IndexModel::builder().keys(doc! { "destination_swap.initiator": 1 }).build(),
+        IndexModel::builder().keys(doc! { "direction": 1 }).build(),
     ];
+    // One index per tx hash field so each clause of the by-tx `$or` is index-backed
+    let order_indexes = order_indexes.into_iter().chain(
+        TX_HASH_FIELDS.iter().map(|field| {
+            let mut keys = Document::new();
+            keys.insert(*field, 1);
+            IndexModel::builder().keys(keys).build()
+        })
+    );
     
     for index in order_indexes {
         match orders_collection.create_index(index, None).await {
@@ -198,6 +358,51 @@ async fn migrate_schema(db: &Database) -> Result<()> {
         }
     }
     
+    // Backfill direction on orders created before it was stored
+    let bitcoin_chains = bitcoin_chain_names();
+    let backfill_direction = vec![doc! {
+        "$set": {
+            "direction": {
+                "$switch": {
+                    "branches": [
+                        { "case": { "$and": [
+                            { "$in": ["$source_swap.chain", &bitcoin_chains] },
+                            { "$in": ["$destination_swap.chain", &bitcoin_chains] }
+                        ] }, "then": "btc_to_btc" },
+                        { "case": { "$in": ["$source_swap.chain", &bitcoin_chains] }, "then": "btc_to_evm" },
+                        { "case": { "$in": ["$destination_swap.chain", &bitcoin_chains] }, "then": "evm_to_btc" }
+                    ],
+                    "default": "evm_to_evm"
+                }
+            }
+        }
+    }];
+    orders_collection
+        .update_many(doc! { "direction": { "$exists": false } }, backfill_direction, None)
+        .await?;
+    
+    // Normalize txids stored before they were normalized on write
+    let mut txids = Document::new();
+    for field in TX_HASH_FIELDS {
+        txids.insert(field, normalize_txid_expr(field));
+    }
+    orders_collection
+        .update_many(doc! {}, vec![doc! { "$set": txids }], None)
+        .await?;
+    
+    // Lowercase parties on orders stored before they were normalized on write
+    let lowercase_parties = vec![doc! {
+        "$set": {
+            "source_swap.initiator": { "$toLower": "$source_swap.initiator" },
+            "source_swap.redeemer": { "$toLower": "$source_swap.redeemer" },
+            "destination_swap.initiator": { "$toLower": "$destination_swap.initiator" },
+            "destination_swap.redeemer": { "$toLower": "$destination_swap.redeemer" }
+        }
+    }];
+    orders_collection
+        .update_many(doc! {}, lowercase_parties, None)
+        .await?;
+    
     // Create unique index for create_id separately
     let unique_create_id_index = IndexModel::builder()
         .keys(doc! { "create_order.create_id": 1 })
@@ -251,7 +456,7 @@ async fn migrate_schema(db: &Database) -> Result<()> {
 pub type AlloyProvider = alloy::providers::fillers::FillProvider<
     JoinFill<
         JoinFill<
-            JoinFill<JoinFill<Identity, GasFiller>, NonceFiller<SimpleNonceManager>>,
+            JoinFill<JoinFill<Identity, GasPolicyFiller>, NonceFiller<SimpleNonceManager>>,
             ChainIdFiller,
         >,
         WalletFiller<EthereumWallet>,
@@ -259,6 +464,61 @@ pub type AlloyProvider = alloy::providers::fillers::FillProvider<
     RootProvider,
 >;
 
+/// Builds the registry contract client for one configured chain
+fn build_registry(chain_config: &ChainConfig) -> Result<HTLCRegistryInstance<AlloyProvider>> {
+    let key = FixedBytes::<32>::from_hex(&chain_config.relay_private_key)
+        .map_err(|e| anyhow!("invalid relay_private_key: {}", e))?;
+    let signer = PrivateKeySigner::from_bytes(&key)
+        .map_err(|e| anyhow!("invalid relay_private_key: {}", e))?;
+    let registry_address = Address::from_str(&chain_config.registry_address)
+        .map_err(|e| anyhow!("invalid registry_address '{}': {}", chain_config.registry_address, e))?;
+
+    let provider = ProviderBuilder::new()
+        .disable_recommended_fillers()
+        .filler(GasPolicyFiller::new(chain_config.gas.clone()))
+        .with_simple_nonce_management()
+        .fetch_chain_id()
+        .wallet(EthereumWallet::from(signer))
+        .connect_http(chain_config.rpc_url.url());
+
+    Ok(HTLCRegistryInstance::new(registry_address, provider))
+}
+
+/// Builds a registry for every configured chain. Invalid entries are all
+/// reported together; in strict mode they abort startup, otherwise they are
+/// skipped with a warning and the remaining chains are served.
+fn build_registries(config: &AppConfig) -> Result<HashMap<String, HTLCRegistryInstance<AlloyProvider>>> {
+    if config.chains.is_empty() {
+        if config.strict {
+            return Err(anyhow!("config.chains is empty"));
+        }
+        warn!("config.chains is empty, no chains will be served");
+    }
+
+    let mut registries = HashMap::new();
+    let mut invalid = Vec::new();
+    for (chain_id, chain_config) in &config.chains {
+        match build_registry(chain_config) {
+            Ok(registry) => {
+                registries.insert(chain_id.clone(), registry);
+            }
+            Err(e) => invalid.push(format!("{}: {}", chain_id, e)),
+        }
+    }
+    invalid.sort();
+
+    if !invalid.is_empty() {
+        if config.strict {
+            return Err(anyhow!("Invalid chain config entries:\n  {}", invalid.join("\n  ")));
+        }
+        for entry in &invalid {
+            warn!("Skipping invalid chain config {}", entry);
+        }
+    }
+
+    Ok(registries)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
 
@@ -278,31 +538,12 @@ async fn main() -> Result<()> {
             e
         })?;
 
-    let mut evm_registries: HashMap<String, HTLCRegistryInstance<AlloyProvider>> = HashMap::new();
-
-    for (chain_id, chain_config) in config.chains.clone() {
-        let signer = PrivateKeySigner::from_bytes(
-            &FixedBytes::from_hex(chain_config.relay_private_key).expect("Invalid executor private key"),
-        )
-        .unwrap();
-        let wallet = EthereumWallet::from(signer.clone());
-
-        let provider =     ProviderBuilder::new()
-            .disable_recommended_fillers()
-            .with_gas_estimation()
-            .with_simple_nonce_management()
-            .fetch_chain_id()
-            .wallet(wallet)
-            .connect_http(Url::parse(&chain_config.rpc_url).unwrap());
-
-        let registry = HTLCRegistryInstance::new(Address::from_str(&chain_config.registry_address).unwrap(), provider);
-        evm_registries.insert(chain_id, registry);
-    }
+    let evm_registries = build_registries(&config)?;
 
     // Create order service
     let order_service = OrderService::new(config.clone(), evm_registries);
     // Create app state
-    let state = AppState { db, order_service };
+    let state = AppState { db, order_service, config: config.clone(), stats_cache: StatsCache::new(STATS_TTL) };
     
     // Build our application with routes and state
     let app = Router::new()
@@ -310,6 +551,11 @@ async fn main() -> Result<()> {
         .route("/orders", post(create_order))
         .route("/orders/id/:order_id", get(get_order))
         .route("/orders/user/:user_id", get(get_orders_by_user))
+        .route("/orders/by-tx/:txid", get(get_order_by_tx))
+        .route("/orders/:order_id/htlc", get(get_order_htlc))
+        .route("/orders/:order_id/ledger", get(get_order_ledger))
+        .route("/stats", get(get_stats))
+        .route("/relayer/balances", get(get_relayer_balances))
         .with_state(state)
         .layer(
             CorsLayer::new()
@@ -328,3 +574,149 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::Bson;
+    #[test]
+    fn test_direction_backfill_covers_every_bitcoin_chain() {
+        assert_eq!(bitcoin_chain_names(), vec!["bitcoin_testnet", "bitcoin_signet"]);
+    }
+
+    #[test]
+    fn test_tx_hash_filter_is_exact_match_on_every_field() {
+        let filter = tx_hash_filter("abc");
+        let clauses = filter.get_array("$or").unwrap();
+        assert_eq!(clauses.len(), TX_HASH_FIELDS.len());
+        for field in TX_HASH_FIELDS {
+            let mut clause = Document::new();
+            clause.insert(field, "abc");
+            assert!(clauses.contains(&Bson::Document(clause)));
+        }
+    }
+
+    #[test]
+    fn test_tx_hash_filter_normalizes_case_and_prefix() {
+        assert_eq!(tx_hash_filter("0xAB12CD"), tx_hash_filter("ab12cd"));
+        assert_eq!(tx_hash_filter(" AB12CD "), tx_hash_filter("ab12cd"));
+    }
+
+    #[test]
+    fn test_created_order_carries_swap_ids_and_deposit_addresses() {
+        let swap = |swap_id: &str, chain: &str, deposit_address: &str| serde_json::json!({
+            "created_at": "2025-01-01T00:00:00Z",
+            "swap_id": swap_id,
+            "chain": chain,
+            "asset": "usdc",
+            "htlc_address": "",
+            "token_address": "",
+            "initiator": "user",
+            "redeemer": "relayer",
+            "filled_amount": "0",
+            "amount": "1000000",
+            "timelock": 100,
+            "secret_hash": "",
+            "secret": null,
+            "initiate_tx_hash": null,
+            "redeem_tx_hash": null,
+            "refund_tx_hash": null,
+            "initiate_block_number": null,
+            "redeem_block_number": null,
+            "refund_block_number": null,
+            "deposit_address": deposit_address,
+            "has_deposit": false
+        });
+        let order: MatchedOrder = serde_json::from_value(serde_json::json!({
+            "created_at": "2025-01-01T00:00:00Z",
+            "direction": "evm_to_btc",
+            "source_swap": swap("evm-swap", "avalanche_testnet", "0x6B1c656ad724C246049EF586Fa35D217A8db13A0"),
+            "destination_swap": swap("btc-swap", "bitcoin_testnet", "tb1pdeposit"),
+            "create_order": {
+                "from": "avalanche_testnet:usdc",
+                "to": "bitcoin_testnet:btc",
+                "source_amount": "1000000",
+                "destination_amount": "50000",
+                "initiator_source_address": "user",
+                "initiator_destination_address": "user",
+                "secret_hash": "",
+                "nonce": "1",
+                "bitcoin_optional_recipient": null,
+                "create_id": "order-1"
+            }
+        }))
+        .unwrap();
+
+        let body = serde_json::to_value(Response::success(CreatedOrder::from(&order))).unwrap();
+        assert_eq!(
+            body["result"],
+            serde_json::json!({
+                "create_id": "order-1",
+                "source_swap_id": "evm-swap",
+                "destination_swap_id": "btc-swap",
+                "source_deposit_address": "0x6B1c656ad724C246049EF586Fa35D217A8db13A0",
+                "destination_deposit_address": "tb1pdeposit"
+            })
+        );
+    }
+
+    fn chain_config(relay_private_key: &str) -> ChainConfig {
+        ChainConfig {
+            executor_address: "0xe62a2b235f7bB86C1122313153824D54E6137e77".to_string(),
+            relay_private_key: relay_private_key.to_string(),
+            rpc_url: "http://localhost:8545".parse().unwrap(),
+            registry_address: "0x66F20a5Fbf43e4B36Ac9e2D9DE33E8B8cAfD3ab7".to_string(),
+            assets: vec![],
+            source_timelock: 100,
+            destination_timelock: 50,
+            source_timelock_secs: None,
+            destination_timelock_secs: None,
+            block_time_ms: None,
+            chain_id: "43113".to_string(),
+            indexer_url: None,
+            relayer_address: None,
+            low_balance_threshold: None,
+            gas: Default::default(),
+        }
+    }
+
+    fn config_with_bad_chain(strict: bool) -> AppConfig {
+        let valid_key = "639ed7560cbdde79096973912f5c83de86ba08aef2ce6f673dad5bf0a1663801";
+        let mut bad = chain_config(valid_key);
+        bad.registry_address = "not an address".to_string();
+
+        AppConfig {
+            chains: HashMap::from([
+                ("avalanche_testnet".to_string(), chain_config(valid_key)),
+                ("arbitrum_sepolia".to_string(), chain_config("zz")),
+                ("base_sepolia".to_string(), bad),
+            ]),
+            strict,
+            spreads: Vec::new(),
+            supported_pairs: Vec::new(),
+            registry_retry: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lenient_mode_skips_malformed_chains() {
+        let registries = build_registries(&config_with_bad_chain(false)).unwrap();
+        assert_eq!(registries.keys().collect::<Vec<_>>(), vec!["avalanche_testnet"]);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_reports_every_malformed_chain() {
+        let err = build_registries(&config_with_bad_chain(true)).err().unwrap().to_string();
+        assert!(err.contains("arbitrum_sepolia: invalid relay_private_key"));
+        assert!(err.contains("base_sepolia: invalid registry_address"));
+        assert!(!err.contains("avalanche_testnet"));
+    }
+
+    #[test]
+    fn test_empty_chains_rejected_only_in_strict_mode() {
+        let config = AppConfig { chains: HashMap::new(), strict: false, spreads: Vec::new(), supported_pairs: Vec::new(), registry_retry: Default::default() };
+        assert!(build_registries(&config).unwrap().is_empty());
+
+        let config = AppConfig { chains: HashMap::new(), strict: true, spreads: Vec::new(), supported_pairs: Vec::new(), registry_retry: Default::default() };
+        assert!(build_registries(&config).is_err());
+    }
+}
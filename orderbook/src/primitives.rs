@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
 use mongodb::bson::{DateTime, oid::ObjectId};
 use std::fmt;
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use bitcoin_primitives::address::parse_bitcoin_address;
+
+use crate::fees::FeeBreakdown;
 
 
 fn serialize_datetime<S>(datetime: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
@@ -69,6 +74,8 @@ pub struct CreateOrder {
     pub secret_hash: String,
     pub nonce : String,
     pub bitcoin_optional_recipient: Option<String>,
+    #[serde(default)]
+    pub hash_function: HashFunction,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub create_id: Option<String>, // Generated automatically by the service
 }
@@ -82,6 +89,10 @@ pub struct MatchedOrder {
     pub source_swap: Swap,
     pub destination_swap: Swap,
     pub create_order: CreateOrder,
+    pub direction: SwapDirection,
+    /// Relayer spread taken from the quoted destination amount, when one is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeBreakdown>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +112,8 @@ pub struct Swap {
     pub amount: String,
     pub timelock: i32,
     pub secret_hash: String,
+    #[serde(default)]
+    pub hash_function: HashFunction,
     pub secret: Option<String>,
     pub initiate_tx_hash: Option<String>,
     pub redeem_tx_hash: Option<String>,
@@ -112,6 +125,9 @@ pub struct Swap {
     pub has_deposit: bool
 }
 
+/// Hash algorithm used for an order's `secret_hash`, shared with the Bitcoin executor and watcher
+pub use bitcoin_primitives::types::HashFunction;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Chain {
     #[serde(rename = "bitcoin_testnet")]
@@ -122,6 +138,44 @@ pub enum Chain {
     AvalancheTestnet,
 }
 
+impl Chain {
+    pub fn is_bitcoin(&self) -> bool {
+        matches!(self, Chain::BitcoinTestnet)
+    }
+
+    /// Direction of an order from this chain to `destination`
+    pub fn direction_to(&self, destination: &Chain) -> SwapDirection {
+        SwapDirection::from_legs(self.is_bitcoin(), destination.is_bitcoin())
+    }
+
+    /// Bitcoin network HTLC addresses on this chain are encoded for
+    pub fn bitcoin_network(&self) -> Option<bitcoin::Network> {
+        match self {
+            Chain::BitcoinTestnet => Some(bitcoin::Network::Testnet4),
+            _ => None,
+        }
+    }
+
+    /// Typical time between blocks, in milliseconds
+    pub fn block_time_ms(&self) -> u64 {
+        match self {
+            Chain::BitcoinTestnet => 600_000,
+            Chain::ArbitrumSepolia => 250,
+            Chain::AvalancheTestnet => 2_000,
+        }
+    }
+
+    /// Time between the block numbers HTLC timelocks are counted in, in milliseconds.
+    /// Arbitrum's `block.number` follows the L1 block, so its timelocks advance every
+    /// ~12 s even though L2 blocks land every 250 ms
+    pub fn timelock_block_time_ms(&self) -> u64 {
+        match self {
+            Chain::ArbitrumSepolia => 12_000,
+            _ => self.block_time_ms(),
+        }
+    }
+}
+
 impl std::fmt::Display for Chain {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -132,6 +186,56 @@ impl std::fmt::Display for Chain {
     }
 }
 
+/// Which way value moves across chains in an order, shared with the Bitcoin executor and watcher
+pub use bitcoin_primitives::types::SwapDirection;
+
+/// A swap participant in the form its chain expects: an x-only public key on
+/// Bitcoin (needed to build the HTLC leaves) and an address on EVM chains
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainParty {
+    BitcoinPubkey(bitcoin::XOnlyPublicKey),
+    EvmAddress(alloy::primitives::Address),
+}
+
+impl ChainParty {
+    pub fn parse(chain: &Chain, value: &str) -> Result<Self> {
+        if chain.is_bitcoin() {
+            bitcoin::XOnlyPublicKey::from_str(value)
+                .map(ChainParty::BitcoinPubkey)
+                .map_err(|_| anyhow!("expected a 32-byte x-only public key on {}, got '{}'", chain, value))
+        } else {
+            parse_evm_address(chain, value).map(ChainParty::EvmAddress)
+        }
+    }
+}
+
+/// An address accepted from outside, validated for the chain it is used on
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainAddress {
+    Bitcoin(bitcoin::Address),
+    Evm(alloy::primitives::Address),
+}
+
+/// Parses `value` with the rules of `chain`: Bitcoin addresses must be encoded
+/// for the chain's network and EVM addresses must be 20 bytes of hex
+pub fn parse_chain_address(chain: &Chain, value: &str) -> Result<ChainAddress> {
+    match chain.bitcoin_network() {
+        Some(network) => parse_bitcoin_address(network, value).map(ChainAddress::Bitcoin),
+        None => parse_evm_address(chain, value).map(ChainAddress::Evm),
+    }
+}
+
+/// A `0x`-prefixed, 20-byte hex address. Mixed-case (EIP-55) input is accepted
+/// without verifying the checksum.
+fn parse_evm_address(chain: &Chain, value: &str) -> Result<alloy::primitives::Address> {
+    let value = value.trim();
+    if !value.starts_with("0x") {
+        return Err(anyhow!("expected a 0x-prefixed address on {}, got '{}'", chain, value));
+    }
+    alloy::primitives::Address::from_str(value)
+        .map_err(|_| anyhow!("expected a 20-byte address on {}, got '{}'", chain, value))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response<T> {
     pub status: ResponseStatus,
@@ -165,3 +269,77 @@ impl<T> Response<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_from_chains() {
+        assert_eq!(
+            Chain::BitcoinTestnet.direction_to(&Chain::AvalancheTestnet),
+            SwapDirection::BtcToEvm
+        );
+        assert_eq!(
+            Chain::ArbitrumSepolia.direction_to(&Chain::BitcoinTestnet),
+            SwapDirection::EvmToBtc
+        );
+        assert_eq!(
+            Chain::ArbitrumSepolia.direction_to(&Chain::AvalancheTestnet),
+            SwapDirection::EvmToEvm
+        );
+    }
+
+    const PUBKEY: &str = "727dde7d4e0726212ccbd76e6ed71f1bceb957082023c39be18cb93ff93773fa";
+    const ADDRESS: &str = "0xe62a2b235f7bB86C1122313153824D54E6137e77";
+
+    #[test]
+    fn test_bitcoin_party_must_be_pubkey() {
+        assert!(matches!(
+            ChainParty::parse(&Chain::BitcoinTestnet, PUBKEY).unwrap(),
+            ChainParty::BitcoinPubkey(_)
+        ));
+        assert!(ChainParty::parse(&Chain::BitcoinTestnet, ADDRESS).is_err());
+        assert!(ChainParty::parse(&Chain::BitcoinTestnet, "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh").is_err());
+    }
+
+    const TESTNET_ADDRESS: &str = "tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy";
+
+    #[test]
+    fn test_parse_bitcoin_chain_address() {
+        assert!(matches!(
+            parse_chain_address(&Chain::BitcoinTestnet, TESTNET_ADDRESS).unwrap(),
+            ChainAddress::Bitcoin(_)
+        ));
+        // Mainnet and regtest encodings are rejected on testnet
+        assert!(parse_chain_address(&Chain::BitcoinTestnet, "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh").is_err());
+        assert!(parse_chain_address(&Chain::BitcoinTestnet, "bcrt1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjlfdsnd").is_err());
+        assert!(parse_chain_address(&Chain::BitcoinTestnet, ADDRESS).is_err());
+        assert!(parse_chain_address(&Chain::BitcoinTestnet, PUBKEY).is_err());
+    }
+
+    #[test]
+    fn test_parse_evm_chain_address() {
+        for chain in [Chain::AvalancheTestnet, Chain::ArbitrumSepolia] {
+            assert!(matches!(parse_chain_address(&chain, ADDRESS).unwrap(), ChainAddress::Evm(_)));
+            assert!(parse_chain_address(&chain, &ADDRESS[2..]).is_err());
+            assert!(parse_chain_address(&chain, &ADDRESS[..41]).is_err());
+            assert!(parse_chain_address(&chain, TESTNET_ADDRESS).is_err());
+        }
+    }
+
+    #[test]
+    fn test_evm_party_must_be_address() {
+        for chain in [Chain::AvalancheTestnet, Chain::ArbitrumSepolia] {
+            assert!(matches!(ChainParty::parse(&chain, ADDRESS).unwrap(), ChainParty::EvmAddress(_)));
+            assert!(ChainParty::parse(&chain, PUBKEY).is_err());
+            assert!(ChainParty::parse(&chain, &ADDRESS[2..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_direction_serializes_snake_case() {
+        assert_eq!(serde_json::to_string(&SwapDirection::BtcToEvm).unwrap(), "\"btc_to_evm\"");
+        assert_eq!(serde_json::from_str::<SwapDirection>("\"evm_to_btc\"").unwrap(), SwapDirection::EvmToBtc);
+    }
+}
@@ -0,0 +1,133 @@
+use alloy::transports::{RpcError, TransportError};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Bounded retry with exponential backoff for registry RPC calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrySettings {
+    /// Attempts in total, including the first; 1 disables retrying
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Wait before the second attempt, doubled before each one after it
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    200
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+        }
+    }
+}
+
+/// Whether a failed contract call may succeed if sent again. Transport failures
+/// and rate limits are transient; a revert or an undecodable result will fail
+/// the same way every time.
+pub fn is_transient(e: &alloy::contract::Error) -> bool {
+    match e {
+        alloy::contract::Error::TransportError(e) => is_transient_transport(e),
+        _ => false,
+    }
+}
+
+fn is_transient_transport(e: &TransportError) -> bool {
+    match e {
+        RpcError::Transport(_) | RpcError::NullResp => true,
+        RpcError::ErrorResp(payload) => payload.is_retry_err(),
+        _ => false,
+    }
+}
+
+/// Runs `call` until it succeeds, fails with a non-transient error or has been
+/// tried `settings.max_attempts` times
+pub async fn retry_contract_call<T, F, Fut>(settings: &RetrySettings, label: &str, mut call: F) -> Result<T, alloy::contract::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, alloy::contract::Error>>,
+{
+    let mut backoff = Duration::from_millis(settings.initial_backoff_ms);
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < settings.max_attempts && is_transient(&e) => {
+                warn!("{} failed (attempt {} of {}), retrying in {:?}: {}", label, attempt, settings.max_attempts, backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::transports::TransportErrorKind;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn settings() -> RetrySettings {
+        RetrySettings { max_attempts: 3, initial_backoff_ms: 1 }
+    }
+
+    fn transport_error() -> alloy::contract::Error {
+        alloy::contract::Error::TransportError(TransportErrorKind::custom_str("connection reset by peer"))
+    }
+
+    fn revert() -> alloy::contract::Error {
+        let payload = serde_json::from_str(r#"{"code":3,"message":"execution reverted"}"#).unwrap();
+        alloy::contract::Error::TransportError(RpcError::ErrorResp(payload))
+    }
+
+    #[tokio::test]
+    async fn test_transient_failure_is_retried_until_success() {
+        let calls = &AtomicU32::new(0);
+        let result = retry_contract_call(&settings(), "getERC20Address", || async move {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(transport_error())
+            } else {
+                Ok("0x6B1c656ad724C246049EF586Fa35D217A8db13A0")
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "0x6B1c656ad724C246049EF586Fa35D217A8db13A0");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_revert_is_not_retried() {
+        let calls = &AtomicU32::new(0);
+        let result: Result<(), _> = retry_contract_call(&settings(), "getERC20Address", || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(revert())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let calls = &AtomicU32::new(0);
+        let result: Result<(), _> = retry_contract_call(&settings(), "getERC20Address", || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(transport_error())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,12 +1,15 @@
 use crate::bitcoin_htlc::{get_htlc_address, HTLCParams};
-use crate::config::AppConfig;
-use crate::primitives::{CreateOrder, MatchedOrder, Swap, Chain};
+use crate::config::{AppConfig, Asset, AssetKind, ChainConfig};
+use crate::errors::ApiError;
+use crate::fees::{apply_spread, dust_limit, FeeBreakdown};
+use crate::retry::{is_transient, retry_contract_call};
+use crate::primitives::{parse_chain_address, ChainAddress, ChainParty, CreateOrder, MatchedOrder, Swap, Chain, HashFunction};
 use crate::AlloyProvider;
 use crate::HTLCRegistry::HTLCRegistryInstance;
 use alloy::hex::FromHex;
 use alloy::primitives::{Address, FixedBytes, U256};
 use anyhow::{Result, anyhow};
-use bitcoin::{Network, XOnlyPublicKey};
+use bitcoin::Network;
 use std::collections::HashMap;
 use std::str::FromStr;
 use mongodb::bson::DateTime;
@@ -30,6 +33,14 @@ impl SupportedChain {
     }
 }
 
+/// Who funds an HTLC and who can redeem it, lowercased so the executor's
+/// exact `$in` on the stored parties matches checksummed addresses
+#[derive(Debug, Clone, PartialEq)]
+struct HtlcParties {
+    initiator: String,
+    redeemer: String,
+}
+
 #[derive(Clone)]
 pub struct OrderService {
     config: AppConfig,
@@ -49,21 +60,15 @@ impl OrderService {
         let (source_chain, source_asset) = Self::parse_chain_asset(&create_order.from)?;
         let (dest_chain, dest_asset) = Self::parse_chain_asset(&create_order.to)?;
         
-        // Validate chains exist in config
-        let source_chain_config = self.config.chains.get(&source_chain)
-            .ok_or_else(|| anyhow!("Source chain {} not found in config", source_chain))?;
-        
-        let dest_chain_config = self.config.chains.get(&dest_chain)
-            .ok_or_else(|| anyhow!("Destination chain {} not found in config", dest_chain))?;
-        
-        // Validate assets exist for each chain
-        let source_asset_config = source_chain_config.assets.iter()
-            .find(|asset| asset.id.to_lowercase() == source_asset.to_lowercase())
-            .ok_or_else(|| anyhow!("Asset {} not found for chain {}", source_asset, source_chain))?;
-        
-        let dest_asset_config = dest_chain_config.assets.iter()
-            .find(|asset| asset.id.to_lowercase() == dest_asset.to_lowercase())
-            .ok_or_else(|| anyhow!("Asset {} not found for chain {}", dest_asset, dest_chain))?;
+        // Validate chains and assets exist in config
+        let (source_chain_config, source_asset_config) = self.lookup_chain_asset(&source_chain, &source_asset)
+            .map_err(|e| anyhow!("Invalid source: {}", e))?;
+        let (dest_chain_config, dest_asset_config) = self.lookup_chain_asset(&dest_chain, &dest_asset)
+            .map_err(|e| anyhow!("Invalid destination: {}", e))?;
+        self.check_pair_supported(
+            &format!("{}:{}", source_chain, source_asset),
+            &format!("{}:{}", dest_chain, dest_asset),
+        )?;
         
         // Parse chain enum
         let source_chain_enum = Chain::from_str(&source_chain)
@@ -73,12 +78,50 @@ impl OrderService {
             .map_err(|_| anyhow!("Invalid destination chain: {}", dest_chain))?;
         
         // Validate bitcoin_optional_recipient is provided if either chain is Bitcoin
-        if !Self::is_evm_chain(&source_chain_enum) || !Self::is_evm_chain(&dest_chain_enum) {
-            if create_order.bitcoin_optional_recipient.is_none() {
-                return Err(anyhow!("bitcoin_optional_recipient is required when either source or destination chain is Bitcoin"));
-            }
+        if let Some(bitcoin_chain) = [&source_chain_enum, &dest_chain_enum].into_iter().find(|chain| chain.is_bitcoin()) {
+            let recipient = create_order.bitcoin_optional_recipient.as_deref().ok_or_else(|| {
+                anyhow!("bitcoin_optional_recipient is required when either source or destination chain is Bitcoin")
+            })?;
+            parse_chain_address(bitcoin_chain, recipient)
+                .map_err(|e| anyhow!("Invalid bitcoin_optional_recipient: {}", e))?;
+        }
+
+        let source_parties = Self::source_parties(&create_order, source_chain_config);
+        let dest_parties = Self::destination_parties(&create_order, dest_chain_config);
+
+        // Every party must be in its chain's form before any HTLC is derived from it
+        let parties = [
+            ("source initiator", &source_chain_enum, &source_parties.initiator),
+            ("source redeemer", &source_chain_enum, &source_parties.redeemer),
+            ("destination initiator", &dest_chain_enum, &dest_parties.initiator),
+            ("destination redeemer", &dest_chain_enum, &dest_parties.redeemer),
+        ];
+        for (role, chain, value) in parties {
+            ChainParty::parse(chain, value).map_err(|e| anyhow!("Invalid {}: {}", role, e))?;
         }
+        Self::validate_destination_recipient(&dest_chain_enum, &dest_parties)?;
+
+        Self::validate_secret_hash(
+            &create_order.secret_hash,
+            create_order.hash_function,
+            Self::is_evm_chain(&source_chain_enum) || Self::is_evm_chain(&dest_chain_enum),
+        )?;
         
+        let fee = self.destination_fee(
+            &format!("{}:{}", source_chain, source_asset),
+            &format!("{}:{}", dest_chain, dest_asset),
+            &create_order.destination_amount,
+            &dest_chain_enum,
+        )?;
+        // What the destination HTLC locks, net of any relayer spread
+        let destination_amount = fee
+            .as_ref()
+            .map(|fee| fee.destination_amount.clone())
+            .unwrap_or_else(|| create_order.destination_amount.clone());
+
+        let source_timelock = source_chain_config.source_timelock_blocks(&source_chain_enum)?;
+        let destination_timelock = dest_chain_config.destination_timelock_blocks(&dest_chain_enum)?;
+
         // Generate current timestamp
         let now = DateTime::now();
         
@@ -90,9 +133,9 @@ impl OrderService {
             self.generate_evm_swap_id(
                 Self::get_chain_id(&source_chain),
                 &create_order.secret_hash,
-                &create_order.initiator_source_address,
-                &source_chain_config.executor_address,
-                source_chain_config.source_timelock,
+                &source_parties.initiator,
+                &source_parties.redeemer,
+                source_timelock,
                 &create_order.source_amount,
                 &source_asset_config.atomic_swap_address,
             ).map_err(|e| anyhow!("Failed to generate source swap id: {}", e))?
@@ -106,17 +149,18 @@ impl OrderService {
         let source_deposit_address = match source_chain_type {
             SupportedChain::Bitcoin => Self::get_bitcoin_deposit_address(
                 &create_order.secret_hash,
-                &create_order.initiator_source_address,
-                &source_chain_config.executor_address,
-                source_chain_config.source_timelock,
+                create_order.hash_function,
+                &source_parties.initiator,
+                &source_parties.redeemer,
+                source_timelock,
             ).await?,
             SupportedChain::Evm => self.get_evm_deposit_address(
-                &source_asset_config.token_address,
+                source_asset_config,
                 &source_chain,
                 &create_order.secret_hash,
-                &create_order.initiator_source_address,
-                &source_chain_config.executor_address,
-                source_chain_config.source_timelock,
+                &source_parties.initiator,
+                &source_parties.redeemer,
+                source_timelock,
                 &create_order.source_amount,
             ).await?
         };
@@ -126,18 +170,19 @@ impl OrderService {
         let destination_deposit_address = match destination_chain_type {
             SupportedChain::Bitcoin => Self::get_bitcoin_deposit_address(
                 &create_order.secret_hash,
-                &dest_chain_config.executor_address,
-                &create_order.initiator_destination_address,
-                dest_chain_config.destination_timelock,
+                create_order.hash_function,
+                &dest_parties.initiator,
+                &dest_parties.redeemer,
+                destination_timelock,
             ).await?,
             SupportedChain::Evm => self.get_evm_deposit_address(
-                &dest_asset_config.token_address,
+                dest_asset_config,
                 &dest_chain,
                 &create_order.secret_hash,
-                &create_order.initiator_destination_address,
-                &dest_chain_config.executor_address,
-                dest_chain_config.destination_timelock,
-                &create_order.destination_amount,
+                &dest_parties.initiator,
+                &dest_parties.redeemer,
+                destination_timelock,
+                &destination_amount,
             ).await?
         };
 
@@ -154,12 +199,13 @@ impl OrderService {
             asset: source_asset.clone(),
             htlc_address: source_asset_config.atomic_swap_address.clone(),
             token_address: source_asset_config.token_address.clone(),
-            initiator: create_order.initiator_source_address.clone(),
-            redeemer: source_chain_config.executor_address.clone(),
+            initiator: source_parties.initiator,
+            redeemer: source_parties.redeemer,
             filled_amount: "0".to_string(),
             amount: create_order.source_amount.clone(),
-            timelock: source_chain_config.source_timelock,
+            timelock: source_timelock,
             secret_hash: create_order.secret_hash.clone(),
+            hash_function: create_order.hash_function,
             secret: None, // Empty at beginning
             initiate_tx_hash: None, // Empty at beginning
             redeem_tx_hash: None, // Empty at beginning
@@ -176,10 +222,10 @@ impl OrderService {
             self.generate_evm_swap_id(
                 Self::get_chain_id(&dest_chain),
                 &create_order.secret_hash,
-                &dest_chain_config.executor_address,
-                &create_order.initiator_destination_address,
-                dest_chain_config.destination_timelock,
-                &create_order.destination_amount,
+                &dest_parties.initiator,
+                &dest_parties.redeemer,
+                destination_timelock,
+                &destination_amount,
                 &dest_asset_config.atomic_swap_address,
             )?
         } else {
@@ -200,12 +246,13 @@ impl OrderService {
             asset: dest_asset.clone(),
             htlc_address: dest_asset_config.atomic_swap_address.clone(),
             token_address: dest_asset_config.token_address.clone(),
-            initiator: dest_chain_config.executor_address.clone(),
-            redeemer: create_order.initiator_destination_address.clone(),
-            filled_amount: create_order.destination_amount.clone(),
-            amount: create_order.destination_amount.clone(),
-            timelock: dest_chain_config.destination_timelock,
+            initiator: dest_parties.initiator,
+            redeemer: dest_parties.redeemer,
+            filled_amount: destination_amount.clone(),
+            amount: destination_amount,
+            timelock: destination_timelock,
             secret_hash: create_order.secret_hash.clone(),
+            hash_function: create_order.hash_function,
             secret: None, // Empty at beginning
             initiate_tx_hash: None, // Empty at beginning
             redeem_tx_hash: None, // Empty at beginning
@@ -217,6 +264,8 @@ impl OrderService {
             has_deposit: false
         };
         
+        let direction = source_swap.chain.direction_to(&destination_swap.chain);
+
         // Create the complete MatchedOrder
         let matched_order = MatchedOrder {
             _id: None, // Will be set by MongoDB
@@ -224,17 +273,108 @@ impl OrderService {
             source_swap,
             destination_swap,
             create_order,
+            direction,
+            fee,
         };
         
         Ok(matched_order)
     }
     
+    /// Rejects a directed pair missing from a non-empty `supported_pairs`
+    fn check_pair_supported(&self, from: &str, to: &str) -> Result<()> {
+        let pairs = &self.config.supported_pairs;
+        if pairs.is_empty() || pairs.iter().any(|pair| pair.matches(from, to)) {
+            return Ok(());
+        }
+        Err(anyhow!("Orders from {} to {} are not supported by this relayer", from, to))
+    }
+
+    /// Applies the spread configured for the `from` -> `to` pair to the quoted
+    /// destination amount, or `None` when the pair has no spread
+    fn destination_fee(&self, from: &str, to: &str, quoted_amount: &str, dest_chain: &Chain) -> Result<Option<FeeBreakdown>> {
+        let Some(spread) = self.config.spreads.iter().find(|spread| spread.matches(from, to)) else {
+            return Ok(None);
+        };
+        apply_spread(quoted_amount, spread.bps, dust_limit(dest_chain)).map(Some)
+    }
+
+    /// The user funds the source HTLC and the executor redeems it
+    fn source_parties(create_order: &CreateOrder, chain_config: &ChainConfig) -> HtlcParties {
+        HtlcParties {
+            initiator: create_order.initiator_source_address.to_lowercase(),
+            redeemer: chain_config.executor_address.to_lowercase(),
+        }
+    }
+
+    /// The executor funds the destination HTLC and the user redeems it, so a
+    /// redeem always pays out to `initiator_destination_address`
+    fn destination_parties(create_order: &CreateOrder, chain_config: &ChainConfig) -> HtlcParties {
+        HtlcParties {
+            initiator: chain_config.executor_address.to_lowercase(),
+            redeemer: create_order.initiator_destination_address.to_lowercase(),
+        }
+    }
+
+    /// Rejects destination recipients the redeemed funds could never reach the user at
+    fn validate_destination_recipient(chain: &Chain, parties: &HtlcParties) -> Result<()> {
+        if let Ok(ChainAddress::Evm(address)) = parse_chain_address(chain, &parties.redeemer) {
+            if address.is_zero() {
+                return Err(anyhow!("initiator_destination_address must not be the zero address"));
+            }
+        }
+        if parties.redeemer.eq_ignore_ascii_case(&parties.initiator) {
+            return Err(anyhow!("initiator_destination_address must not be the executor's address"));
+        }
+        Ok(())
+    }
+
+    /// Splits a "chain:asset" string into its trimmed, lowercased parts
     fn parse_chain_asset(chain_asset: &str) -> Result<(String, String)> {
         let parts: Vec<&str> = chain_asset.split(':').collect();
         if parts.len() != 2 {
             return Err(anyhow!("Invalid format: expected 'chain:asset', got '{}'", chain_asset));
         }
-        Ok((parts[0].to_string(), parts[1].to_string()))
+
+        let chain = parts[0].trim().to_lowercase();
+        let asset = parts[1].trim().to_lowercase();
+        if chain.is_empty() || asset.is_empty() {
+            return Err(anyhow!("Invalid format: expected 'chain:asset', got '{}'", chain_asset));
+        }
+        Ok((chain, asset))
+    }
+
+    /// Looks up the config for a normalized chain and asset, distinguishing an
+    /// unknown chain from an asset the chain doesn't support
+    fn lookup_chain_asset(&self, chain: &str, asset: &str) -> Result<(&ChainConfig, &Asset)> {
+        let chain_config = self.config.chains.get(chain)
+            .ok_or_else(|| anyhow!("Unknown chain '{}'", chain))?;
+
+        let asset_config = chain_config.assets.iter()
+            .find(|a| a.id.to_lowercase() == asset)
+            .ok_or_else(|| anyhow!("Unknown asset '{}' on chain '{}'", asset, chain))?;
+
+        Ok((chain_config, asset_config))
+    }
+    
+    /// Checks the secret hash is valid hex of the length `hash_function` produces.
+    /// The EVM HTLC contracts only hash with SHA256, so other functions are
+    /// rejected whenever either leg settles on an EVM chain.
+    fn validate_secret_hash(secret_hash: &str, hash_function: HashFunction, has_evm_leg: bool) -> Result<()> {
+        if has_evm_leg && hash_function != HashFunction::Sha256 {
+            return Err(anyhow!("Hash function {:?} is not supported on EVM chains, use Sha256", hash_function));
+        }
+
+        let secret_hash_bytes = hex::decode(secret_hash.strip_prefix("0x").unwrap_or(secret_hash))
+            .map_err(|e| anyhow!("Invalid secret hash: {}", e))?;
+        if secret_hash_bytes.len() != hash_function.digest_len() {
+            return Err(anyhow!(
+                "Secret hash must be {} bytes for {:?}, got {}",
+                hash_function.digest_len(),
+                hash_function,
+                secret_hash_bytes.len()
+            ));
+        }
+        Ok(())
     }
     
     fn generate_create_id() -> String {
@@ -335,37 +475,25 @@ impl OrderService {
 
     async fn get_bitcoin_deposit_address(
         secret_hash: &str,
+        hash_function: HashFunction,
         initiator: &str,
         redeemer: &str,
         timelock: i32,
     ) -> Result<String> {
-        let secret_hash_bytes = hex::decode(secret_hash.strip_prefix("0x").unwrap_or(secret_hash))?;
-        if secret_hash_bytes.len() != 32 {
-            return Err(anyhow!("Secret hash must be 32 bytes, got {}", secret_hash_bytes.len()));
+        let htlc_params = HTLCParams::parse(secret_hash, hash_function, initiator, redeemer, timelock)?;
+        if htlc_params.secret_hash.len() != hash_function.digest_len() {
+            return Err(anyhow!("Secret hash must be {} bytes, got {}", hash_function.digest_len(), htlc_params.secret_hash.len()));
         }
         
-        let mut secret_hash_array = [0u8; 32];
-        secret_hash_array.copy_from_slice(&secret_hash_bytes);
-        
-        let initiator_pubkey = XOnlyPublicKey::from_str(initiator)
-            .map_err(|_| anyhow!("Invalid initiator public key: {}", initiator))?;
-        let redeemer_pubkey = XOnlyPublicKey::from_str(redeemer)
-            .map_err(|_| anyhow!("Invalid redeemer public key: {}", redeemer))?;
-        
-        let htlc_params = HTLCParams {
-            secret_hash: secret_hash_array,
-            redeemer_pubkey,
-            initiator_pubkey,
-            timelock: timelock as u32,
-        };
-        
         let bitcoin_address = get_htlc_address(&htlc_params, Network::Testnet4).map_err(|e| anyhow!("Failed to generate Bitcoin HTLC address: {}", e))?;
         Ok(bitcoin_address.to_string())
     }
 
+    /// Deposit address the registry derives for an HTLC of `asset`, through
+    /// `getNativeAddress` for native assets and `getERC20Address` otherwise
     async fn get_evm_deposit_address(
         &self,
-        token: &str,
+        asset: &Asset,
         chain_identifier: &str,
         secret_hash: &str,
         initiator: &str,
@@ -373,14 +501,33 @@ impl OrderService {
         timelock: i32,
         amount: &str,
     ) -> Result<String> {
-            let token_address = Address::from_str(token).map_err(|e| anyhow!("Invalid token address: {}", e))?;
             let refund_address = Address::from_str(initiator).map_err(|e| anyhow!("Invalid redeemer address: {}", e))?;
             let redeemer_address = Address::from_str(redeemer).map_err(|e| anyhow!("Invalid redeemer address: {}", e))?;
             let registry = self.evm_registries.get(chain_identifier).ok_or_else(|| anyhow!("Registry not found for chain ID: {}", chain_identifier))?;
             let timelock = U256::from(timelock as u64);
             let amount = U256::from_str(amount).map_err(|e| anyhow!("Invalid amount: {}", e))?;
             let secret_hash_bytes = FixedBytes::from_hex(secret_hash)?;
-            let deposit_address = registry.getERC20Address(token_address, refund_address, redeemer_address, timelock, amount, secret_hash_bytes).call().await?;
+            let label = format!("Registry call on {}", chain_identifier);
+            let deposit_address = match asset.kind {
+                AssetKind::Native => retry_contract_call(&self.config.registry_retry, &label, || async move {
+                    registry.getNativeAddress(refund_address, redeemer_address, timelock, amount, secret_hash_bytes).call().await
+                })
+                .await,
+                AssetKind::Erc20 => {
+                    let token_address = Address::from_str(&asset.token_address)
+                        .map_err(|e| anyhow!("Invalid token address for {} on {}: {}", asset.id, chain_identifier, e))?;
+                    retry_contract_call(&self.config.registry_retry, &label, || async move {
+                        registry.getERC20Address(token_address, refund_address, redeemer_address, timelock, amount, secret_hash_bytes).call().await
+                    })
+                    .await
+                }
+            }
+                .map_err(|e| if is_transient(&e) {
+                    // Still failing after the configured retries
+                    ApiError::UpstreamUnavailable(format!("Registry call failed on {}: {}", chain_identifier, e))
+                } else {
+                    ApiError::Validation(format!("Registry rejected the HTLC on {}: {}", chain_identifier, e))
+                })?;
             Ok(deposit_address.to_string())
     }
 }
@@ -401,6 +548,12 @@ impl std::str::FromStr for Chain {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::SupportedPair;
+    use crate::fees::PairSpread;
+    use crate::HTLCRegistry;
+    use alloy::sol_types::SolCall;
+    use std::sync::{Arc, Mutex};
+    use bitcoin_primitives::mock_server::{MockRequest, MockServer};
 
     #[test]
     fn test_evm_swap_id_generation() {
@@ -430,4 +583,378 @@ mod tests {
 
         assert_eq!(generated_swap_id_with_prefix, expected_swap_id);
     }
+
+    #[test]
+    fn test_parse_chain_asset_trims_whitespace() {
+        let (chain, asset) = OrderService::parse_chain_asset(" bitcoin_testnet : btc ").unwrap();
+        assert_eq!(chain, "bitcoin_testnet");
+        assert_eq!(asset, "btc");
+    }
+
+    #[test]
+    fn test_parse_chain_asset_normalizes_case() {
+        let (chain, asset) = OrderService::parse_chain_asset("Bitcoin_Testnet : BTC ").unwrap();
+        assert_eq!(chain, "bitcoin_testnet");
+        assert_eq!(asset, "btc");
+    }
+
+    #[test]
+    fn test_parse_chain_asset_rejects_missing_colon() {
+        assert!(OrderService::parse_chain_asset("bitcoin_testnet").is_err());
+        assert!(OrderService::parse_chain_asset("bitcoin_testnet:").is_err());
+    }
+
+    #[test]
+    fn test_parse_chain_asset_rejects_extra_colons() {
+        assert!(OrderService::parse_chain_asset("bitcoin_testnet:btc:extra").is_err());
+    }
+
+    #[test]
+    fn test_lookup_distinguishes_unknown_chain_and_asset() {
+        let config = AppConfig::from_file("config.json").unwrap();
+        let service = OrderService::new(config, HashMap::new());
+
+        assert!(service.lookup_chain_asset("bitcoin_testnet", "btc").is_ok());
+
+        let err = service.lookup_chain_asset("dogecoin", "btc").unwrap_err();
+        assert!(err.to_string().contains("Unknown chain"));
+
+        let err = service.lookup_chain_asset("bitcoin_testnet", "doge").unwrap_err();
+        assert!(err.to_string().contains("Unknown asset"));
+    }
+
+    fn service_with_pairs(pairs: &[(&str, &str)]) -> OrderService {
+        let mut config = AppConfig::from_file("config.json").unwrap();
+        config.supported_pairs = pairs
+            .iter()
+            .map(|(from, to)| SupportedPair { from: from.to_string(), to: to.to_string() })
+            .collect();
+        OrderService::new(config, HashMap::new())
+    }
+
+    #[test]
+    fn test_supported_pair_is_allowed() {
+        let service = service_with_pairs(&[("Bitcoin_Testnet:BTC", "avalanche_testnet:usdc")]);
+        assert!(service.check_pair_supported("bitcoin_testnet:btc", "avalanche_testnet:usdc").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_of_supported_pair_is_rejected() {
+        let service = service_with_pairs(&[("bitcoin_testnet:btc", "avalanche_testnet:usdc")]);
+        let err = service.check_pair_supported("avalanche_testnet:usdc", "bitcoin_testnet:btc").unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+
+        let create_order = CreateOrder {
+            _id: None,
+            from: "avalanche_testnet:usdc".to_string(),
+            to: "bitcoin_testnet:btc".to_string(),
+            source_amount: "1000000".to_string(),
+            destination_amount: "50000".to_string(),
+            initiator_source_address: "0x5A6A32dE366b917A594342B28530d53708f2881c".to_string(),
+            initiator_destination_address: "4c77d732a1331bfcbf2acfca28ebf661ee87d2a490e269b2ebb96c153f256203".to_string(),
+            secret_hash: "a201be6510790b5b1ebab36fc5e0ee5db382f1afb7850d1444e80952c58edcd8".to_string(),
+            nonce: "1".to_string(),
+            bitcoin_optional_recipient: Some("tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy".to_string()),
+            hash_function: HashFunction::Sha256,
+            create_id: None,
+        };
+        let err = service.get_matched_order(create_order).await.unwrap_err();
+        assert!(err.to_string().contains("Orders from avalanche_testnet:usdc to bitcoin_testnet:btc are not supported"));
+    }
+
+    #[test]
+    fn test_empty_supported_pairs_allows_all() {
+        let service = service_with_pairs(&[]);
+        assert!(service.check_pair_supported("bitcoin_testnet:btc", "avalanche_testnet:usdc").is_ok());
+        assert!(service.check_pair_supported("avalanche_testnet:usdc", "bitcoin_testnet:btc").is_ok());
+    }
+
+    #[test]
+    fn test_configured_spread_reduces_destination_amount() {
+        let mut config = AppConfig::from_file("config.json").unwrap();
+        config.spreads.push(PairSpread {
+            from: "arbitrum_sepolia:usdc".to_string(),
+            to: "bitcoin_testnet:btc".to_string(),
+            bps: 50,
+        });
+        let service = OrderService::new(config, HashMap::new());
+
+        let fee = service
+            .destination_fee("arbitrum_sepolia:usdc", "bitcoin_testnet:btc", "200000", &Chain::BitcoinTestnet)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fee.fee_amount, "1000");
+        assert_eq!(fee.destination_amount, "199000");
+
+        // Unlisted pairs pass the quoted amount through
+        assert!(service
+            .destination_fee("bitcoin_testnet:btc", "arbitrum_sepolia:usdc", "200000", &Chain::ArbitrumSepolia)
+            .unwrap()
+            .is_none());
+        assert!(service
+            .destination_fee("arbitrum_sepolia:usdc", "bitcoin_testnet:btc", "500", &Chain::BitcoinTestnet)
+            .is_err());
+    }
+
+    const SHA256_SECRET_HASH: &str = "a201be6510790b5b1ebab36fc5e0ee5db382f1afb7850d1444e80952c58edcd8";
+    const HASH160_SECRET_HASH: &str = "a201be6510790b5b1ebab36fc5e0ee5db382f1af";
+    const INITIATOR_PUBKEY: &str = "727dde7d4e0726212ccbd76e6ed71f1bceb957082023c39be18cb93ff93773fa";
+    const REDEEMER_PUBKEY: &str = "4c77d732a1331bfcbf2acfca28ebf661ee87d2a490e269b2ebb96c153f256202";
+
+    #[tokio::test]
+    async fn test_sha256_order_deposit_address() {
+        OrderService::validate_secret_hash(SHA256_SECRET_HASH, HashFunction::Sha256, true).unwrap();
+        OrderService::validate_secret_hash(&format!("0x{}", SHA256_SECRET_HASH), HashFunction::Sha256, false).unwrap();
+
+        let address = OrderService::get_bitcoin_deposit_address(
+            SHA256_SECRET_HASH, HashFunction::Sha256, INITIATOR_PUBKEY, REDEEMER_PUBKEY, 12,
+        ).await.unwrap();
+        assert!(address.starts_with("tb1p"));
+    }
+
+    #[tokio::test]
+    async fn test_hash160_order_deposit_address() {
+        OrderService::validate_secret_hash(HASH160_SECRET_HASH, HashFunction::Hash160, false).unwrap();
+
+        let address = OrderService::get_bitcoin_deposit_address(
+            HASH160_SECRET_HASH, HashFunction::Hash160, INITIATOR_PUBKEY, REDEEMER_PUBKEY, 12,
+        ).await.unwrap();
+        assert!(address.starts_with("tb1p"));
+    }
+
+    #[test]
+    fn test_secret_hash_length_mismatch_is_rejected() {
+        assert!(OrderService::validate_secret_hash(HASH160_SECRET_HASH, HashFunction::Sha256, false).is_err());
+        assert!(OrderService::validate_secret_hash(SHA256_SECRET_HASH, HashFunction::Hash160, false).is_err());
+        assert!(OrderService::validate_secret_hash(HASH160_SECRET_HASH, HashFunction::Sha256d, false).is_err());
+        OrderService::validate_secret_hash(SHA256_SECRET_HASH, HashFunction::Sha256d, false).unwrap();
+        assert!(OrderService::validate_secret_hash(SHA256_SECRET_HASH, HashFunction::Sha256d, true).is_err());
+    }
+
+    #[test]
+    fn test_hash160_rejected_with_evm_leg() {
+        let err = OrderService::validate_secret_hash(HASH160_SECRET_HASH, HashFunction::Hash160, true).unwrap_err();
+        assert!(err.to_string().contains("not supported on EVM"));
+    }
+
+    #[tokio::test]
+    async fn test_hash160_bitcoin_order_matches_executor_htlc() {
+        use bitcoin_primitives::htlc::BitcoinHTLC;
+
+        let secret = [7u8; 32];
+        let secret_hash = hex::encode(HashFunction::Hash160.hash(&secret));
+        let service = OrderService::new(AppConfig::from_file("config.json").unwrap(), HashMap::new());
+        let create_order = CreateOrder {
+            _id: None,
+            from: "bitcoin_testnet:btc".to_string(),
+            to: "bitcoin_testnet:btc".to_string(),
+            source_amount: "50000".to_string(),
+            destination_amount: "50000".to_string(),
+            initiator_source_address: INITIATOR_PUBKEY.to_string(),
+            initiator_destination_address: INITIATOR_PUBKEY.to_string(),
+            secret_hash: secret_hash.clone(),
+            nonce: "1".to_string(),
+            bitcoin_optional_recipient: Some("tb1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjaq5ayy".to_string()),
+            hash_function: HashFunction::Hash160,
+            create_id: None,
+        };
+
+        let order = service.get_matched_order(create_order).await.unwrap();
+        for swap in [&order.source_swap, &order.destination_swap] {
+            assert_eq!(swap.hash_function, HashFunction::Hash160);
+            // The executor and watcher rebuild the HTLC from the stored swap
+            let htlc = BitcoinHTLC::new(
+                swap.secret_hash.clone(),
+                swap.initiator.clone(),
+                swap.redeemer.clone(),
+                swap.timelock as i64,
+                Network::Testnet4,
+            )
+            .unwrap()
+            .with_hash_function(swap.hash_function);
+            assert_eq!(swap.deposit_address.as_deref(), Some(htlc.address().unwrap().to_string().as_str()));
+            assert!(htlc.verify_secret(&hex::encode(secret)).unwrap());
+
+            let sha256_htlc = BitcoinHTLC::new(
+                swap.secret_hash.clone(),
+                swap.initiator.clone(),
+                swap.redeemer.clone(),
+                swap.timelock as i64,
+                Network::Testnet4,
+            )
+            .unwrap();
+            assert!(sha256_htlc.address().is_err());
+        }
+    }
+
+    const USER_ADDRESS: &str = "0x5A6A32dE366b917A594342B28530d53708f2881c";
+
+    fn evm_create_order(initiator_destination_address: &str) -> CreateOrder {
+        CreateOrder {
+            _id: None,
+            from: "arbitrum_sepolia:usdc".to_string(),
+            to: "avalanche_testnet:usdc".to_string(),
+            source_amount: "50000".to_string(),
+            destination_amount: "50000".to_string(),
+            initiator_source_address: USER_ADDRESS.to_string(),
+            initiator_destination_address: initiator_destination_address.to_string(),
+            secret_hash: SHA256_SECRET_HASH.to_string(),
+            nonce: "1".to_string(),
+            bitcoin_optional_recipient: None,
+            hash_function: HashFunction::Sha256,
+            create_id: None,
+        }
+    }
+
+    #[test]
+    fn test_destination_recipient_validation() {
+        let config = AppConfig::from_file("config.json").unwrap();
+        let chain_config = &config.chains["avalanche_testnet"];
+
+        let parties = OrderService::destination_parties(&evm_create_order(USER_ADDRESS), chain_config);
+        assert!(OrderService::validate_destination_recipient(&Chain::AvalancheTestnet, &parties).is_ok());
+
+        let zero = "0x0000000000000000000000000000000000000000";
+        let parties = OrderService::destination_parties(&evm_create_order(zero), chain_config);
+        assert!(OrderService::validate_destination_recipient(&Chain::AvalancheTestnet, &parties).is_err());
+
+        let executor = chain_config.executor_address.to_uppercase().replace("0X", "0x");
+        let parties = OrderService::destination_parties(&evm_create_order(&executor), chain_config);
+        assert!(OrderService::validate_destination_recipient(&Chain::AvalancheTestnet, &parties).is_err());
+    }
+
+    /// JSON-RPC node that records the calldata of every `eth_call` and answers it
+    /// with `deposit`; other methods get a placeholder quantity
+    async fn recording_node(deposit: Address, calls: Arc<Mutex<Vec<String>>>) -> String {
+        let server = MockServer::start(move |request: &MockRequest| {
+            let request: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            let result = if request["method"] == "eth_call" {
+                let tx = &request["params"][0];
+                let input = tx["input"].as_str().or(tx["data"].as_str()).unwrap_or_default();
+                calls.lock().unwrap().push(input.to_string());
+                format!("0x{}", hex::encode(deposit.into_word()))
+            } else {
+                "0x1".to_string()
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }).to_string()
+        })
+        .await;
+        server.url().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_evm_redeem_pays_destination_address() {
+        let destination_address = Address::from_str("0x29f72597ca8a21F9D925AE9527ec5639bAFD5075").unwrap();
+        let deposit = Address::from_str("0x6B1c656ad724C246049EF586Fa35D217A8db13A0").unwrap();
+        let mut config = AppConfig::from_file("config.json").unwrap();
+        let mut registries = HashMap::new();
+        let mut calls = HashMap::new();
+        for chain in ["arbitrum_sepolia", "avalanche_testnet"] {
+            let chain_calls = Arc::new(Mutex::new(Vec::new()));
+            let chain_config = config.chains.get_mut(chain).unwrap();
+            chain_config.rpc_url = recording_node(deposit, chain_calls.clone()).await.parse().unwrap();
+            registries.insert(chain.to_string(), crate::build_registry(chain_config).unwrap());
+            calls.insert(chain, chain_calls);
+        }
+        let executor_address = Address::from_str(&config.chains["avalanche_testnet"].executor_address).unwrap();
+        let service = OrderService::new(config, registries);
+
+        let order = service
+            .get_matched_order(evm_create_order(&destination_address.to_string()))
+            .await
+            .unwrap();
+        // Parties are stored lowercased for the executor's exact match
+        assert_eq!(order.destination_swap.redeemer, destination_address.to_string().to_lowercase());
+        assert_eq!(order.source_swap.initiator, USER_ADDRESS.to_lowercase());
+
+        // The destination HTLC the registry derives pays out to the user's destination address
+        let calls = calls["avalanche_testnet"].lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        let call = HTLCRegistry::getERC20AddressCall::abi_decode(&hex::decode(calls[0].trim_start_matches("0x")).unwrap()).unwrap();
+        assert_eq!(call.redeemer, destination_address);
+        assert_eq!(call.refundAddress, executor_address);
+        assert_ne!(call.redeemer, Address::from_str(USER_ADDRESS).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_native_asset_uses_native_registry_method() {
+        let deposit = Address::from_str("0x6B1c656ad724C246049EF586Fa35D217A8db13A0").unwrap();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let url = recording_node(deposit, calls.clone()).await;
+
+        let mut config = AppConfig::from_file("config.json").unwrap();
+        let chain_config = config.chains.get_mut("avalanche_testnet").unwrap();
+        chain_config.rpc_url = url.parse().unwrap();
+        let registry = crate::build_registry(chain_config).unwrap();
+        let service = OrderService::new(config, HashMap::from([("avalanche_testnet".to_string(), registry)]));
+
+        let native: Asset = serde_json::from_value(serde_json::json!({
+            "id": "avax",
+            "atomic_swap_address": "0x6B1c656ad724C246049EF586Fa35D217A8db13A0",
+            "kind": "native",
+        }))
+        .unwrap();
+        assert_eq!(native.kind, AssetKind::Native);
+        assert!(native.token_address.is_empty());
+
+        let address = service
+            .get_evm_deposit_address(
+                &native,
+                "avalanche_testnet",
+                "a201be6510790b5b1ebab36fc5e0ee5db382f1afb7850d1444e80952c58edcd8",
+                "0x5A6A32dE366b917A594342B28530d53708f2881c",
+                "0x29f72597ca8a21F9D925AE9527ec5639bAFD5075",
+                4045,
+                "50000",
+            )
+            .await
+            .unwrap();
+        assert_eq!(address, deposit.to_string());
+
+        let native_selector = format!("0x{}", hex::encode(HTLCRegistry::getNativeAddressCall::SELECTOR));
+        let erc20_selector = format!("0x{}", hex::encode(HTLCRegistry::getERC20AddressCall::SELECTOR));
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].starts_with(&native_selector), "{}", calls[0]);
+        assert!(!calls[0].starts_with(&erc20_selector));
+    }
+
+    #[tokio::test]
+    async fn test_registry_revert_is_not_retried_or_unavailable() {
+        let server = MockServer::start(|request: &MockRequest| {
+            let request: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+            if request["method"] == "eth_call" {
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": 3, "message": "execution reverted", "data": "0x" },
+                })
+            } else {
+                serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": "0x1" })
+            }
+            .to_string()
+        })
+        .await;
+
+        let mut config = AppConfig::from_file("config.json").unwrap();
+        let mut registries = HashMap::new();
+        for chain in ["arbitrum_sepolia", "avalanche_testnet"] {
+            let chain_config = config.chains.get_mut(chain).unwrap();
+            chain_config.rpc_url = server.url().parse().unwrap();
+            registries.insert(chain.to_string(), crate::build_registry(chain_config).unwrap());
+        }
+        let service = OrderService::new(config, registries);
+
+        let err = service.get_matched_order(evm_create_order(USER_ADDRESS)).await.unwrap_err();
+        let err = ApiError::from_order_error(err);
+        assert!(matches!(err, ApiError::Validation(_)), "{:?}", err);
+        assert_ne!(err.status_code(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        let eth_calls = server
+            .requests()
+            .iter()
+            .filter(|request| request.body.contains("eth_call"))
+            .count();
+        assert_eq!(eth_calls, 1);
+    }
 }
@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use mongodb::bson::{bson, doc, Bson, Document};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::primitives::Chain;
+
+/// How long `GET /stats` serves a computed result before aggregating again
+pub const STATS_TTL: Duration = Duration::from_secs(30);
+
+/// Where an order is in its lifecycle, derived from the transactions seen on its swaps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// The source HTLC has not been funded
+    Created,
+    /// The source HTLC is funded and neither leg has settled
+    Funded,
+    /// The source swap was redeemed, which happens last in a successful swap
+    Completed,
+    /// Either leg was refunded
+    Refunded,
+}
+
+/// Aggregate figures over every order in the orderbook
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub total_orders: u64,
+    pub orders_by_status: BTreeMap<OrderStatus, u64>,
+    /// Funded source amounts per "chain:asset", in the asset's smallest unit
+    pub volume_by_asset: BTreeMap<String, String>,
+    /// Mean time from source funding to source redeem over completed orders,
+    /// estimated from the blocks between them and the source chain's block time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_completion_secs: Option<u64>,
+    /// Share of settled orders that were refunded rather than completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refund_rate: Option<f64>,
+}
+
+/// Orders sharing a status and source `chain:asset`, as grouped by `stats_pipeline`
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsGroup {
+    pub status: OrderStatus,
+    pub chain: Chain,
+    pub asset: String,
+    pub orders: u64,
+    /// Funded source amount above the last `VOLUME_SPLIT_DIGITS` digits
+    pub volume_high: String,
+    /// Funded source amount in the last `VOLUME_SPLIT_DIGITS` digits
+    pub volume_low: String,
+    /// Blocks from source funding to source redeem, summed over `timed_orders`
+    pub completion_blocks: u64,
+    pub timed_orders: u64,
+}
+
+/// Amounts are decimal strings that can exceed what MongoDB sums exactly. Each is
+/// split at this many trailing digits and the halves are summed as Decimal128,
+/// which is exact up to 34 digits.
+const VOLUME_SPLIT_DIGITS: i64 = 17;
+
+/// True when `field` holds a non-empty transaction hash
+fn is_set(field: &str) -> Bson {
+    bson!({ "$gt": [{ "$strLenCP": { "$ifNull": [field, ""] } }, 0] })
+}
+
+/// `field` as a block number, or null when it is missing or not a number
+fn block(field: &str) -> Bson {
+    bson!({ "$convert": { "input": field, "to": "long", "onError": null, "onNull": null } })
+}
+
+/// Groups orders by status and source `chain:asset`, counting them and summing
+/// funded volume and completion blocks. Statuses follow `OrderStatus`.
+pub fn stats_pipeline() -> Vec<Document> {
+    let split_at = bson!({ "$max": [{ "$subtract": [{ "$strLenCP": "$amount" }, VOLUME_SPLIT_DIGITS] }, 0] });
+    vec![
+        doc! {
+            "$project": {
+                "_id": 0,
+                "chain": "$source_swap.chain",
+                "asset": { "$toLower": "$source_swap.asset" },
+                "status": {
+                    "$switch": {
+                        "branches": [
+                            {
+                                "case": { "$or": [is_set("$source_swap.refund_tx_hash"), is_set("$destination_swap.refund_tx_hash")] },
+                                "then": "refunded",
+                            },
+                            { "case": is_set("$source_swap.redeem_tx_hash"), "then": "completed" },
+                            { "case": is_set("$source_swap.initiate_tx_hash"), "then": "funded" },
+                        ],
+                        "default": "created",
+                    }
+                },
+                "amount": "$source_swap.amount",
+                "initiated_at": block("$source_swap.initiate_block_number"),
+                "redeemed_at": block("$source_swap.redeem_block_number"),
+            }
+        },
+        doc! {
+            "$project": {
+                "chain": 1,
+                "asset": 1,
+                "status": 1,
+                // Unfunded orders add no volume
+                "amount": { "$cond": [{ "$eq": ["$status", "created"] }, "0", "$amount"] },
+                "completion_blocks": {
+                    "$cond": [
+                        {
+                            "$and": [
+                                { "$eq": ["$status", "completed"] },
+                                { "$gt": ["$initiated_at", 0] },
+                                { "$gte": ["$redeemed_at", "$initiated_at"] },
+                            ]
+                        },
+                        { "$subtract": ["$redeemed_at", "$initiated_at"] },
+                        null,
+                    ]
+                },
+            }
+        },
+        doc! {
+            "$group": {
+                "_id": { "status": "$status", "chain": "$chain", "asset": "$asset" },
+                "orders": { "$sum": 1 },
+                "volume_high": {
+                    "$sum": {
+                        "$cond": [
+                            { "$gt": [split_at.clone(), 0] },
+                            { "$toDecimal": { "$substrCP": ["$amount", 0, split_at.clone()] } },
+                            { "$toDecimal": "0" },
+                        ]
+                    }
+                },
+                "volume_low": {
+                    "$sum": { "$toDecimal": { "$substrCP": ["$amount", split_at, VOLUME_SPLIT_DIGITS] } }
+                },
+                "completion_blocks": { "$sum": "$completion_blocks" },
+                "timed_orders": { "$sum": { "$cond": [{ "$eq": ["$completion_blocks", null] }, 0, 1] } },
+            }
+        },
+        doc! {
+            "$project": {
+                "_id": 0,
+                "status": "$_id.status",
+                "chain": "$_id.chain",
+                "asset": "$_id.asset",
+                "orders": 1,
+                "volume_high": { "$toString": "$volume_high" },
+                "volume_low": { "$toString": "$volume_low" },
+                "completion_blocks": 1,
+                "timed_orders": 1,
+            }
+        },
+    ]
+}
+
+impl StatsGroup {
+    fn volume(&self) -> Result<BigUint> {
+        let parse = |value: &str| {
+            BigUint::from_str(value)
+                .map_err(|_| anyhow!("Invalid volume '{}' on {}:{}", value, self.chain, self.asset))
+        };
+        Ok(parse(&self.volume_high)? * BigUint::from(10u8).pow(VOLUME_SPLIT_DIGITS as u32) + parse(&self.volume_low)?)
+    }
+}
+
+/// Totals the groups returned by `stats_pipeline`
+pub fn compute_stats(groups: &[StatsGroup]) -> Result<Stats> {
+    let mut orders_by_status = BTreeMap::new();
+    let mut volumes: BTreeMap<String, BigUint> = BTreeMap::new();
+    let mut completion_secs = 0;
+    let mut timed_orders = 0;
+
+    for group in groups {
+        *orders_by_status.entry(group.status).or_insert(0) += group.orders;
+        if group.status != OrderStatus::Created {
+            *volumes.entry(format!("{}:{}", group.chain, group.asset)).or_default() += group.volume()?;
+        }
+        completion_secs += group.completion_blocks * group.chain.block_time_ms() / 1000;
+        timed_orders += group.timed_orders;
+    }
+
+    let completed = orders_by_status.get(&OrderStatus::Completed).copied().unwrap_or(0);
+    let refunded = orders_by_status.get(&OrderStatus::Refunded).copied().unwrap_or(0);
+    let settled = completed + refunded;
+
+    Ok(Stats {
+        total_orders: orders_by_status.values().sum(),
+        orders_by_status,
+        volume_by_asset: volumes.into_iter().map(|(asset, volume)| (asset, volume.to_string())).collect(),
+        avg_completion_secs: (timed_orders > 0).then(|| completion_secs / timed_orders),
+        refund_rate: (settled > 0).then(|| refunded as f64 / settled as f64),
+    })
+}
+
+/// Last computed `Stats`, shared by every handler clone
+#[derive(Clone)]
+pub struct StatsCache {
+    ttl: Duration,
+    cached: Arc<Mutex<Option<(Instant, Stats)>>>,
+    /// Held while stats are recomputed, so concurrent misses aggregate once
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl StatsCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, cached: Arc::new(Mutex::new(None)), refreshing: Arc::new(tokio::sync::Mutex::new(())) }
+    }
+
+    /// The cached stats, unless they are older than the TTL
+    pub fn get(&self) -> Option<Stats> {
+        let cached = self.cached.lock().unwrap();
+        cached
+            .as_ref()
+            .filter(|(computed_at, _)| computed_at.elapsed() < self.ttl)
+            .map(|(_, stats)| stats.clone())
+    }
+
+    pub fn store(&self, stats: Stats) {
+        *self.cached.lock().unwrap() = Some((Instant::now(), stats));
+    }
+
+    /// The cached stats, or the result of `refresh` when they have expired. Only one
+    /// caller refreshes at a time; the others wait for and share its result.
+    pub async fn get_or_refresh<F, Fut, E>(&self, refresh: F) -> Result<Stats, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Stats, E>>,
+    {
+        if let Some(stats) = self.get() {
+            return Ok(stats);
+        }
+        let _refreshing = self.refreshing.lock().await;
+        if let Some(stats) = self.get() {
+            return Ok(stats);
+        }
+        let stats = refresh().await?;
+        self.store(stats.clone());
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn group(status: OrderStatus, chain: Chain, volume: (&str, &str), orders: u64, completion: (u64, u64)) -> StatsGroup {
+        StatsGroup {
+            status,
+            chain,
+            asset: "usdc".to_string(),
+            orders,
+            volume_high: volume.0.to_string(),
+            volume_low: volume.1.to_string(),
+            completion_blocks: completion.0,
+            timed_orders: completion.1,
+        }
+    }
+
+    #[test]
+    fn test_counts_volumes_and_rates() {
+        let groups = vec![
+            group(OrderStatus::Created, Chain::AvalancheTestnet, ("0", "0"), 1, (0, 0)),
+            group(OrderStatus::Funded, Chain::AvalancheTestnet, ("0", "2000"), 1, (0, 0)),
+            // 150 blocks at 2s
+            group(OrderStatus::Completed, Chain::AvalancheTestnet, ("0", "3000"), 1, (150, 1)),
+            // 2400 blocks at 250ms, on an amount above what Decimal128 holds
+            group(OrderStatus::Completed, Chain::ArbitrumSepolia, ("10000000000000000000000", "0"), 1, (2400, 1)),
+            group(OrderStatus::Refunded, Chain::ArbitrumSepolia, ("0", "500"), 1, (0, 0)),
+        ];
+
+        let stats = compute_stats(&groups).unwrap();
+        assert_eq!(stats.total_orders, 5);
+        assert_eq!(
+            stats.orders_by_status,
+            BTreeMap::from([
+                (OrderStatus::Created, 1),
+                (OrderStatus::Funded, 1),
+                (OrderStatus::Completed, 2),
+                (OrderStatus::Refunded, 1),
+            ])
+        );
+        assert_eq!(
+            stats.volume_by_asset,
+            BTreeMap::from([
+                ("arbitrum_sepolia:usdc".to_string(), "1000000000000000000000000000000000000500".to_string()),
+                ("avalanche_testnet:usdc".to_string(), "5000".to_string()),
+            ])
+        );
+        assert_eq!(stats.avg_completion_secs, Some(450));
+        assert_eq!(stats.refund_rate, Some(1.0 / 3.0));
+    }
+
+    #[test]
+    fn test_empty_orderbook() {
+        let stats = compute_stats(&[]).unwrap();
+        assert_eq!(stats.total_orders, 0);
+        assert!(stats.orders_by_status.is_empty());
+        assert_eq!(stats.avg_completion_secs, None);
+        assert_eq!(stats.refund_rate, None);
+    }
+
+    #[test]
+    fn test_pipeline_groups_in_the_database() {
+        let pipeline = stats_pipeline();
+        let group = pipeline.iter().find_map(|stage| stage.get_document("$group").ok()).unwrap();
+        assert_eq!(
+            group.get_document("_id").unwrap(),
+            &doc! { "status": "$status", "chain": "$chain", "asset": "$asset" }
+        );
+        for field in ["orders", "volume_high", "volume_low", "completion_blocks", "timed_orders"] {
+            assert!(group.get_document(field).unwrap().contains_key("$sum"), "{} is not summed", field);
+        }
+    }
+
+    #[test]
+    fn test_cache_expires() {
+        let stats = compute_stats(&[]).unwrap();
+        let cache = StatsCache::new(Duration::from_secs(60));
+        assert!(cache.get().is_none());
+        cache.store(stats.clone());
+        assert_eq!(cache.get(), Some(stats.clone()));
+
+        let cache = StatsCache::new(Duration::ZERO);
+        cache.store(stats);
+        assert!(cache.get().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_refresh_once() {
+        let cache = StatsCache::new(Duration::from_secs(60));
+        let refreshes = AtomicUsize::new(0);
+        let refresh = || async {
+            refreshes.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            compute_stats(&[]).map_err(|e| e.to_string())
+        };
+
+        let results = futures::future::join_all((0..5).map(|_| cache.get_or_refresh(refresh))).await;
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    }
+}
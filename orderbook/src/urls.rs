@@ -0,0 +1,73 @@
+use anyhow::Result;
+use bitcoin_primitives::urls::parse_http_url;
+use alloy::transports::http::reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub use bitcoin_primitives::urls::IndexerUrl;
+
+/// JSON-RPC endpoint of an EVM chain, validated when config is loaded
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RpcUrl(String);
+
+impl RpcUrl {
+    pub fn parse(value: &str) -> Result<Self> {
+        parse_http_url(value).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn url(&self) -> Url {
+        Url::parse(&self.0).expect("validated when parsed")
+    }
+}
+
+impl FromStr for RpcUrl {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::parse(value)
+    }
+}
+
+impl TryFrom<String> for RpcUrl {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<RpcUrl> for String {
+    fn from(url: RpcUrl) -> Self {
+        url.0
+    }
+}
+
+impl std::fmt::Display for RpcUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_slashes_are_dropped() {
+        let url = RpcUrl::parse("http://localhost:8545/").unwrap();
+        assert_eq!(url.to_string(), "http://localhost:8545");
+        assert_eq!(url.url().as_str(), "http://localhost:8545/");
+    }
+
+    #[test]
+    fn test_non_http_schemes_are_rejected() {
+        assert!(RpcUrl::parse("ftp://localhost:8545").is_err());
+        assert!(RpcUrl::parse("not a url").is_err());
+        assert!(serde_json::from_str::<RpcUrl>("\"wss://api.avax-test.network/ext/bc/C/ws\"").is_err());
+    }
+}